@@ -25,6 +25,9 @@ fn enterprise() {
         ],
         seat_pos: Vector3::new(3.3767, 0.0, 0.5515),
         elasticity: 0.3,
+        drag_coefficient: 0.8,
+        reference_area: 12.0,
+        lift_coefficient: 0.4,
     }).unwrap()).unwrap();
     
     fs::write(Path::new(&format!("{}/accessories/chair.dat", ROOT_PATH)), bincode::serialize(&PartData {
@@ -73,6 +76,9 @@ fn test() {
         attachments: Vec::new(),
         seat_pos: Vector3::zero(),
         elasticity: 0.5,
+        drag_coefficient: 1.05,
+        reference_area: 1.0,
+        lift_coefficient: 0.0,
     }).unwrap()).unwrap();
 }
 
@@ -0,0 +1,109 @@
+//! Times the physics world on scenes of scattered boxes. Run with `cargo bench -p lepton` and
+//! compare the times printed before and after a change.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use cgmath::{Vector3, Quaternion, Matrix3, SquareMatrix};
+use lepton::physics::{Object, ObjectManager, PhysicsTask, PhysicsWorld, RigidBody, Collider, Updater, FIXED_DELTA_TIME};
+
+const NUM_FRAMES: u32 = 20;
+
+/// Small deterministic generator so the scenes are the same on every run
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn vector(&mut self, scale: f64) -> Vector3<f64> {
+        Vector3::new(self.next() - 0.5, self.next() - 0.5, self.next() - 0.5) * 2.0 * scale
+    }
+}
+
+/// Boxes flying about in a cube of half width spread
+fn scattered_cubes(num: usize, spread: f64, seed: u64) -> PhysicsWorld {
+    let mut rng = Lcg(seed);
+    let mut objects = ObjectManager::new();
+    let mut world = PhysicsWorld::new().with_sleeping(false);
+    for _ in 0..num {
+        let body = RigidBody::new(rng.vector(spread), rng.vector(10.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), rng.vector(1.0))
+            .motivate(1.0, Matrix3::identity())
+            .collide(vec![Collider::cube(0.5 + rng.next())], 0.5);
+        world.add_body(objects.get_object(), body);
+    }
+    world
+}
+
+/// A grid of boxes lying on a floor, pulled down by their own fields so that they fall asleep
+fn resting_cubes(num: usize) -> PhysicsWorld {
+    let mut objects = ObjectManager::new();
+    let mut world = PhysicsWorld::new();
+    let side = (num as f64).sqrt().ceil() as usize;
+    world.add_body(objects.get_object(), RigidBody::by_pos(Vector3::new(0.0, -1.0, 0.0))
+        .collide(vec![Collider::cuboid(Vector3::new(side as f64 * 2.0, 1.0, side as f64 * 2.0))], 0.5));
+    for i in 0..num {
+        let pos = Vector3::new((i % side) as f64 * 2.0 - side as f64, 0.5, (i / side) as f64 * 2.0 - side as f64);
+        world.add_body(objects.get_object(), RigidBody::by_pos(pos)
+            .motivate(1.0, Matrix3::identity() / 6.0)
+            .field(Box::new(|_| Vector3::new(0.0, -9.8, 0.0)))
+            .collide(vec![Collider::cube(0.5)], 0.0));
+    }
+    world
+}
+
+/// Steps the world, counting the pairs its interaction is called for
+fn time_steps(world: &mut PhysicsWorld) -> (Duration, usize) {
+    let calls = Cell::new(0);
+    let start = Instant::now();
+    for _ in 0..NUM_FRAMES {
+        world.step_with(FIXED_DELTA_TIME, |tasks, p_i, p_j| count(&calls, tasks, p_i, p_j));
+    }
+    (start.elapsed() / NUM_FRAMES, calls.get() / NUM_FRAMES as usize)
+}
+
+/// Calls the interaction for every pair of bodies, as the world once did each step
+fn time_all_pairs(world: &PhysicsWorld) -> (Duration, usize) {
+    let bodies = world.bodies().collect::<Vec<_>>();
+    let calls = Cell::new(0);
+    let mut tasks = Vec::new();
+    let start = Instant::now();
+    for _ in 0..NUM_FRAMES {
+        for (i, p_i) in bodies.iter().enumerate() {
+            for p_j in &bodies[i + 1..] {
+                count(&calls, &mut tasks, *p_i, *p_j);
+            }
+        }
+    }
+    (start.elapsed() / NUM_FRAMES, calls.get() / NUM_FRAMES as usize)
+}
+
+/// An interaction which only counts its calls
+fn count(calls: &Cell<usize>, _tasks: &mut Vec<PhysicsTask>, p_i: (&Object, &RigidBody), p_j: (&Object, &RigidBody)) {
+    std::hint::black_box((p_i, p_j));
+    calls.set(calls.get() + 1);
+}
+
+fn main() {
+    // Once every box is asleep a step should cost next to nothing
+    for num in [100, 500, 2000] {
+        let mut world = resting_cubes(num);
+        let (awake_time, _) = time_steps(&mut world);
+        let mut settling = 0;
+        while world.bodies().any(|(_, body)| matches!(body.updater, Updater::Free) && !body.asleep) && settling < 600 {
+            world.step(FIXED_DELTA_TIME);
+            settling += 1;
+        }
+        let (asleep_time, _) = time_steps(&mut world);
+        println!("{} resting bodies: step {:?} awake, {:?} asleep", num, awake_time, asleep_time);
+    }
+
+    for num in [100, 500, 2000] {
+        let mut world = scattered_cubes(num, 4.0 * (num as f64).cbrt(), 2);
+        let (pairs_time, pairs) = time_all_pairs(&world);
+        let (step_time, interactions) = time_steps(&mut world);
+        println!("{} bodies: step {:?} with {} interactions, all pairs {:?} ({} pairs)",
+            num, step_time, interactions, pairs_time, pairs);
+    }
+}
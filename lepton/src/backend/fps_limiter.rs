@@ -57,7 +57,8 @@ impl FPSLimiter {
 
         self.current_frame += 1;
         if self.current_frame == REFRESH_FRAME {
-            self.counter = Instant::now();
+            // Move the origin up to this tick so no time is lost in the refresh
+            self.counter += Duration::from_micros(now_micros as u64);
             self.last_micros = 0;
             self.current_frame = 0;
         } else {
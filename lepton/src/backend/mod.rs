@@ -8,6 +8,8 @@ mod renderer;
 use winit::{event::{
     Event, WindowEvent, DeviceEvent, KeyboardInput, ElementState},
     event_loop::{EventLoop, ControlFlow}};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use std::sync::mpsc::{self, Sender, Receiver};
 use std::thread;
 
@@ -15,7 +17,7 @@ pub use fps_limiter::*;
 pub use receiver::*;
 pub use renderer::*;
 use crate::{Graphics, GraphicsData};
-use crate::physics::{Physics, PhysicsData};
+use crate::physics::{Object, Physics, PhysicsData, CollisionEvent, SensorEvent, BodyState, JointId, PhysicsWorld, PhysicsQueries, Gravity};
 
 
 pub struct Backend {
@@ -24,6 +26,16 @@ pub struct Backend {
     pub(crate) graphics_data_receiver: Option<Receiver<GraphicsData>>,
     pub(crate) physics_data_sender: Sender<PhysicsData>,
     pub(crate) physics_data_receiver: Option<Receiver<PhysicsData>>,
+    pub(crate) collision_event_sender: Sender<Vec<CollisionEvent>>,
+    collision_event_receiver: Receiver<Vec<CollisionEvent>>,
+    pub(crate) sensor_event_sender: Sender<Vec<SensorEvent>>,
+    sensor_event_receiver: Receiver<Vec<SensorEvent>>,
+    pub(crate) body_state_sender: Sender<Vec<(Object, Option<BodyState>)>>,
+    body_state_receiver: Receiver<Vec<(Object, Option<BodyState>)>>,
+    pub(crate) broken_joint_sender: Sender<Vec<JointId>>,
+    broken_joint_receiver: Receiver<Vec<JointId>>,
+    pub(crate) physics_world: Arc<RwLock<PhysicsWorld>>,
+    physics_recording: Option<PathBuf>,
 }
 
 impl Backend {
@@ -31,22 +43,59 @@ impl Backend {
         let event_loop = EventLoop::new();
         let graphics_data = mpsc::channel();
         let physics_data = mpsc::channel();
+        let collision_events = mpsc::channel();
+        let sensor_events = mpsc::channel();
+        let body_states = mpsc::channel();
+        let broken_joints = mpsc::channel();
         Backend {
             event_loop,
             graphics_data_sender: graphics_data.0,
             graphics_data_receiver: Some(graphics_data.1),
             physics_data_sender: physics_data.0,
             physics_data_receiver: Some(physics_data.1),
+            collision_event_sender: collision_events.0,
+            collision_event_receiver: collision_events.1,
+            sensor_event_sender: sensor_events.0,
+            sensor_event_receiver: sensor_events.1,
+            body_state_sender: body_states.0,
+            body_state_receiver: body_states.1,
+            broken_joint_sender: broken_joints.0,
+            broken_joint_receiver: broken_joints.1,
+            physics_world: Arc::new(RwLock::new(PhysicsWorld::new())),
+            physics_recording: None,
         }
     }
 
+    /// A handle for asking the physics world about rays and overlaps from the game thread
+    pub fn physics_queries(&self) -> PhysicsQueries {
+        PhysicsQueries::new(self.physics_world.clone())
+    }
+
+    /// Pull the bodies of the physics world together with gravity
+    pub fn set_gravity(&mut self, gravity: Gravity) {
+        self.physics_world.write().unwrap().set_gravity(Some(gravity));
+    }
+
+    /// Record the physics to a file from the moment the game starts running, so that the run can
+    /// be replayed with physics::Recording
+    pub fn record_physics<P: AsRef<Path>>(&mut self, path: P) {
+        self.physics_recording = Some(path.as_ref().to_path_buf());
+    }
+
     /// Run the main game loop. Consumes self.
     pub fn run<L: Renderer + InputReceiver>(mut self, mut graphics: Graphics, mut lepton: L) -> ! {
         let mut physics = Physics::new(&mut self, |tasks, p_i, p_j| { L::interaction(tasks, p_i, p_j) });
 
         // Add objects to graphics and physics
         graphics.object_models = lepton.load_models(&graphics);
-        physics.rigid_bodies = lepton.load_rigid_bodies();
+        for (object, body) in lepton.load_rigid_bodies() {
+            physics.add_body(object, body);
+        }
+        if let Some(path) = self.physics_recording.take() {
+            if let Err(e) = physics.record(&path) {
+                panic!("Could not record the physics to {:?}: {}", path, e);
+            }
+        }
         
         // Validate the receivers and senders
         if self.graphics_data_receiver.is_some() {
@@ -58,7 +107,8 @@ impl Backend {
 
         // Begin initialization of threads
         let mut graphics_limiter = FPSLimiter::with_limits(None, None);
-        let mut physics_limiter = FPSLimiter::with_limits(Some(60), Some(2));
+        // The physics thread never drops frames; PhysicsWorld splits the elapsed time into fixed steps
+        let mut physics_limiter = FPSLimiter::with_limits(Some(60), None);
         let mut first_mouse_motion = true;
         lepton.load_other(&graphics);
         
@@ -143,6 +193,21 @@ impl Backend {
                 | Event::RedrawRequested(_window_id) => {
                     let delta_time = graphics_limiter.tick_frame();
                     graphics.receive();
+                    for event in self.collision_event_receiver.try_iter().flatten() {
+                        lepton.on_collision(event);
+                    }
+                    for event in self.sensor_event_receiver.try_iter().flatten() {
+                        lepton.on_sensor(event);
+                    }
+                    for (object, state) in self.body_state_receiver.try_iter().flatten() {
+                        lepton.on_body_state(object, state);
+                    }
+                    for id in self.broken_joint_receiver.try_iter().flatten() {
+                        lepton.on_joint_broken(id);
+                    }
+                    for task in lepton.update_models(&graphics) {
+                        graphics.apply_model_task(task);
+                    }
                     self.physics_data_sender.send(lepton.update_physics(&graphics, delta_time)).unwrap();
                     match graphics.begin_frame() {
                         Some(data) => {
@@ -2,7 +2,7 @@ use rustc_hash::FxHashMap;
 
 use crate::{Graphics};
 use crate::model::{Model, DrawState};
-use crate::physics::{Object, RigidBody, PhysicsTask};
+use crate::physics::{Object, RigidBody, PhysicsTask, CollisionEvent, SensorEvent, BodyState, JointId};
 use crate::shader::{ShaderTrait};
 use crate::ui::UserInterfaceTrait;
 
@@ -16,6 +16,14 @@ pub enum RenderTask<'a> {
     ClearDepthBuffer,
 }
 
+/// A change to the models drawn for an object while the game runs
+pub enum ModelTask {
+    /// Draw these models for the object, replacing any it had
+    Register(Object, Vec<DrawState>),
+    /// Stop drawing the object's models
+    Unregister(Object),
+}
+
 /// A user-end trait which enables rendering and response to key presses
 pub trait Renderer: 'static {
     /// Render the scene by updating all inputs and returning the render tasks to be performed
@@ -29,9 +37,31 @@ pub trait Renderer: 'static {
     /// time.
     fn update_physics(&mut self, _graphics: &Graphics, _delta_time: f32) -> Vec<PhysicsTask>;
 
-    /// Handles any two-body iteraction during the game
+    /// Register the models of objects spawned since the last frame, and drop those of objects
+    /// which were despawned. Called every frame just before update_physics, so that a model is
+    /// ready by the time its body is first drawn.
+    fn update_models(&mut self, _graphics: &Graphics) -> Vec<ModelTask> {
+        Vec::new()
+    }
+
+    /// Handles any two-body iteraction during the game. Called each physics step for the pairs of
+    /// bodies near enough to collide; forces at a distance belong to the world's gravity.
     fn interaction(_tasks: &mut Vec<PhysicsTask>, _rb_i: (&Object, &RigidBody), _rb_j: (&Object, &RigidBody)) {}
 
+    /// Called for every collision reported by the physics thread, just before update_physics.
+    fn on_collision(&mut self, _event: CollisionEvent) {}
+
+    /// Called for every body entering or leaving a sensor, just before update_physics.
+    fn on_sensor(&mut self, _event: SensorEvent) {}
+
+    /// Called with the answer to each PhysicsTask::RequestState, just before update_physics. The
+    /// state is None if the object had no body.
+    fn on_body_state(&mut self, _object: Object, _state: Option<BodyState>) {}
+
+    /// Called for every joint which broke under its load, just before update_physics. The joint
+    /// is already gone from the world.
+    fn on_joint_broken(&mut self, _id: JointId) {}
+
     /// Called only on window resize.
     fn resize(&mut self, _graphics: &Graphics) {}
 
@@ -7,6 +7,7 @@ use winit::event_loop::EventLoop;
 use std::ffi::CString;
 use std::os::raw::{c_char, c_void};
 use std::ptr;
+use std::cell::Cell;
 use std::sync::mpsc::{Receiver, Sender};
 use rustc_hash::FxHashMap;
 use cgmath::{Vector3, Matrix3};
@@ -14,10 +15,12 @@ use cgmath::{Vector3, Matrix3};
 use primitives::*;
 pub(crate) use primitives::{DoubleBuffered, GraphicsInnerData, Deletable};
 pub use crate::backend::RenderTask;
-use crate::{backend::Backend, constants::*, model::DrawState, physics::Object, input::InputType};
+use crate::{backend::{Backend, ModelTask}, constants::*, model::DrawState, physics::Object, input::InputType};
 use debug::ValidationInfo;
 
 pub(crate) type GraphicsData = FxHashMap<Object, GraphicsInnerData>;
+/// How far the camera may stray from the render origin before the origin is moved to it
+const REBASE_DISTANCE: f64 = 1_000.0;
 
 
 pub(crate) static mut DEVICE: Option<ash::Device> = None;
@@ -83,6 +86,8 @@ pub struct Graphics {
     pub(crate) delete_sender: Sender<Deletable>,
     pub(crate) object_models: FxHashMap<Object, Vec<DrawState>>,
     last_graphics_data: FxHashMap<Object, GraphicsInnerData>,
+    /// World position which is drawn at zero, so that nearby vertices keep f32 precision
+    render_origin: Cell<Vector3<f64>>,
 
     #[cfg(target_os = "macos")]
     pub(crate) last_delta: (f64, f64),
@@ -213,6 +218,7 @@ impl Graphics {
             graphics_data_receiver,
             object_models: FxHashMap::default(),
             last_graphics_data: FxHashMap::default(),
+            render_origin: Cell::new(Vector3::new(0.0, 0.0, 0.0)),
             delete_sender,
             delete_receiver,
             delete_queue,
@@ -243,6 +249,13 @@ impl Graphics {
         };
     }
 
+    pub(crate) fn apply_model_task(&mut self, task: ModelTask) {
+        match task {
+            ModelTask::Register(object, draw_states) => { self.object_models.insert(object, draw_states); },
+            ModelTask::Unregister(object) => { self.object_models.remove(&object); },
+        };
+    }
+
     pub(crate) fn record(&mut self, buffer_index: usize, actions: Vec<RenderTask>) {
         unsafe {
             crate::get_device().reset_command_buffer(
@@ -264,15 +277,16 @@ impl Graphics {
                 RenderTask::DrawObject(o) => {
                     if let Some(draw_states) = self.object_models.get(o) {
                         if let Some(d) = self.last_graphics_data.get(o) {
+                            let push_constants = d.push_constants_from(self.render_origin.get());
                             for state in draw_states {
                                 match state {
                                     DrawState::Standard(model) => {
-                                        let bytes = crate::tools::struct_as_bytes(&d.push_constants);
+                                        let bytes = crate::tools::struct_as_bytes(&push_constants);
                                         model.render(pipeline_layout.expect("You must first load a shader"),
                                         self.command_buffers.get(buffer_index), buffer_index, Some(bytes))
                                     },
                                     DrawState::Offset(model, matrix) => {
-                                        let new_pc = d.push_constants.model * matrix;
+                                        let new_pc = push_constants.model * matrix;
                                         let bytes = crate::tools::struct_as_bytes(&new_pc);
                                         model.render(pipeline_layout.expect("You must first load a shader"),
                                         self.command_buffers.get(buffer_index), buffer_index, Some(bytes));
@@ -284,7 +298,8 @@ impl Graphics {
                 },
                 RenderTask::DrawModelWithObject(o, m) => {
                     if let Some(d) = self.last_graphics_data.get(o) {
-                        let bytes = crate::tools::struct_as_bytes(&d.push_constants);
+                        let push_constants = d.push_constants_from(self.render_origin.get());
+                        let bytes = crate::tools::struct_as_bytes(&push_constants);
                         m.render(pipeline_layout.expect("You must first load a shader"),
                             self.command_buffers.get(buffer_index), buffer_index, Some(bytes));
                     }
@@ -592,14 +607,34 @@ impl Graphics {
         self.window.set_cursor_visible(visible);
     }
 
-    pub fn get_pos(&self, object: &Object) -> Option<Vector3<f32>> {
+    /// The world position which is drawn at zero
+    pub fn get_render_origin(&self) -> Vector3<f64> {
+        self.render_origin.get()
+    }
+
+    /// Move the render origin to pos if pos has wandered more than REBASE_DISTANCE from it. The
+    /// camera calls this each frame before anything is drawn.
+    pub fn rebase(&self, pos: Vector3<f64>) {
+        let offset = pos - self.render_origin.get();
+        if offset.x * offset.x + offset.y * offset.y + offset.z * offset.z > REBASE_DISTANCE * REBASE_DISTANCE {
+            self.render_origin.set(pos);
+        }
+    }
+
+    /// A world position as it is drawn, relative to the render origin
+    pub fn to_render_space(&self, pos: Vector3<f64>) -> Vector3<f32> {
+        let offset = pos - self.render_origin.get();
+        Vector3::new(offset.x as f32, offset.y as f32, offset.z as f32)
+    }
+
+    pub fn get_pos(&self, object: &Object) -> Option<Vector3<f64>> {
         if let Some(d) = self.last_graphics_data.get(object) {
             return Some(d.pos);
         }
         None
     }
 
-    pub fn get_pos_and_rot(&self, object: &Object) -> Option<(Vector3<f32>, Matrix3<f32>)> {
+    pub fn get_pos_and_rot(&self, object: &Object) -> Option<(Vector3<f64>, Matrix3<f32>)> {
         if let Some(d) = self.last_graphics_data.get(object) {
             return Some((d.pos, Matrix3::new(
                 d.push_constants.rotation.x.x, d.push_constants.rotation.x.y, d.push_constants.rotation.x.z,
@@ -1,7 +1,7 @@
 use ash::vk;
 use std::ffi::CString;
 use std::os::raw::c_char;
-use cgmath::Vector3;
+use cgmath::{Vector3, Matrix4};
 
 const MAX_BUFFERS: usize = 8;
 
@@ -110,8 +110,21 @@ impl<T: Clone + Copy> DoubleBuffered<T> {
     }
 }
 pub(crate) struct GraphicsInnerData {
+    /// Push constants of the object at the origin, which is moved to pos when drawn
     pub push_constants: crate::shader::builtin::ObjectPushConstants,
-    pub pos: Vector3<f32>,
+    pub pos: Vector3<f64>,
+}
+
+impl GraphicsInnerData {
+    /// Push constants placing the object relative to the render origin. The offset is taken in
+    /// f64, so only the distance from the origin is rounded to f32.
+    pub(crate) fn push_constants_from(&self, origin: Vector3<f64>) -> crate::shader::builtin::ObjectPushConstants {
+        let offset = self.pos - origin;
+        crate::shader::builtin::ObjectPushConstants {
+            model: Matrix4::from_translation(Vector3::new(offset.x as f32, offset.y as f32, offset.z as f32)) * self.push_constants.model,
+            rotation: self.push_constants.rotation,
+        }
+    }
 }
 
 #[derive(Clone)]
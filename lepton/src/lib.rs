@@ -31,8 +31,8 @@ mod constants {
 pub mod prelude {
     pub use crate::{Renderer, InputReceiver,
         graphics::{Graphics},
-        backend::{Backend, RenderTask, KeyTracker, VirtualKeyCode, MouseButton},
-        physics::{Object, ObjectManager, RigidBody, PhysicsTask, Collider},
+        backend::{Backend, RenderTask, ModelTask, KeyTracker, VirtualKeyCode, MouseButton},
+        physics::{Object, ObjectManager, RigidBody, PhysicsTask, BodyState, Updater, PhysicsWorld, Collider, CollisionEvent, SensorEvent, SensorEventKind, PhysicsQueries, RayHit, Integrator, RotationIntegrator, Gravity, GravityMethod, Atmosphere, Aerodynamics, CollisionFilter, PhysicsSnapshot, Resolver},
         model::{Model, DrawState},
         shader::{self, Shader, builtin, vertex},
         input::{InputType, InputLevel, Input, TextureType, VertexType},
@@ -0,0 +1,124 @@
+use cgmath::{Vector3, InnerSpace, Zero, Rotation};
+use rustc_hash::FxHashMap;
+use serde::{Serialize, Deserialize};
+
+use super::{Object, RigidBody, Updater, Collider};
+use super::snapshot::FakeVector;
+
+/// Air around a planet, thinning exponentially with altitude. The air turns with the planet.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Atmosphere {
+    pub surface_density: f64,
+    pub scale_height: f64,
+}
+
+/// How a body is pushed by the air it moves through. Drag acts against the airflow and lift
+/// across it, both at the body's position, so the air never turns the body.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Aerodynamics {
+    pub drag_coefficient: f64,
+    /// Area which the drag and lift coefficients are measured against
+    pub reference_area: f64,
+    pub lift_coefficient: f64,
+    /// Normal of the lifting surface in the body's frame
+    #[serde(with = "FakeVector")]
+    pub lift_axis: Vector3<f64>,
+}
+
+impl Atmosphere {
+    pub fn new(surface_density: f64, scale_height: f64) -> Self {
+        Self { surface_density, scale_height }
+    }
+
+    /// Density at a height above the surface. Below the surface the air is as thick as at it.
+    pub fn density(&self, altitude: f64) -> f64 {
+        self.surface_density * (-altitude.max(0.0) / self.scale_height).exp()
+    }
+}
+
+impl Aerodynamics {
+    /// A body which only feels drag
+    pub fn new(drag_coefficient: f64, reference_area: f64) -> Self {
+        Self {
+            drag_coefficient,
+            reference_area,
+            lift_coefficient: 0.0,
+            lift_axis: Vector3::unit_z(),
+        }
+    }
+
+    /// Add lift like a flat plate with the given normal, which is greatest when the air meets the
+    /// plate at 45 degrees and vanishes edge on or face on
+    pub fn lift(mut self, lift_coefficient: f64, lift_axis: Vector3<f64>) -> Self {
+        self.lift_coefficient = lift_coefficient;
+        self.lift_axis = lift_axis.normalize();
+        self
+    }
+
+    /// The change in velocity over delta_time for a body of the given mass moving at airflow
+    /// relative to air of the given density, with the body's lift axis in world coordinates.
+    /// Quadratic drag is integrated exactly, so however thick the air it can only slow the body
+    /// towards the air's speed and never push it back.
+    fn velocity_change(&self, airflow: Vector3<f64>, density: f64, lift_axis: Vector3<f64>, mass: f64, delta_time: f64) -> Vector3<f64> {
+        let speed = airflow.magnitude();
+        if speed == 0.0 {
+            return Vector3::zero();
+        }
+        let pressure = 0.5 * density * speed * speed * self.reference_area;
+        let drag_rate = pressure * self.drag_coefficient / (mass * speed) * delta_time;
+        let drag = -airflow * (drag_rate / (1.0 + drag_rate));
+
+        let direction = airflow / speed;
+        let across = lift_axis - direction * lift_axis.dot(direction);
+        let lift = if across.magnitude2() > 0.0 {
+            // sin 2α for the angle of attack α, which is negative when the air meets the plate
+            // from above and pushes it down
+            let sin_2a = -2.0 * lift_axis.dot(direction) * across.magnitude();
+            across.normalize() * (pressure * self.lift_coefficient * sin_2a / mass * delta_time)
+        } else {
+            Vector3::zero()
+        };
+        drag + lift
+    }
+}
+
+/// Height of a point above a body's planet collider, or above its position if it has none
+fn altitude(planet: &RigidBody, point: Vector3<f64>) -> f64 {
+    let local = planet.orientation.invert() * (point - planet.pos);
+    for collider in &planet.colliders {
+        if let Collider::Radial { func, .. } = collider {
+            return func(local);
+        }
+    }
+    local.magnitude()
+}
+
+/// The impulse which every atmosphere gives each awake free body with aerodynamics over
+/// delta_time
+pub(crate) fn air_impulses(bodies: &FxHashMap<Object, RigidBody>, delta_time: f64) -> Vec<(Object, Vector3<f64>)> {
+    let flying = bodies.iter()
+        .filter(|(_, body)| matches!(body.updater, Updater::Free) && !body.asleep)
+        .filter_map(|(object, body)| body.aerodynamics.map(|aerodynamics| (object, body, aerodynamics)))
+        .collect::<Vec<_>>();
+    if flying.is_empty() {
+        return Vec::new();
+    }
+    let mut planets = bodies.iter()
+        .filter_map(|(object, body)| body.atmosphere.map(|atmosphere| (*object, body, atmosphere)))
+        .collect::<Vec<_>>();
+    planets.sort_unstable_by_key(|(object, _, _)| *object);
+    flying.into_iter()
+        .map(|(object, body, aerodynamics)| {
+            let mut impulse = Vector3::zero();
+            for (planet_object, planet, atmosphere) in &planets {
+                if planet_object == object {
+                    continue;
+                }
+                let density = atmosphere.density(altitude(planet, body.pos));
+                let airflow = body.point_velocity(Vector3::zero()) - planet.point_velocity(body.pos - planet.pos);
+                impulse += aerodynamics.velocity_change(airflow, density, body.orientation * aerodynamics.lift_axis, body.mass, delta_time) * body.mass;
+            }
+            (*object, impulse)
+        })
+        .collect()
+}
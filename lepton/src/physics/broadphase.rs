@@ -0,0 +1,129 @@
+use cgmath::Vector3;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::{Object, RigidBody};
+
+/// An axis-aligned bounding box in world coordinates
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Aabb {
+    pub min: Vector3<f64>,
+    pub max: Vector3<f64>,
+}
+
+impl Aabb {
+    /// The box which contains the body over the whole step, assuming it moves with its current
+    /// velocity. Bodies without colliders have no box.
+    pub fn swept(body: &RigidBody, delta_time: f64) -> Option<Self> {
+        if body.colliders.is_empty() {
+            return None;
+        }
+        let radius = body.bounding_radius();
+        let start = body.pos;
+        let end = body.pos + body.vel * delta_time;
+        Some(Self {
+            min: Vector3::new(start.x.min(end.x) - radius, start.y.min(end.y) - radius, start.z.min(end.z) - radius),
+            max: Vector3::new(start.x.max(end.x) + radius, start.y.max(end.y) + radius, start.z.max(end.z) + radius),
+        })
+    }
+
+    pub fn overlaps(&self, o: &Aabb) -> bool {
+        self.min.x <= o.max.x && o.min.x <= self.max.x &&
+        self.min.y <= o.max.y && o.min.y <= self.max.y &&
+        self.min.z <= o.max.z && o.min.z <= self.max.z
+    }
+}
+
+/// Sweep-and-prune broadphase along the x axis. The sorted list is kept between frames, so the
+/// insertion sort is nearly linear when the bodies move coherently.
+pub(crate) struct SweepAndPrune {
+    entries: Vec<Entry>,
+}
+
+/// A body's box, and what the body was doing when it was last looked at
+struct Entry {
+    object: Object,
+    aabb: Aabb,
+    /// Whether the body could move, so that it must be tested against the others
+    active: bool,
+    /// Whether the body was lying asleep, so that its box is already where it rests
+    resting: bool,
+}
+
+impl Entry {
+    fn new(object: Object, aabb: Aabb, body: &RigidBody) -> Self {
+        Self { object, aabb, active: body.is_active(), resting: body.asleep && body.carrier.is_none() }
+    }
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Returns the pairs of bodies whose swept boxes overlap and of which at least one can move,
+    /// leaving out the excluded pairs and those whose collision filters keep them apart. Each pair
+    /// is ordered so that the lower object comes first, as are the excluded pairs, and the list is
+    /// sorted so the result does not depend on the hash map's iteration order.
+    pub fn candidate_pairs(&mut self, bodies: &FxHashMap<Object, RigidBody>, delta_time: f64, excluded: &FxHashSet<(Object, Object)>) -> Vec<(Object, Object)> {
+        // Refresh the boxes of the bodies we already track, in their old order. A body resting
+        // asleep keeps its box from the first step it lay still, since it hasn't moved since.
+        let mut seen = FxHashSet::default();
+        self.entries.retain_mut(|entry| {
+            let body = match bodies.get(&entry.object) {
+                Some(body) => body,
+                None => return false,
+            };
+            let resting = body.asleep && body.carrier.is_none();
+            if !(resting && entry.resting) {
+                match Aabb::swept(body, delta_time) {
+                    Some(aabb) => *entry = Entry::new(entry.object, aabb, body),
+                    None => return false,
+                }
+            }
+            entry.active = body.is_active();
+            seen.insert(entry.object);
+            true
+        });
+
+        // Add the new bodies
+        let mut new_entries = bodies.iter()
+            .filter(|(object, _)| !seen.contains(*object))
+            .filter_map(|(object, body)| Aabb::swept(body, delta_time).map(|aabb| Entry::new(*object, aabb, body)))
+            .collect::<Vec<_>>();
+        new_entries.sort_by_key(|entry| entry.object);
+        self.entries.append(&mut new_entries);
+
+        // With nothing able to move there is nothing to test, and the list can be sorted later
+        if !self.entries.iter().any(|entry| entry.active) {
+            return Vec::new();
+        }
+
+        // Insertion sort on the lower x bound
+        for i in 1..self.entries.len() {
+            let mut j = i;
+            while j > 0 && self.entries[j - 1].aabb.min.x > self.entries[j].aabb.min.x {
+                self.entries.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+
+        // Sweep
+        let mut pairs = Vec::new();
+        for (i, e_i) in self.entries.iter().enumerate() {
+            for e_j in &self.entries[i + 1..] {
+                if e_j.aabb.min.x > e_i.aabb.max.x {
+                    break;
+                }
+                if !(e_i.active || e_j.active) || !e_i.aabb.overlaps(&e_j.aabb) {
+                    continue;
+                }
+                let pair = (e_i.object.min(e_j.object), e_i.object.max(e_j.object));
+                if !excluded.contains(&pair) && bodies[&e_i.object].collision_filter.allows(&bodies[&e_j.object].collision_filter) {
+                    pairs.push(pair);
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+}
@@ -0,0 +1,97 @@
+// Expanding Polytope Algorithm: http://allenchou.net/2013/12/game-physics-contact-generation-epa/
+use cgmath::{Vector3, InnerSpace};
+
+use super::Contact;
+use super::primitives::{Vertex, barycentric};
+
+const EPA_TOL: f64 = 1e-9;
+const MAX_ITERATIONS: usize = 64;
+
+struct Face {
+    indices: [usize; 3],
+    normal: Vector3<f64>,
+    distance: f64,
+}
+
+impl Face {
+    /// A face wound anticlockwise when seen from outside. Degenerate faces give None.
+    fn new(vertices: &[Vertex], indices: [usize; 3]) -> Option<Self> {
+        let a = vertices[indices[0]].0;
+        let normal = (vertices[indices[1]].0 - a).cross(vertices[indices[2]].0 - a);
+        if normal.magnitude2() == 0.0 {
+            return None;
+        }
+        let normal = normal.normalize();
+        Some(Self { indices, normal, distance: normal.dot(a) })
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.indices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Expand a tetrahedron of the Minkowski difference which contains the origin until it finds the
+/// face of the difference closest to the origin. That face's normal and distance are the contact
+/// normal and penetration depth.
+pub(super) fn epa<F>(tetrahedron: [Vertex; 4], support: &F) -> Option<Contact>
+where F: Fn(Vector3<f64>) -> Vertex {
+    let mut vertices = tetrahedron.to_vec();
+    let centroid = vertices.iter().map(|v| v.0).sum::<Vector3<f64>>() / 4.0;
+    let mut faces = Vec::new();
+    for [a, b, c] in [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]] {
+        let mut face = Face::new(&vertices, [a, b, c])?;
+        if face.normal.dot(vertices[a].0 - centroid) < 0.0 {
+            face = Face::new(&vertices, [a, c, b])?;
+        }
+        faces.push(face);
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let closest = closest_face(&faces);
+        let new_vertex = support(closest.normal);
+        if new_vertex.0.dot(closest.normal) - closest.distance < EPA_TOL * (1.0 + closest.distance.abs()) {
+            break;
+        }
+
+        // Remove every face the new vertex can see and patch the hole with faces through it
+        let new_index = vertices.len();
+        vertices.push(new_vertex);
+        let mut horizon: Vec<(usize, usize)> = Vec::new();
+        faces.retain(|face| {
+            if face.normal.dot(new_vertex.0 - vertices[face.indices[0]].0) <= 0.0 {
+                return true;
+            }
+            for (a, b) in face.edges() {
+                match horizon.iter().position(|edge| *edge == (b, a)) {
+                    Some(i) => { horizon.swap_remove(i); },
+                    None => horizon.push((a, b)),
+                }
+            }
+            false
+        });
+        for (a, b) in horizon {
+            if let Some(face) = Face::new(&vertices, [a, b, new_index]) {
+                faces.push(face);
+            }
+        }
+        if faces.is_empty() {
+            return None;
+        }
+    }
+
+    // Find the closest point on the face to the origin, and the points on each collider there
+    let face = closest_face(&faces);
+    let depth = face.distance.max(0.0);
+    let projection = face.normal * face.distance;
+    let corners = face.indices.map(|i| vertices[i]);
+    let weights = barycentric(&corners.map(|v| v.0 - projection))
+        .unwrap_or_else(|| vec![1.0 / 3.0; 3]);
+    let point_a = corners.iter().zip(weights.iter()).map(|(v, w)| v.1 * *w).sum();
+    let point_b = corners.iter().zip(weights.iter()).map(|(v, w)| v.2 * *w).sum();
+    Some(Contact { normal: face.normal, depth, point_a, point_b })
+}
+
+fn closest_face(faces: &[Face]) -> &Face {
+    faces.iter().min_by(|a, b| a.distance.total_cmp(&b.distance)).unwrap()
+}
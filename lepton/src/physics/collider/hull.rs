@@ -0,0 +1,123 @@
+// Incremental convex hull: each point outside the hull so far replaces the faces it can see with
+// a fan of faces joining it to their horizon.
+use cgmath::{Vector3, InnerSpace};
+
+/// Points closer to a face than this fraction of the cloud's size count as lying on it
+const HULL_TOL: f64 = 1e-9;
+
+/// The convex hull of a cloud of points. Faces index into vertices and are wound anticlockwise
+/// when seen from outside. Each vertex's place in the original cloud is kept in indices.
+pub(super) struct Hull {
+    pub vertices: Vec<Vector3<f64>>,
+    pub indices: Vec<usize>,
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl Hull {
+    /// None is returned if the points are all on one plane, so that they enclose no volume
+    pub fn new(points: &[Vector3<f64>]) -> Option<Self> {
+        let scale = points.iter().map(|p| p.x.abs().max(p.y.abs()).max(p.z.abs())).fold(0.0, f64::max);
+        let tol = HULL_TOL * scale.max(1.0);
+        let [a, b, c, d] = initial_tetrahedron(points, tol)?;
+
+        let centroid = (points[a] + points[b] + points[c] + points[d]) / 4.0;
+        let mut faces = Vec::new();
+        for [i, j, k] in [[a, b, c], [a, d, b], [a, c, d], [b, d, c]] {
+            if normal(points, [i, j, k]).dot(points[i] - centroid) < 0.0 {
+                faces.push([i, k, j]);
+            } else {
+                faces.push([i, j, k]);
+            }
+        }
+
+        for (p, point) in points.iter().enumerate() {
+            let visible = |face: &[usize; 3]| {
+                let n = normal(points, *face);
+                n.dot(point - points[face[0]]) > tol * n.magnitude()
+            };
+            if !faces.iter().any(visible) {
+                continue;
+            }
+            let mut horizon: Vec<(usize, usize)> = Vec::new();
+            faces.retain(|face| {
+                if !visible(face) {
+                    return true;
+                }
+                for (i, j) in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                    match horizon.iter().position(|edge| *edge == (j, i)) {
+                        Some(e) => { horizon.swap_remove(e); },
+                        None => horizon.push((i, j)),
+                    }
+                }
+                false
+            });
+            faces.extend(horizon.into_iter().map(|(i, j)| [i, j, p]));
+        }
+
+        // Keep only the points the faces use, in their original order
+        let mut used = points.iter().map(|_| None).collect::<Vec<Option<usize>>>();
+        for face in &faces {
+            for p in face {
+                used[*p] = Some(0);
+            }
+        }
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (p, point) in points.iter().enumerate() {
+            if used[p].is_some() {
+                used[p] = Some(vertices.len());
+                vertices.push(*point);
+                indices.push(p);
+            }
+        }
+        let faces = faces.into_iter().map(|face| face.map(|p| used[p].unwrap())).collect();
+        Some(Self { vertices, indices, faces })
+    }
+
+    /// The neighbours of each point of the original cloud along the hull's edges. Points inside
+    /// the hull have none.
+    pub fn adjacency(&self, num_points: usize) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![Vec::new(); num_points];
+        for face in &self.faces {
+            for (i, j) in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                // Each edge is shared by two faces, which run along it in opposite directions
+                adjacency[self.indices[i]].push(self.indices[j]);
+            }
+        }
+        for neighbours in &mut adjacency {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+        adjacency
+    }
+}
+
+/// Unnormalized outward normal of a face
+fn normal(points: &[Vector3<f64>], [a, b, c]: [usize; 3]) -> Vector3<f64> {
+    (points[b] - points[a]).cross(points[c] - points[a])
+}
+
+/// Four points of the cloud which are spread as far apart as can be found quickly
+fn initial_tetrahedron(points: &[Vector3<f64>], tol: f64) -> Option<[usize; 4]> {
+    let furthest = |distance: &dyn Fn(Vector3<f64>) -> f64| {
+        points.iter().enumerate()
+            .map(|(i, p)| (i, distance(*p)))
+            .max_by(|x, y| x.1.total_cmp(&y.1))
+    };
+    let a = furthest(&|p| -p.x)?.0;
+    let (b, length) = furthest(&|p| (p - points[a]).magnitude())?;
+    if length <= tol {
+        return None;
+    }
+    let line = (points[b] - points[a]) / length;
+    let (c, width) = furthest(&|p| (p - points[a] - line * line.dot(p - points[a])).magnitude())?;
+    if width <= tol {
+        return None;
+    }
+    let plane = line.cross(points[c] - points[a]).normalize();
+    let (d, height) = furthest(&|p| plane.dot(p - points[a]).abs())?;
+    if height <= tol {
+        return None;
+    }
+    Some([a, b, c, d])
+}
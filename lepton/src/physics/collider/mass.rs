@@ -0,0 +1,111 @@
+use cgmath::{Vector3, Matrix, Matrix3, SquareMatrix, InnerSpace, Zero};
+use std::f64::consts::PI;
+
+use super::Collider;
+use super::hull::Hull;
+
+impl Collider {
+    /// Volume enclosed by the collider. Radial colliders have no volume.
+    pub fn volume(&self) -> f64 {
+        self.mass_properties().0
+    }
+
+    /// Center of the collider's volume, in the body's frame
+    pub fn center_of_mass(&self) -> Vector3<f64> {
+        self.mass_properties().1
+    }
+
+    /// Moment of inertia about the center of mass of the collider filled at the given density, in
+    /// the body's frame
+    pub fn inertia(&self, density: f64) -> Matrix3<f64> {
+        self.mass_properties().2 * density
+    }
+
+    /// Volume, center of mass and the moment of inertia about it at unit density
+    fn mass_properties(&self) -> (f64, Vector3<f64>, Matrix3<f64>) {
+        match self {
+            Collider::Cube { length } => box_properties(Vector3::new(*length, *length, *length)),
+            Collider::Box { half_extents } => box_properties(*half_extents),
+            Collider::Sphere { radius } => {
+                let volume = 4.0 / 3.0 * PI * radius.powi(3);
+                let moment = 0.4 * volume * radius * radius;
+                (volume, Vector3::zero(), diagonal(moment, moment, moment))
+            },
+            Collider::Capsule { half_length, radius } => {
+                let (h, r) = (*half_length, *radius);
+                let cylinder = 2.0 * PI * r * r * h;
+                let sphere = 4.0 / 3.0 * PI * r.powi(3);
+                // Each hemisphere's center of mass is 3r/8 from its flat face
+                let axial = cylinder * r * r / 2.0 + sphere * 0.4 * r * r;
+                let transverse = cylinder * (r * r / 4.0 + h * h / 3.0)
+                    + sphere * (83.0 / 320.0 * r * r + (h + 3.0 * r / 8.0).powi(2));
+                (cylinder + sphere, Vector3::zero(), diagonal(transverse, transverse, axial))
+            },
+            Collider::Polyhedron { vertices, offset, .. } => {
+                let hull = match Hull::new(vertices) {
+                    Some(hull) => hull,
+                    None => return (0.0, *offset, Matrix3::zero()),
+                };
+                let (volume, center, inertia) = hull_properties(&hull);
+                (volume, offset + center, inertia)
+            },
+            Collider::Compound { children, .. } => {
+                let parts = children.iter().map(|(translation, rotation, child)| {
+                    let (volume, center, inertia) = child.mass_properties();
+                    let rotation = Matrix3::from(*rotation);
+                    (volume, translation + rotation * center, rotation * inertia * rotation.transpose())
+                }).collect::<Vec<_>>();
+                let volume = parts.iter().map(|p| p.0).sum::<f64>();
+                if volume == 0.0 {
+                    return (0.0, Vector3::zero(), Matrix3::zero());
+                }
+                let center = parts.iter().map(|p| p.1 * p.0).sum::<Vector3<f64>>() / volume;
+                let inertia = parts.iter()
+                    .map(|(v, c, i)| i + shifted_inertia(*v, c - center))
+                    .fold(Matrix3::zero(), |a, b| a + b);
+                (volume, center, inertia)
+            },
+            Collider::Radial { .. } => panic!("Radial colliders have no mass properties"),
+        }
+    }
+}
+
+/// Moment of inertia of a point mass at displacement d, to add when moving the axis by d
+pub(crate) fn shifted_inertia(mass: f64, d: Vector3<f64>) -> Matrix3<f64> {
+    diagonal(1.0, 1.0, 1.0) * (mass * d.magnitude2()) - outer(d, d) * mass
+}
+
+fn box_properties(half_extents: Vector3<f64>) -> (f64, Vector3<f64>, Matrix3<f64>) {
+    let (a, b, c) = (half_extents.x, half_extents.y, half_extents.z);
+    let volume = 8.0 * a * b * c;
+    (volume, Vector3::zero(), diagonal(b * b + c * c, a * a + c * c, a * a + b * b) * (volume / 3.0))
+}
+
+/// Integrate over tetrahedra joining the origin to each face. The second moment of a tetrahedron
+/// with one corner at the origin is V/20 (sum of v vᵀ over the corners + s sᵀ), s being the sum
+/// of the corners.
+fn hull_properties(hull: &Hull) -> (f64, Vector3<f64>, Matrix3<f64>) {
+    let mut volume = 0.0;
+    let mut moment = Vector3::zero();
+    let mut second = Matrix3::zero();
+    for [i, j, k] in &hull.faces {
+        let (a, b, c) = (hull.vertices[*i], hull.vertices[*j], hull.vertices[*k]);
+        let v = a.dot(b.cross(c)) / 6.0;
+        let s = a + b + c;
+        volume += v;
+        moment += s * (v / 4.0);
+        second += (outer(a, a) + outer(b, b) + outer(c, c) + outer(s, s)) * (v / 20.0);
+    }
+    let center = moment / volume;
+    let second = second - outer(center, center) * volume;
+    let trace = second.x.x + second.y.y + second.z.z;
+    (volume, center, diagonal(trace, trace, trace) - second)
+}
+
+fn diagonal(x: f64, y: f64, z: f64) -> Matrix3<f64> {
+    Matrix3::from_diagonal(Vector3::new(x, y, z))
+}
+
+fn outer(a: Vector3<f64>, b: Vector3<f64>) -> Matrix3<f64> {
+    Matrix3::from_cols(a * b.x, a * b.y, a * b.z)
+}
@@ -1,19 +1,73 @@
 // GKJ algorithm: http://realtimecollisiondetection.net/pubs/SIGGRAPH04_Ericson_GJK_notes.pdf
 mod primitives;
+mod epa;
+mod query;
+mod hull;
+mod mass;
+
+pub(crate) use mass::shifted_inertia;
 
 use primitives::*;
-pub use primitives::CollisionData;
 
-use cgmath::{Vector3, Quaternion, InnerSpace, Zero, Rotation};
-use anyhow::{Result, anyhow};
+use cgmath::{Vector3, Quaternion, Matrix3, Matrix4, InnerSpace, Zero, Rotation, Rotation3, Rad};
+use serde::{Serialize, Deserialize};
 
 const EPSILON: f64 = 1e-10;
-const MAX_ITERATIONS: u32 = 20;
+const MAX_ITERATIONS: u32 = 64;
+/// How far colliders are tilted to look for extra contact points
+const PERTURBATION_ANGLE: f64 = 0.05;
+/// Extra contact points this far outside the other collider are still kept, so that a face which
+/// has only just touched down reports all of its corners
+const CONTACT_SLOP: f64 = 1e-3;
+
+/// Where two colliders touch, in world coordinates. The normal points out of the first collider
+/// into the second. Point a is the point of the first collider deepest inside the second, and
+/// point b the reverse, so they are depth apart along the normal.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub normal: Vector3<f64>,
+    pub depth: f64,
+    pub point_a: Vector3<f64>,
+    pub point_b: Vector3<f64>,
+}
+
+impl Contact {
+    /// The same contact seen from the second collider
+    pub(crate) fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            depth: self.depth,
+            point_a: self.point_b,
+            point_b: self.point_a,
+        }
+    }
+}
+
+/// Which piece of a body something touched: the index of the collider in the body's list, and
+/// the index of the child if that collider is a compound
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ColliderPart {
+    pub collider: usize,
+    pub child: Option<usize>,
+}
+
+/// How far a point in a radial collider's frame lies above its surface, negative below it
+pub type RadialFunction = Box<dyn 'static + Send + Sync + Fn(Vector3<f64>) -> f64>;
 
 pub enum Collider {
     Cube{length: f64},
-    Radial{func: Box<dyn 'static + Send + Sync + Fn(Vector3<f64>) -> f64>, length: f64 },
-    Polyhedron{vertices: Vec<Vector3<f64>>, length: f64, offset: Vector3<f64>}
+    Radial{func: RadialFunction, length: f64 },
+    /// Vertices of a convex shape. Those on the hull list their neighbours along its edges in
+    /// adjacency, and support lookups climb from start towards the furthest one. Flat meshes
+    /// have no adjacency and are scanned instead.
+    Polyhedron{vertices: Vec<Vector3<f64>>, length: f64, offset: Vector3<f64>, adjacency: Vec<Vec<usize>>, start: usize},
+    Sphere{radius: f64},
+    /// A segment along the z axis from -half_length to half_length, swept by a sphere
+    Capsule{half_length: f64, radius: f64},
+    Box{half_extents: Vector3<f64>},
+    /// Colliders held together, each placed in the body's frame by a translation and a rotation.
+    /// The bounding sphere of all of them is cached in length and offset.
+    Compound{children: Vec<(Vector3<f64>, Quaternion<f64>, Collider)>, length: f64, offset: Vector3<f64>},
 }
 
 impl Collider {
@@ -23,7 +77,7 @@ impl Collider {
     }
     
     /// A planetary collider function. Pass in the value function and the length
-    pub fn planet(func: Box<dyn 'static + Send + Sync + Fn(Vector3<f64>) -> f64>, length: f64) -> Self {
+    pub fn planet(func: RadialFunction, length: f64) -> Self {
         Collider::Radial { func, length }
     }
 
@@ -32,10 +86,82 @@ impl Collider {
         let offset = vertices.iter().map(|v| v).sum::<Vector3<f64>>() / vertices.len() as f64;
         let vertices = vertices.into_iter().map(|v| v - offset).collect::<Vec<Vector3<f64>>>();
         let length = vertices.iter().map(|v| v.magnitude2()).max_by(|a, b| a.total_cmp(b)).unwrap().sqrt();
-        Collider::Polyhedron { vertices, length, offset }
+        let (adjacency, start) = match hull::Hull::new(&vertices) {
+            Some(hull) => (hull.adjacency(vertices.len()), hull.indices[0]),
+            None => (Vec::new(), 0),
+        };
+        Collider::Polyhedron { vertices, length, offset, adjacency, start }
+    }
+
+    /// Polyhedron of the convex hull of the points. Points inside the hull are dropped, so that
+    /// support lookups don't need to visit them. Flat clouds are kept whole.
+    pub fn convex_hull(points: Vec<Vector3<f64>>) -> Self {
+        match hull::Hull::new(&points) {
+            Some(hull) => Collider::polyhedron(hull.vertices),
+            None => Collider::polyhedron(points),
+        }
+    }
+
+    pub fn sphere(radius: f64) -> Self {
+        Collider::Sphere { radius }
+    }
+
+    /// Capsule lying along the z axis, with half_length between the centers of its end caps
+    pub fn capsule(half_length: f64, radius: f64) -> Self {
+        Collider::Capsule { half_length, radius }
+    }
+
+    /// Box with the given half widths along x, y and z
+    pub fn cuboid(half_extents: Vector3<f64>) -> Self {
+        Collider::Box { half_extents }
+    }
+
+    /// Several colliders acting as one, such as a ship's hull and the parts attached to it. Each
+    /// child is placed by a rigid transform, like the matrix of an attached part. Compounds can't
+    /// be nested.
+    pub fn compound(children: Vec<(Matrix4<f64>, Collider)>) -> Self {
+        let children = children.into_iter().map(|(matrix, collider)| {
+            if let Collider::Compound{..} = collider {
+                panic!("Compound colliders cannot contain other compounds");
+            }
+            let rotation = Matrix3::from_cols(matrix.x.truncate(), matrix.y.truncate(), matrix.z.truncate());
+            (matrix.w.truncate(), Quaternion::from(rotation).normalize(), collider)
+        }).collect::<Vec<_>>();
+
+        let centers = children.iter()
+            .map(|(translation, rotation, c)| translation + rotation * c.offset())
+            .collect::<Vec<_>>();
+        let offset = if centers.is_empty() {
+            Vector3::zero()
+        } else {
+            centers.iter().sum::<Vector3<f64>>() / centers.len() as f64
+        };
+        let length = centers.iter().zip(children.iter())
+            .map(|(center, (_, _, c))| (center - offset).magnitude() + c.radius())
+            .fold(0.0, f64::max);
+        Collider::Compound { children, length, offset }
     }
 }
 
+/// Every collider in the list which is not a compound, with the part of the body it is and its
+/// position and orientation when the body is at pos with the given orientation
+pub(crate) fn leaves(colliders: &[Collider], pos: Vector3<f64>, orientation: Quaternion<f64>)
+    -> Vec<(ColliderPart, &Collider, Vector3<f64>, Quaternion<f64>)> {
+    let mut output = Vec::with_capacity(colliders.len());
+    for (i, collider) in colliders.iter().enumerate() {
+        match collider {
+            Collider::Compound { children, .. } => {
+                for (j, (translation, rotation, child)) in children.iter().enumerate() {
+                    output.push((ColliderPart { collider: i, child: Some(j) }, child,
+                        pos + orientation * translation, orientation * rotation));
+                }
+            },
+            _ => output.push((ColliderPart { collider: i, child: None }, collider, pos, orientation)),
+        }
+    }
+    output
+}
+
 impl Collider {
     pub(super) fn support(&self, dir: Vector3<f64>) -> Vector3<f64> {
         match self {
@@ -70,18 +196,36 @@ impl Collider {
                     }
                 }
             },
-            Collider::Polyhedron { vertices, .. } => {
-                //// Later, implement a binary tree?
-                let mut max_dot = 0.0;
-                let mut max_v = None;
-                for v in vertices {
-                    let dot = v.dot(dir);
-                    if dot > max_dot || max_v.is_none() {
-                        max_dot = dot;
-                        max_v = Some(v);
+            Collider::Box{half_extents} => Vector3::new(
+                half_extents.x.copysign(dir.x),
+                half_extents.y.copysign(dir.y),
+                half_extents.z.copysign(dir.z),
+            ),
+            Collider::Sphere{radius} => sphere_support(dir, *radius),
+            Collider::Capsule{half_length, radius} => {
+                Vector3::new(0.0, 0.0, half_length.copysign(dir.z)) + sphere_support(dir, *radius)
+            },
+            Collider::Polyhedron { vertices, adjacency, start, .. } => {
+                if adjacency.is_empty() {
+                    return scan_support(vertices, dir);
+                }
+                // On a convex hull, a vertex no neighbour of which reaches further is the furthest
+                let mut current = *start;
+                let mut max_dot = vertices[current].dot(dir);
+                loop {
+                    let mut next = current;
+                    for neighbour in &adjacency[current] {
+                        let dot = vertices[*neighbour].dot(dir);
+                        if dot > max_dot {
+                            max_dot = dot;
+                            next = *neighbour;
+                        }
                     }
+                    if next == current {
+                        break vertices[current];
+                    }
+                    current = next;
                 }
-                *max_v.unwrap()
             },
             _ => panic!("Collider not provided with a support function")
         }
@@ -89,9 +233,13 @@ impl Collider {
 
     pub(super) fn radius(&self) -> f64 {
         match self {
-            Collider::Cube{length} =>  *length * 2.0f64.sqrt(),
+            Collider::Cube{length} =>  *length * 3.0f64.sqrt(),
             Collider::Radial{length, ..} =>  *length,
             Collider::Polyhedron{length, ..} =>  *length,
+            Collider::Sphere{radius} =>  *radius,
+            Collider::Capsule{half_length, radius} =>  *half_length + *radius,
+            Collider::Box{half_extents} =>  half_extents.magnitude(),
+            Collider::Compound{length, ..} =>  *length,
         }
     }
 
@@ -100,79 +248,105 @@ impl Collider {
             Collider::Cube{..} => Vector3::zero(),
             Collider::Radial{..} => Vector3::zero(),
             Collider::Polyhedron { offset, ..} => *offset,
+            Collider::Sphere{..} | Collider::Capsule{..} | Collider::Box{..} => Vector3::zero(),
+            Collider::Compound { offset, ..} => *offset,
         }
     }
 
-    pub(super) fn gjk_collide(my_c: &Collider, o_c: &Collider,
+    /// The support point of the collider placed at pos with the given orientation, in world
+    /// coordinates
+    fn world_support(&self, pos: Vector3<f64>, orientation: Quaternion<f64>, dir: Vector3<f64>) -> Vector3<f64> {
+        pos + orientation * (self.offset() + self.support(orientation.invert() * dir))
+    }
+
+    /// The contact between two overlapping convex colliders, found with GJK and then EPA
+    pub(super) fn gjk_contact(my_c: &Collider, o_c: &Collider,
         my_rb_pos: Vector3<f64>, o_rb_pos: Vector3<f64>,
-        my_orientation: Quaternion<f64>, o_orientation: Quaternion<f64>,
-        shift: Vector3<f64>) -> Option<CollisionData> {
+        my_orientation: Quaternion<f64>, o_orientation: Quaternion<f64>) -> Option<Contact> {
 
-        // Confirm the objects are within collision distance
-        let my_c_offset = my_orientation * my_c.offset();
-        let o_c_offset = o_orientation * o_c.offset();
-        let displacement = o_rb_pos - my_rb_pos - my_c_offset + o_c_offset;
-        if displacement.magnitude() > my_c.radius() + o_c.radius() {
+        let my_center = my_rb_pos + my_orientation * my_c.offset();
+        let o_center = o_rb_pos + o_orientation * o_c.offset();
+        if (o_center - my_center).magnitude() > my_c.radius() + o_c.radius() {
             return None;
         }
 
-        // Initialization
-        let my_inv_orientation = my_orientation.invert();
-        let o_inv_orientation = o_orientation.invert();
-        let initial_axis = displacement.normalize();
+        let support = |dir: Vector3<f64>| {
+            let my_pos = my_c.world_support(my_rb_pos, my_orientation, dir);
+            let o_pos = o_c.world_support(o_rb_pos, o_orientation, -dir);
+            (my_pos - o_pos, my_pos, o_pos)
+        };
+        let tetrahedron = match gjk(&support, my_center - o_center)? {
+            Simplex::Tetrahedron(a, b, c, d) => [a, b, c, d],
+            simplex => complete_tetrahedron(simplex, &support)?,
+        };
+        epa::epa(tetrahedron, &support)
+    }
 
-        let my_pos = my_c.support(my_inv_orientation * initial_axis);
-        let o_pos = o_c.support(o_inv_orientation * -initial_axis);
-        let mut dir = -(my_orientation * my_pos - (o_orientation * o_pos + displacement));
-        if shift.dot(initial_axis) > 0.0 {
-            dir -= shift;
-        }
-        let mut state = GJKState::new((-dir, my_pos, o_pos));
-        loop {
-            dir = dir.normalize();
-            let my_pos = my_c.support(my_inv_orientation * dir);
-            let o_pos = o_c.support(o_inv_orientation * -dir);
-            let mut new_vec = my_orientation * my_pos - (o_orientation * o_pos + displacement);
-            if shift.dot(dir) > 0.0 {
-                new_vec += shift;
-            }
+    /// Contacts spread over the area where two convex colliders touch. EPA only finds one point,
+    /// so the smaller collider is tilted a little each way about the normal to find the corners of
+    /// the touching face. Those are moved back onto the untilted collider and measured against the
+    /// surface of the other.
+    pub(super) fn gjk_contacts(my_c: &Collider, o_c: &Collider,
+        my_rb_pos: Vector3<f64>, o_rb_pos: Vector3<f64>,
+        my_orientation: Quaternion<f64>, o_orientation: Quaternion<f64>) -> Vec<Contact> {
 
-            if new_vec.dot(dir) < 0.0 {
-                break;
-            }
-            state.push((new_vec, my_pos, o_pos));
-            if match state.contains_origin(&mut dir) {
-                Err(_) => break,
-                Ok(b) => b
-            } {
-                return Some(state.get_collision_data(my_c_offset, my_c_offset - o_c_offset + o_rb_pos - my_rb_pos,
-                    my_orientation, o_orientation));
+        let contact = match Self::gjk_contact(my_c, o_c, my_rb_pos, o_rb_pos, my_orientation, o_orientation) {
+            Some(c) => c,
+            None => return Vec::new(),
+        };
+        let normal = contact.normal;
+        let tangent = if normal.x.abs() < 0.9 { normal.cross(Vector3::unit_x()) } else { normal.cross(Vector3::unit_y()) }.normalize();
+
+        let mut contacts = vec![contact];
+        for quarter in 0..4 {
+            let axis = Quaternion::from_axis_angle(normal, Rad(quarter as f64 * std::f64::consts::FRAC_PI_2)) * tangent;
+            let tilt = Quaternion::from_axis_angle(axis, Rad(PERTURBATION_ANGLE));
+            let extra = if my_c.radius() < o_c.radius() {
+                Self::gjk_contact(my_c, o_c, my_rb_pos, o_rb_pos, tilt * my_orientation, o_orientation).map(|c| {
+                    let point_a = my_rb_pos + tilt.invert() * (c.point_a - my_rb_pos);
+                    let depth = normal.dot(point_a - contact.point_b);
+                    Contact { normal, depth, point_a, point_b: point_a - normal * depth }
+                })
+            } else {
+                Self::gjk_contact(my_c, o_c, my_rb_pos, o_rb_pos, my_orientation, tilt * o_orientation).map(|c| {
+                    let point_b = o_rb_pos + tilt.invert() * (c.point_b - o_rb_pos);
+                    let depth = normal.dot(contact.point_a - point_b);
+                    Contact { normal, depth, point_a: point_b + normal * depth, point_b }
+                })
+            };
+            if let Some(extra) = extra {
+                if extra.depth > -CONTACT_SLOP {
+                    contacts.push(extra);
+                }
             }
         }
-        None
+        contacts
     }
 
+    /// The contact between a planet and a convex collider, using the point of the collider which
+    /// reaches furthest towards the planet's center. The normal points away from the planet.
     pub(super) fn planet_collide(planet: &Collider, c: &Collider,
         planet_pos: Vector3<f64>, c_pos: Vector3<f64>,
-        planet_orientation: Quaternion<f64>, c_orientation: Quaternion<f64>,
-        shift: Vector3<f64>) -> Option<CollisionData> {
-            // Confirm the objects are within collision distance
-        let planet_offset = planet_orientation * planet.offset();
-        let c_offset = c_orientation * c.offset();
-        let displacement = c_pos - planet_pos - planet_offset + c_offset - shift;
+        planet_orientation: Quaternion<f64>, c_orientation: Quaternion<f64>) -> Option<Contact> {
+        // Confirm the objects are within collision distance
+        let planet_center = planet_pos + planet_orientation * planet.offset();
+        let displacement = c_pos + c_orientation * c.offset() - planet_center;
         if displacement.magnitude() > planet.radius() + c.radius() {
             return None;
         }
 
-        let lowermost_point = c_orientation * c.support(-c_orientation.invert() * displacement.normalize()) - displacement;
+        let lowermost_point = c.world_support(c_pos, c_orientation, -displacement) - planet_center;
         if let Self::Radial{ func, ..} = planet {
-            let value = func(lowermost_point);
+            let value = func(planet_orientation.invert() * lowermost_point);
             if value < 0.0 {
                 // Collision
-                let lowermost_mag = lowermost_point.magnitude();
-                let normal = lowermost_point / lowermost_mag;
-                let point = lowermost_point * (1.0 + value / lowermost_mag);
-                return Some(CollisionData::Collision(point, normal));
+                let normal = lowermost_point.normalize();
+                return Some(Contact {
+                    normal,
+                    depth: -value,
+                    point_a: planet_center + lowermost_point - normal * value,
+                    point_b: planet_center + lowermost_point,
+                });
             }
         } else {
             unreachable!();
@@ -181,65 +355,113 @@ impl Collider {
     }
 }
 
-pub(super) enum CollisionType {
-    EdgeEdge((Vector3<f64>, Vector3<f64>), (Vector3<f64>, Vector3<f64>)),
-    FaceVertex((Vector3<f64>, Vector3<f64>, Vector3<f64>), (Vector3<f64>,)),
-    VertexFace((Vector3<f64>,), (Vector3<f64>, Vector3<f64>, Vector3<f64>)),
-    Other,
+/// The vertex furthest along dir, found by checking every one
+fn scan_support(vertices: &[Vector3<f64>], dir: Vector3<f64>) -> Vector3<f64> {
+    let mut max_dot = 0.0;
+    let mut max_v = None;
+    for v in vertices {
+        let dot = v.dot(dir);
+        if dot > max_dot || max_v.is_none() {
+            max_dot = dot;
+            max_v = Some(v);
+        }
+    }
+    *max_v.unwrap()
 }
 
-pub(super) struct GJKState {
-    simplex: Simplex,
-    num_iter: u32,
+/// The point of a sphere about the origin furthest along dir
+fn sphere_support(dir: Vector3<f64>, radius: f64) -> Vector3<f64> {
+    if dir.magnitude2() > 0.0 {
+        dir.normalize() * radius
+    } else {
+        Vector3::unit_x() * radius
+    }
 }
 
-impl GJKState {
-    pub fn new(point: Vertex) -> Self {
-        Self {
-            simplex: Simplex::Point(point),
-            num_iter: 0,
+/// Run GJK on a Minkowski difference given by its support function. Returns the final simplex if
+/// the difference contains the origin, which means the colliders overlap or touch.
+fn gjk<F>(support: &F, initial_dir: Vector3<f64>) -> Option<Simplex>
+where F: Fn(Vector3<f64>) -> Vertex {
+    let dir = if initial_dir.magnitude2() > 0.0 { initial_dir } else { Vector3::unit_x() };
+    let first = support(dir);
+    let mut closest = first.0;
+    let mut simplex = Simplex::Point(first);
+    for _ in 0..MAX_ITERATIONS {
+        if closest.magnitude2() <= EPSILON * EPSILON {
+            // The origin is on the simplex, so the colliders just touch
+            return Some(simplex);
+        }
+        let dir = -closest;
+        let new_vec = support(dir);
+        if new_vec.0.dot(dir) < 0.0 {
+            // Nothing in the difference reaches past the origin
+            return None;
+        }
+        simplex.push(new_vec);
+        match simplex.downgrade() {
+            None => return Some(simplex),
+            Some(p) => closest = p,
         }
     }
-    
-    pub fn push(&mut self, point: Vertex) {
-        self.simplex.push(point);
-    }
-
-    /// Returns true if the simplex contains the origin. Otherwise, returns the direction in which
-    /// to search for the next point. That's the negative of the position of the closest point in
-    /// the simplex to the origin. If the state decides the operation is taking too long, err is 
-    /// returned.
-    pub fn contains_origin(&mut self, dir: &mut Vector3<f64>) -> Result<bool> {
-        let p = match self.simplex.downgrade() {
-            Some(p) => p,
-            None => return Ok(true) // Intersection detected.
-        };
-        *dir = -p;
-        self.num_iter += 1;
-        if self.num_iter > MAX_ITERATIONS {
-            return Err(anyhow!("Too many iterations"));
+    None
+}
+
+/// Grow a simplex which touches the origin into a tetrahedron so that EPA has somewhere to start.
+/// None is returned if the difference is flat or the origin is outside the tetrahedron.
+fn complete_tetrahedron<F>(simplex: Simplex, support: &F) -> Option<[Vertex; 4]>
+where F: Fn(Vector3<f64>) -> Vertex {
+    let mut vertices = simplex.vertices();
+    while vertices.len() < 4 {
+        let (best, best_distance) = search_directions(&vertices).into_iter()
+            .map(|dir| {
+                let v = support(dir);
+                (v, distance_from_hull(&vertices, v.0))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        if best_distance.is_nan() || best_distance <= EPSILON {
+            return None;
         }
-        Ok(false)
+        vertices.push(best);
     }
 
-    pub fn get_collision_data(&mut self, my_offset: Vector3<f64>, o_offset: Vector3<f64>,
-        my_orientation: Quaternion<f64>, o_orientation: Quaternion<f64>) -> CollisionData {
+    let weights = barycentric(&[vertices[0].0, vertices[1].0, vertices[2].0, vertices[3].0])?;
+    if weights.iter().any(|w| *w < -EPSILON) {
+        return None;
+    }
+    Some([vertices[0], vertices[1], vertices[2], vertices[3]])
+}
 
-        if let Simplex::Tetrahedron(a, b, c, d) = self.simplex {
-            let my_simplex = VertexCounter::reduce(
-                my_orientation * a.1 + my_offset,
-                my_orientation * b.1 + my_offset,
-                my_orientation * c.1 + my_offset,
-                my_orientation * d.1 + my_offset);
-            let o_simplex = VertexCounter::reduce(
-                o_orientation * a.2 + o_offset,
-                o_orientation * b.2 + o_offset,
-                o_orientation * c.2 + o_offset,
-                o_orientation * d.2 + o_offset);
+/// Directions to look for a new vertex off the affine hull of the vertices
+fn search_directions(vertices: &[Vertex]) -> Vec<Vector3<f64>> {
+    match vertices.len() {
+        1 => vec![Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z(),
+            -Vector3::unit_x(), -Vector3::unit_y(), -Vector3::unit_z()],
+        2 => {
+            let line = vertices[1].0 - vertices[0].0;
+            let p = if line.x.abs() < 0.9 * line.magnitude() { line.cross(Vector3::unit_x()) } else { line.cross(Vector3::unit_y()) };
+            let q = line.cross(p);
+            vec![p, q, -p, -q]
+        },
+        _ => {
+            let normal = (vertices[1].0 - vertices[0].0).cross(vertices[2].0 - vertices[0].0);
+            vec![normal, -normal]
+        },
+    }
+}
 
-            VertexCounter::collide_data(my_simplex, o_simplex)
-        } else {
-            unreachable!()
-        }
+/// Distance of a point from the affine hull of the vertices
+fn distance_from_hull(vertices: &[Vertex], point: Vector3<f64>) -> f64 {
+    let d = point - vertices[0].0;
+    match vertices.len() {
+        1 => d.magnitude(),
+        2 => {
+            let line = (vertices[1].0 - vertices[0].0).normalize();
+            (d - line * line.dot(d)).magnitude()
+        },
+        _ => {
+            let normal = (vertices[1].0 - vertices[0].0).cross(vertices[2].0 - vertices[0].0).normalize();
+            normal.dot(d).abs()
+        },
     }
-}
\ No newline at end of file
+}
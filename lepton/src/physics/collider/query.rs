@@ -0,0 +1,228 @@
+use cgmath::{Vector3, Quaternion, InnerSpace, Rotation};
+
+use super::{Collider, EPSILON, MAX_ITERATIONS};
+use super::primitives::{Simplex, Vertex};
+
+/// Casts stop once they are this close to the surface
+const CAST_TOL: f64 = 1e-7;
+/// Relative accuracy of the GJK distance
+const DISTANCE_TOL: f64 = 1e-10;
+/// Fraction of a value function's magnitude a march along a ray may step at once. Value functions
+/// are not exact distances, so only part of it can be trusted.
+const MARCH_FRACTION: f64 = 0.5;
+/// Marching never takes more steps than this to cross a radial collider
+const MAX_MARCH_STEPS: usize = 1024;
+const BISECTION_STEPS: usize = 64;
+/// Step used to differentiate value functions, relative to the collider's size
+const GRADIENT_STEP: f64 = 1e-7;
+/// Colliders are measured between their bounding spheres while the spheres are further apart than
+/// this fraction of their combined radius
+const SPHERE_GAP_FRACTION: f64 = 0.1;
+
+impl Collider {
+    /// Sweep a sphere from origin along the unit vector dir and return the distance travelled when
+    /// it first touches this collider, together with the point touched and the surface normal
+    /// there. A sphere of zero radius is a ray. A sphere which starts inside is hit at zero.
+    /// Spheres cast against radial colliders treat the value function as a distance.
+    pub(crate) fn cast_sphere(&self, pos: Vector3<f64>, orientation: Quaternion<f64>,
+        origin: Vector3<f64>, dir: Vector3<f64>, radius: f64, max_distance: f64) -> Option<(f64, Vector3<f64>, Vector3<f64>)> {
+
+        // Skip colliders whose bounding sphere the cast misses
+        let center = pos + orientation * self.offset();
+        let (enter, exit) = sphere_interval(center - origin, dir, self.radius() + radius)?;
+        if exit < 0.0 || enter > max_distance {
+            return None;
+        }
+
+        match self {
+            Collider::Radial { func, .. } => {
+                let value = |t: f64| func(orientation.invert() * (origin + dir * t - pos)) - radius;
+                let t = march(&value, enter.max(0.0), exit.min(max_distance))?;
+                let point = origin + dir * t;
+                let normal = self.radial_normal(pos, orientation, point).unwrap_or(-dir);
+                Some((t, point - normal * radius, normal))
+            },
+            _ => self.advance_sphere(pos, orientation, origin, dir, radius, max_distance),
+        }
+    }
+
+    /// Whether a sphere overlaps this collider. Radial colliders treat the value function as a
+    /// distance.
+    pub(crate) fn overlaps_sphere(&self, pos: Vector3<f64>, orientation: Quaternion<f64>,
+        center: Vector3<f64>, radius: f64) -> bool {
+        if (pos + orientation * self.offset() - center).magnitude() > self.radius() + radius {
+            return false;
+        }
+        match self {
+            Collider::Radial { func, .. } => func(orientation.invert() * (center - pos)) < radius,
+            _ => match self.closest_point(pos, orientation, center) {
+                Some(closest) => (closest - center).magnitude() <= radius,
+                None => true,
+            },
+        }
+    }
+
+    /// A lower bound on the gap between two colliders, and the unit normal pointing across it
+    /// from the first to the second. None is returned if they overlap. Colliders whose bounding
+    /// spheres are well apart are measured between the spheres, and radial colliders only trust
+    /// part of their value function.
+    pub(crate) fn separation(my_c: &Collider, o_c: &Collider,
+        my_pos: Vector3<f64>, o_pos: Vector3<f64>,
+        my_orientation: Quaternion<f64>, o_orientation: Quaternion<f64>) -> Option<(f64, Vector3<f64>)> {
+        let my_center = my_pos + my_orientation * my_c.offset();
+        let o_center = o_pos + o_orientation * o_c.offset();
+        let between = o_center - my_center;
+        let sphere_gap = between.magnitude() - my_c.radius() - o_c.radius();
+        if sphere_gap > SPHERE_GAP_FRACTION * (my_c.radius() + o_c.radius()) {
+            return Some((sphere_gap, between / between.magnitude()));
+        }
+
+        match (my_c, o_c) {
+            (Collider::Radial { func, .. }, _) => {
+                let lowermost = o_c.world_support(o_pos, o_orientation, -between);
+                let value = func(my_orientation.invert() * (lowermost - my_pos));
+                if value <= 0.0 {
+                    return None;
+                }
+                let normal = lowermost - my_center;
+                Some((value * MARCH_FRACTION, if normal.magnitude2() > 0.0 { normal.normalize() } else { Vector3::unit_x() }))
+            },
+            (_, Collider::Radial { .. }) => Self::separation(o_c, my_c, o_pos, my_pos, o_orientation, my_orientation)
+                .map(|(gap, normal)| (gap, -normal)),
+            _ => {
+                let support = |dir: Vector3<f64>| {
+                    let my_point = my_c.world_support(my_pos, my_orientation, dir);
+                    let o_point = o_c.world_support(o_pos, o_orientation, -dir);
+                    (my_point - o_point, my_point, o_point)
+                };
+                let closest = gjk_distance(&support, -between)?;
+                let gap = closest.magnitude();
+                Some((gap, -closest / gap))
+            },
+        }
+    }
+
+    /// Conservative advancement: the collider lies beyond the plane through its closest point, so
+    /// the sphere can always move as far as that plane. None is returned if it moves away.
+    fn advance_sphere(&self, pos: Vector3<f64>, orientation: Quaternion<f64>,
+        origin: Vector3<f64>, dir: Vector3<f64>, radius: f64, max_distance: f64) -> Option<(f64, Vector3<f64>, Vector3<f64>)> {
+        let mut t = 0.0;
+        let mut normal = -dir;
+        for _ in 0..MAX_ITERATIONS {
+            let x = origin + dir * t;
+            let closest = match self.closest_point(pos, orientation, x) {
+                Some(closest) => closest,
+                None => return Some((t, x, normal)),
+            };
+            let distance = (x - closest).magnitude();
+            normal = (x - closest) / distance;
+            let gap = distance - radius;
+            if gap < CAST_TOL {
+                return Some((t, closest, normal));
+            }
+            let approach = -dir.dot(normal);
+            if approach <= 0.0 {
+                return None;
+            }
+            t += gap / approach;
+            if t > max_distance {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// The point of a convex collider closest to the point x, or None if x is inside
+    fn closest_point(&self, pos: Vector3<f64>, orientation: Quaternion<f64>, x: Vector3<f64>) -> Option<Vector3<f64>> {
+        let support = |dir: Vector3<f64>| {
+            let p = self.world_support(pos, orientation, dir);
+            (p - x, p, x)
+        };
+        gjk_distance(&support, pos + orientation * self.offset() - x).map(|v| x + v)
+    }
+
+    /// The normal of a radial collider's surface near point, from the value function's gradient
+    fn radial_normal(&self, pos: Vector3<f64>, orientation: Quaternion<f64>, point: Vector3<f64>) -> Option<Vector3<f64>> {
+        let (func, length) = match self {
+            Collider::Radial { func, length } => (func, *length),
+            _ => return None,
+        };
+        let local = orientation.invert() * (point - pos);
+        let h = GRADIENT_STEP * length.max(1.0);
+        let gradient = Vector3::new(
+            func(local + Vector3::unit_x() * h) - func(local - Vector3::unit_x() * h),
+            func(local + Vector3::unit_y() * h) - func(local - Vector3::unit_y() * h),
+            func(local + Vector3::unit_z() * h) - func(local - Vector3::unit_z() * h),
+        );
+        if gradient.magnitude2() > 0.0 {
+            Some(orientation * gradient.normalize())
+        } else if local.magnitude2() > 0.0 {
+            Some(orientation * local.normalize())
+        } else {
+            None
+        }
+    }
+}
+
+/// The closest point of a Minkowski difference to the origin, or None if it contains the origin
+fn gjk_distance<F>(support: &F, initial_dir: Vector3<f64>) -> Option<Vector3<f64>>
+where F: Fn(Vector3<f64>) -> Vertex {
+    let dir = if initial_dir.magnitude2() > 0.0 { -initial_dir } else { Vector3::unit_x() };
+    let first = support(dir);
+    let mut closest = first.0;
+    let mut simplex = Simplex::Point(first);
+    for _ in 0..MAX_ITERATIONS {
+        if closest.magnitude2() <= EPSILON * EPSILON {
+            return None;
+        }
+        let new_vec = support(-closest);
+        // Stop once no support point gets meaningfully closer than the current estimate
+        if closest.magnitude2() - closest.dot(new_vec.0) <= DISTANCE_TOL * closest.magnitude2() {
+            return Some(closest);
+        }
+        simplex.push(new_vec);
+        closest = simplex.downgrade()?;
+    }
+    Some(closest)
+}
+
+/// The range of distances along the unit vector dir at which a ray from the origin is inside the
+/// sphere at center
+fn sphere_interval(center: Vector3<f64>, dir: Vector3<f64>, radius: f64) -> Option<(f64, f64)> {
+    let along = center.dot(dir);
+    let miss = center.magnitude2() - along * along;
+    if miss > radius * radius {
+        return None;
+    }
+    let half_chord = (radius * radius - miss).sqrt();
+    Some((along - half_chord, along + half_chord))
+}
+
+/// The first root of value between start and end, where value is positive outside. The march
+/// brackets a sign change, which bisection then narrows down.
+fn march<F: Fn(f64) -> f64>(value: &F, start: f64, end: f64) -> Option<f64> {
+    let mut t = start;
+    let mut v = value(t);
+    if v <= 0.0 {
+        return Some(t);
+    }
+    let min_step = (end - start) / MAX_MARCH_STEPS as f64;
+    while t < end {
+        let next = (t + (v * MARCH_FRACTION).max(min_step)).min(end);
+        let next_v = value(next);
+        if next_v <= 0.0 {
+            let (mut low, mut high) = (t, next);
+            for _ in 0..BISECTION_STEPS {
+                let mid = 0.5 * (low + high);
+                if value(mid) > 0.0 { low = mid } else { high = mid }
+                if high - low < CAST_TOL {
+                    break;
+                }
+            }
+            return Some(high);
+        }
+        t = next;
+        v = next_v;
+    }
+    None
+}
@@ -0,0 +1,27 @@
+use serde::{Serialize, Deserialize};
+
+/// Which bodies a body collides with. A body belongs to the groups whose bits are set in group
+/// and collides with the groups set in mask. Two bodies only collide if each is in the other's
+/// mask, so either can opt out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CollisionFilter {
+    pub group: u32,
+    pub mask: u32,
+}
+
+impl CollisionFilter {
+    pub fn new(group: u32, mask: u32) -> Self {
+        Self { group, mask }
+    }
+
+    pub fn allows(&self, o: &CollisionFilter) -> bool {
+        self.group & o.mask != 0 && o.group & self.mask != 0
+    }
+}
+
+impl Default for CollisionFilter {
+    /// In the first group and colliding with every group
+    fn default() -> Self {
+        Self { group: 1, mask: u32::MAX }
+    }
+}
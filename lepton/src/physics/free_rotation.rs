@@ -0,0 +1,244 @@
+// Exact torque-free motion of a rigid body, after Landau & Lifshitz, Mechanics §37. The angular
+// momentum in the body's principal frame follows Jacobi elliptic functions, and the attitude is
+// recovered from the fixed angular momentum in the world plus the angle turned about it.
+use cgmath::{Vector3, Matrix3, Quaternion, InnerSpace, Rotation3, Rad, Matrix, SquareMatrix, Zero};
+
+/// Jacobi sweeps stop once the off-diagonal part is this small relative to the whole matrix
+const EIGEN_TOL: f64 = 1e-15;
+const MAX_SWEEPS: usize = 32;
+/// Motion closer than this to a steady spin about one axis is treated as one
+const STEADY_TOL: f64 = 1e-12;
+/// The precession angle is integrated over pieces of the step no longer than this in the
+/// argument of the elliptic functions
+const QUADRATURE_SPAN: f64 = 0.5;
+const MAX_QUADRATURE_PIECES: usize = 10_000;
+/// Five point Gauss-Legendre nodes and weights on [-1, 1]
+const GAUSS_NODES: [f64; 5] = [-0.906179845938664, -0.5384693101056831, 0.0, 0.5384693101056831, 0.906179845938664];
+const GAUSS_WEIGHTS: [f64; 5] = [0.23692688505618908, 0.47862867049936647, 0.5688888888888889, 0.47862867049936647, 0.23692688505618908];
+
+/// Turn a free body for time dt with no torque. The moment of inertia is in the body's frame and
+/// the angular velocity in the world's. Returns the new orientation and angular velocity.
+pub(super) fn torque_free_step(local_moi: Matrix3<f64>, orientation: Quaternion<f64>, ang_vel: Vector3<f64>, dt: f64)
+    -> (Quaternion<f64>, Vector3<f64>) {
+    let (moments, to_local) = principal_axes(local_moi);
+    // Rotates the principal frame into the world
+    let attitude = orientation * to_local;
+    let omega = attitude.conjugate() * ang_vel;
+    let momentum = Vector3::new(moments.x * omega.x, moments.y * omega.y, moments.z * omega.z);
+
+    let motion = match FreeMotion::new(moments, momentum) {
+        Some(motion) => motion,
+        None => {
+            // Steady spin about a principal axis, or a body which turns the same about every axis
+            let speed = ang_vel.magnitude();
+            if speed == 0.0 {
+                return (orientation, ang_vel);
+            }
+            let turn = Quaternion::from_axis_angle(ang_vel / speed, Rad(speed * dt));
+            return ((turn * orientation).normalize(), ang_vel);
+        },
+    };
+
+    let world_momentum = attitude * momentum;
+    let axis = world_momentum.normalize();
+    let precession = motion.precession(dt);
+    let final_momentum = motion.momentum(dt);
+    let attitude = (Quaternion::from_axis_angle(axis, Rad(precession)) * attitude
+        * motion.align(momentum).conjugate() * motion.align(final_momentum)).normalize();
+
+    let final_omega = Vector3::new(final_momentum.x / moments.x, final_momentum.y / moments.y, final_momentum.z / moments.z);
+    ((attitude * to_local.conjugate()).normalize(), attitude * final_omega)
+}
+
+/// The motion of the angular momentum in the principal frame. Following Landau, axis r is the one
+/// the momentum circles, which is the axis of greatest or least inertia, and p is the other
+/// extreme. The angular velocity about p, q and r goes as amplitude times cn, sn and dn of
+/// phase + direction rate t with parameter k2.
+struct FreeMotion {
+    moments: [f64; 3],
+    axes: [usize; 3],
+    amplitude: [f64; 3],
+    k2: f64,
+    phase: f64,
+    rate: f64,
+    /// Twice the kinetic energy over the magnitude of the angular momentum
+    spin: f64,
+    /// Unit vector along axis r on the side the momentum lies
+    pole: Vector3<f64>,
+}
+
+impl FreeMotion {
+    /// None is returned for steady spins, which the elliptic solution can't describe
+    fn new(moments: Vector3<f64>, momentum: Vector3<f64>) -> Option<Self> {
+        let moments = [moments.x, moments.y, moments.z];
+        let m = [momentum.x, momentum.y, momentum.z];
+        let omega = [m[0] / moments[0], m[1] / moments[1], m[2] / moments[2]];
+        let omega_vec = Vector3::new(omega[0], omega[1], omega[2]);
+        if momentum.cross(omega_vec).magnitude() <= STEADY_TOL * momentum.magnitude() * omega_vec.magnitude() {
+            return None;
+        }
+
+        let mut order = [0, 1, 2];
+        order.sort_by(|i, j| moments[*i].total_cmp(&moments[*j]));
+        let [lo, mid, hi] = order;
+        // M² - 2E I, summed so that terms which should cancel do
+        let excess = |inertia: f64| (0..3).map(|i| m[i] * m[i] * (1.0 - inertia / moments[i])).sum::<f64>();
+        let d = excess(moments[mid]);
+        let (p, r) = if d > 0.0 || (d == 0.0 && moments[hi] > moments[mid]) { (lo, hi) } else { (hi, lo) };
+        let q = mid;
+        let (i_p, i_q, i_r) = (moments[p], moments[q], moments[r]);
+        let (s_p, s_r) = (excess(i_p), excess(i_r));
+
+        let mut amplitude = [0.0; 3];
+        amplitude[p] = (-s_r / (i_p * (i_r - i_p))).max(0.0).sqrt();
+        amplitude[q] = (-s_r / (i_q * (i_r - i_q))).max(0.0).sqrt();
+        amplitude[r] = (s_p / (i_r * (i_r - i_p))).max(0.0).sqrt() * if omega[r] < 0.0 { -1.0 } else { 1.0 };
+        let rate = ((i_r - i_q) * s_p / (i_p * i_q * i_r)).max(0.0).sqrt();
+        if amplitude[p] == 0.0 || amplitude[q] == 0.0 || rate == 0.0 || !rate.is_finite() {
+            return None;
+        }
+        let k2 = ((i_q - i_p) * -s_r / ((i_r - i_q) * s_p)).clamp(0.0, 1.0 - f64::EPSILON);
+
+        // Match the starting point, and run forwards or backwards as Euler's equations say
+        let amp = (omega[q] / amplitude[q]).atan2(omega[p] / amplitude[p]);
+        let phase = elliptic_f(amp, k2);
+        let cyclic = (q + 3 - p) % 3 == 1;
+        let direction = (if cyclic { 1.0 } else { -1.0 }) * (i_r - i_p).signum() * amplitude[r].signum();
+
+        let spin = (0..3).map(|i| m[i] * omega[i]).sum::<f64>() / momentum.magnitude();
+        let mut pole = Vector3::zero();
+        pole[r] = if m[r] < 0.0 { -1.0 } else { 1.0 };
+        Some(Self { moments, axes: [p, q, r], amplitude, k2, phase, rate: rate * direction, spin, pole })
+    }
+
+    fn omega(&self, t: f64) -> Vector3<f64> {
+        let (sn, cn, dn) = jacobi(self.phase + self.rate * t, self.k2);
+        let [p, q, r] = self.axes;
+        let mut omega = Vector3::zero();
+        omega[p] = self.amplitude[p] * cn;
+        omega[q] = self.amplitude[q] * sn;
+        omega[r] = self.amplitude[r] * dn;
+        omega
+    }
+
+    fn momentum(&self, t: f64) -> Vector3<f64> {
+        let omega = self.omega(t);
+        Vector3::new(self.moments[0] * omega.x, self.moments[1] * omega.y, self.moments[2] * omega.z)
+    }
+
+    /// The smallest rotation taking the direction of the momentum onto the pole. It never has to
+    /// turn halfway round, because the momentum stays on the pole's side.
+    fn align(&self, momentum: Vector3<f64>) -> Quaternion<f64> {
+        let n = momentum.normalize();
+        Quaternion::from_sv(1.0 + n.dot(self.pole), n.cross(self.pole)).normalize()
+    }
+
+    /// The angle the body turns about the angular momentum in time t, beyond what align accounts
+    /// for. Its rate is (2E/M + Ω·pole) / (1 + n·pole), n being the direction of the momentum.
+    fn precession(&self, t: f64) -> f64 {
+        let rate = |time: f64| {
+            let momentum = self.momentum(time);
+            let n = momentum.normalize();
+            (self.spin + self.omega(time).dot(self.pole)) / (1.0 + n.dot(self.pole))
+        };
+        let pieces = ((self.rate * t).abs() / QUADRATURE_SPAN).ceil().clamp(1.0, MAX_QUADRATURE_PIECES as f64) as usize;
+        let width = t / pieces as f64;
+        (0..pieces).map(|piece| {
+            let center = (piece as f64 + 0.5) * width;
+            GAUSS_NODES.iter().zip(GAUSS_WEIGHTS.iter())
+                .map(|(x, w)| w * rate(center + 0.5 * width * x))
+                .sum::<f64>() * 0.5 * width
+        }).sum()
+    }
+}
+
+/// The principal moments of inertia, and the rotation taking the principal axes into the frame
+/// the moment of inertia was given in
+pub(super) fn principal_axes(moi: Matrix3<f64>) -> (Vector3<f64>, Quaternion<f64>) {
+    let mut a = moi;
+    let mut v = Matrix3::identity();
+    let scale = (0..3).map(|i| a[i].magnitude2()).sum::<f64>().sqrt();
+    for _ in 0..MAX_SWEEPS {
+        let off = a[0][1].abs() + a[0][2].abs() + a[1][2].abs();
+        if off <= EIGEN_TOL * scale {
+            break;
+        }
+        for (i, j) in [(0, 1), (0, 2), (1, 2)] {
+            if a[i][j] == 0.0 {
+                continue;
+            }
+            // Rotate in the i-j plane to zero a[i][j]
+            let theta = 0.5 * (a[j][j] - a[i][i]) / a[i][j];
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            let mut rotation = Matrix3::identity();
+            rotation[i][i] = c;
+            rotation[j][j] = c;
+            rotation[j][i] = s;
+            rotation[i][j] = -s;
+            a = rotation.transpose() * a * rotation;
+            v = v * rotation;
+        }
+    }
+    if v.determinant() < 0.0 {
+        v.z = -v.z;
+    }
+    (Vector3::new(a[0][0], a[1][1], a[2][2]), Quaternion::from(v).normalize())
+}
+
+/// Jacobi elliptic functions sn, cn and dn of u with parameter m, by the descending Landen
+/// transformation (Abramowitz & Stegun 16.4)
+fn jacobi(u: f64, m: f64) -> (f64, f64, f64) {
+    if m < f64::EPSILON {
+        return (u.sin(), u.cos(), 1.0);
+    }
+    let mut a = [0.0; 16];
+    let mut c = [0.0; 16];
+    a[0] = 1.0;
+    c[0] = m.sqrt();
+    let mut b = (1.0 - m).sqrt();
+    let mut n = 0;
+    while n < 15 && c[n].abs() > f64::EPSILON {
+        a[n + 1] = 0.5 * (a[n] + b);
+        c[n + 1] = 0.5 * (a[n] - b);
+        b = (a[n] * b).sqrt();
+        n += 1;
+    }
+    let mut phi = 2f64.powi(n as i32) * a[n] * u;
+    let mut previous = phi;
+    for j in (1..=n).rev() {
+        previous = phi;
+        phi = 0.5 * (phi + (c[j] / a[j] * phi.sin()).asin());
+    }
+    (phi.sin(), phi.cos(), phi.cos() / (previous - phi).cos())
+}
+
+/// The incomplete elliptic integral of the first kind F(amp | m), for any amplitude
+fn elliptic_f(amp: f64, m: f64) -> f64 {
+    let turns = (amp / std::f64::consts::PI).round();
+    let amp = amp - turns * std::f64::consts::PI;
+    let (s, c) = amp.sin_cos();
+    let complete = carlson_rf(0.0, 1.0 - m, 1.0);
+    2.0 * turns * complete + s * carlson_rf(c * c, 1.0 - m * s * s, 1.0)
+}
+
+/// Carlson's symmetric elliptic integral R_F, by duplication (Numerical Recipes §6.12)
+fn carlson_rf(x: f64, y: f64, z: f64) -> f64 {
+    let (mut x, mut y, mut z) = (x, y, z);
+    for _ in 0..64 {
+        let mean = (x + y + z) / 3.0;
+        let (dx, dy, dz) = (1.0 - x / mean, 1.0 - y / mean, 1.0 - z / mean);
+        if dx.abs().max(dy.abs()).max(dz.abs()) < 0.0025 {
+            let e2 = dx * dy - dz * dz;
+            let e3 = dx * dy * dz;
+            return (1.0 + (e2 / 24.0 - 0.1 - 3.0 * e3 / 44.0) * e2 + e3 / 14.0) / mean.sqrt();
+        }
+        let (sx, sy, sz) = (x.sqrt(), y.sqrt(), z.sqrt());
+        let lambda = sx * (sy + sz) + sy * sz;
+        x = 0.25 * (x + lambda);
+        y = 0.25 * (y + lambda);
+        z = 0.25 * (z + lambda);
+    }
+    1.0 / ((x + y + z) / 3.0).sqrt()
+}
@@ -0,0 +1,222 @@
+use cgmath::{Vector3, InnerSpace, Zero};
+use rustc_hash::FxHashMap;
+use serde::{Serialize, Deserialize};
+
+use super::{Object, RigidBody, Updater};
+
+/// Octree nodes stop splitting at this depth, so that sources at the same place share a leaf
+const MAX_DEPTH: usize = 24;
+
+/// How a body takes part in the world's gravity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GravityRole {
+    /// Neither pulls nor is pulled
+    Inert,
+    /// Pulls on every other body with its mass, and is pulled by the other sources if it is free
+    Source,
+    /// Pulled by the sources without pulling on anything, like a ship near a planet
+    Particle,
+}
+
+/// How the pull of all the sources on a body is summed
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GravityMethod {
+    /// Every source is visited for every body pulled
+    Pairwise,
+    /// Distant groups of sources are replaced by their total mass at their center of mass, using
+    /// an octree. A group is opened when its width is more than opening_angle times its distance,
+    /// so zero gives the exact sum and about 0.5 is usual.
+    BarnesHut { opening_angle: f64 },
+}
+
+/// Newtonian gravity between the bodies of a world. The pull is softened by the Plummer length
+/// softening, which keeps close encounters from flinging bodies apart.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gravity {
+    pub constant: f64,
+    pub softening: f64,
+    pub method: GravityMethod,
+}
+
+/// A body which pulls, sorted by object so the sums do not depend on the hash map's order
+struct Source {
+    object: Object,
+    pos: Vector3<f64>,
+    mass: f64,
+}
+
+impl Gravity {
+    /// Exact, unsoftened gravity with the given gravitational constant
+    pub fn new(constant: f64) -> Self {
+        Self {
+            constant,
+            softening: 0.0,
+            method: GravityMethod::Pairwise,
+        }
+    }
+
+    pub fn soften(mut self, softening: f64) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    pub fn barnes_hut(mut self, opening_angle: f64) -> Self {
+        self.method = GravityMethod::BarnesHut { opening_angle };
+        self
+    }
+
+    /// The acceleration of every awake free body which feels gravity. Sleeping bodies still pull,
+    /// but the sources aren't gathered at all when no body is awake to be pulled.
+    pub(crate) fn accelerations(&self, bodies: &FxHashMap<Object, RigidBody>) -> Vec<(Object, Vector3<f64>)> {
+        let pulled = bodies.iter()
+            .filter(|(_, body)| body.gravity_role != GravityRole::Inert && matches!(body.updater, Updater::Free) && !body.asleep)
+            .collect::<Vec<_>>();
+        if pulled.is_empty() {
+            return Vec::new();
+        }
+        let field = SourceField::new(self, bodies);
+        pulled.into_iter()
+            .map(|(object, body)| (*object, field.at(body.pos, Some(*object))))
+            .collect()
+    }
+
+    /// The acceleration a particle at point would feel
+    pub(crate) fn field_at(&self, bodies: &FxHashMap<Object, RigidBody>, point: Vector3<f64>) -> Vector3<f64> {
+        SourceField::new(self, bodies).at(point, None)
+    }
+
+    /// Acceleration towards a mass at from
+    fn pull(&self, from: Vector3<f64>, point: Vector3<f64>, mass: f64) -> Vector3<f64> {
+        let d = from - point;
+        let r2 = d.magnitude2() + self.softening * self.softening;
+        if r2 == 0.0 {
+            return Vector3::zero();
+        }
+        d * (self.constant * mass / (r2 * r2.sqrt()))
+    }
+}
+
+/// The sources of one step, with their octree if Barnes-Hut is used
+struct SourceField<'a> {
+    gravity: &'a Gravity,
+    sources: Vec<Source>,
+    tree: Option<(Octree, f64)>,
+}
+
+impl<'a> SourceField<'a> {
+    fn new(gravity: &'a Gravity, bodies: &FxHashMap<Object, RigidBody>) -> Self {
+        let mut sources = bodies.iter()
+            .filter(|(_, body)| body.gravity_role == GravityRole::Source && body.mass > 0.0)
+            .map(|(object, body)| Source { object: *object, pos: body.pos, mass: body.mass })
+            .collect::<Vec<_>>();
+        sources.sort_unstable_by_key(|source| source.object);
+        let tree = match gravity.method {
+            GravityMethod::Pairwise => None,
+            GravityMethod::BarnesHut { opening_angle } => Some((Octree::new(&sources), opening_angle)),
+        };
+        Self { gravity, sources, tree }
+    }
+
+    /// The pull of every source except the body itself
+    fn at(&self, point: Vector3<f64>, exclude: Option<Object>) -> Vector3<f64> {
+        let (tree, opening_angle) = match &self.tree {
+            Some((tree, opening_angle)) => (tree, *opening_angle),
+            None => {
+                return self.sources.iter()
+                    .filter(|source| Some(source.object) != exclude)
+                    .map(|source| self.gravity.pull(source.pos, point, source.mass))
+                    .sum();
+            },
+        };
+        let mut acceleration = Vector3::zero();
+        let mut stack = if tree.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(n) = stack.pop() {
+            let node = &tree.nodes[n];
+            if node.children.is_empty() {
+                for source in node.sources.iter().map(|s| &self.sources[*s]) {
+                    if Some(source.object) != exclude {
+                        acceleration += self.gravity.pull(source.pos, point, source.mass);
+                    }
+                }
+                continue;
+            }
+            // A node around the point always opens, so a body never pulls on itself
+            let width = 2.0 * node.half_width;
+            let far = width * width < opening_angle * opening_angle * (node.center_of_mass - point).magnitude2();
+            if far && !node.contains(point) {
+                acceleration += self.gravity.pull(node.center_of_mass, point, node.mass);
+            } else {
+                stack.extend(node.children.iter().rev());
+            }
+        }
+        acceleration
+    }
+}
+
+struct Node {
+    center: Vector3<f64>,
+    half_width: f64,
+    mass: f64,
+    center_of_mass: Vector3<f64>,
+    children: Vec<usize>,
+    /// The sources in a leaf
+    sources: Vec<usize>,
+}
+
+impl Node {
+    fn contains(&self, point: Vector3<f64>) -> bool {
+        let d = point - self.center;
+        d.x.abs() <= self.half_width && d.y.abs() <= self.half_width && d.z.abs() <= self.half_width
+    }
+}
+
+/// An octree over the sources, with the root at index zero
+struct Octree {
+    nodes: Vec<Node>,
+}
+
+impl Octree {
+    fn new(sources: &[Source]) -> Self {
+        let mut nodes = Vec::new();
+        if sources.is_empty() {
+            return Self { nodes };
+        }
+        let (mut min, mut max) = (sources[0].pos, sources[0].pos);
+        for source in sources {
+            min = Vector3::new(min.x.min(source.pos.x), min.y.min(source.pos.y), min.z.min(source.pos.z));
+            max = Vector3::new(max.x.max(source.pos.x), max.y.max(source.pos.y), max.z.max(source.pos.z));
+        }
+        let extent = max - min;
+        let half_width = 0.5 * extent.x.max(extent.y).max(extent.z);
+        build(&mut nodes, sources, (0..sources.len()).collect(), (min + max) * 0.5, half_width, 0);
+        Self { nodes }
+    }
+}
+
+/// Add the node holding members and everything below it, returning its index
+fn build(nodes: &mut Vec<Node>, sources: &[Source], members: Vec<usize>, center: Vector3<f64>, half_width: f64, depth: usize) -> usize {
+    let mass = members.iter().map(|m| sources[*m].mass).sum::<f64>();
+    let center_of_mass = members.iter().map(|m| sources[*m].pos * sources[*m].mass).sum::<Vector3<f64>>() / mass;
+    let index = nodes.len();
+    nodes.push(Node { center, half_width, mass, center_of_mass, children: Vec::new(), sources: Vec::new() });
+    if members.len() <= 1 || depth == MAX_DEPTH {
+        nodes[index].sources = members;
+        return index;
+    }
+
+    let mut octants: [Vec<usize>; 8] = Default::default();
+    for m in members {
+        let p = sources[m].pos;
+        octants[(p.x >= center.x) as usize | ((p.y >= center.y) as usize) << 1 | ((p.z >= center.z) as usize) << 2].push(m);
+    }
+    for (i, octant) in octants.into_iter().enumerate() {
+        if octant.is_empty() {
+            continue;
+        }
+        let side = |bit: usize| if i & bit == 0 { -0.5 } else { 0.5 };
+        let child_center = center + Vector3::new(side(1), side(2), side(4)) * half_width;
+        let child = build(nodes, sources, octant, child_center, 0.5 * half_width, depth + 1);
+        nodes[index].children.push(child);
+    }
+    index
+}
@@ -0,0 +1,104 @@
+use cgmath::{Vector3, Matrix3, Quaternion, InnerSpace, Matrix};
+use serde::{Serialize, Deserialize};
+
+use super::RigidBody;
+use super::free_rotation::torque_free_step;
+
+/// How a free body's position and velocity are stepped. Impulses from tasks and contacts are
+/// always applied in full at the start of the step; the integrators differ in how they sample
+/// the body's acceleration field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Kick with the field at the start of the step, then drift. Cheap and symplectic.
+    SemiImplicitEuler,
+    /// Velocity Verlet: half a kick either side of the drift. Symplectic and second order, so
+    /// the energy of long orbits stays bounded.
+    Leapfrog,
+    /// Classic fourth-order Runge-Kutta. Very accurate over short times, but the energy slowly
+    /// drifts.
+    Rk4,
+}
+
+/// How a free body's orientation and angular velocity are stepped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationIntegrator {
+    /// First-order update of the orientation, with the gyroscopic term applied explicitly
+    Euler,
+    /// Fourth-order Runge-Kutta on the orientation, holding the angular momentum fixed
+    Rk4,
+    /// The exact motion of a free rigid body between torques. Energy and angular momentum are
+    /// kept to rounding error however fast the body spins.
+    TorqueFree,
+}
+
+/// Acceleration a body feels at each position in the world
+pub type Field = Box<dyn 'static + Send + Sync + Fn(Vector3<f64>) -> Vector3<f64>>;
+
+impl RigidBody {
+    /// Move a free body through dt with its velocity after the step's impulses
+    pub(crate) fn integrate_translation(&mut self, dt: f64) {
+        let field = match &self.field {
+            Some(field) => field,
+            None => {
+                self.pos += self.vel * dt;
+                return;
+            },
+        };
+        match self.integrator {
+            Integrator::SemiImplicitEuler => {
+                self.vel += field(self.pos) * dt;
+                self.pos += self.vel * dt;
+            },
+            Integrator::Leapfrog => {
+                self.vel += field(self.pos) * (0.5 * dt);
+                self.pos += self.vel * dt;
+                self.vel += field(self.pos) * (0.5 * dt);
+            },
+            Integrator::Rk4 => {
+                let (x, v) = (self.pos, self.vel);
+                let (k1x, k1v) = (v, field(x));
+                let (k2x, k2v) = (v + k1v * (0.5 * dt), field(x + k1x * (0.5 * dt)));
+                let (k3x, k3v) = (v + k2v * (0.5 * dt), field(x + k2x * (0.5 * dt)));
+                let (k4x, k4v) = (v + k3v * dt, field(x + k3x * dt));
+                self.pos += (k1x + k2x * 2.0 + k3x * 2.0 + k4x) * (dt / 6.0);
+                self.vel += (k1v + k2v * 2.0 + k3v * 2.0 + k4v) * (dt / 6.0);
+            },
+        }
+    }
+
+    /// Turn a free body through dt, applying the step's torque impulse first. The Euler
+    /// integrator's gyroscopic term covers the whole step of length delta_time.
+    pub(crate) fn integrate_rotation(&mut self, delta_time: f64, dt: f64) {
+        match self.rotation_integrator {
+            RotationIntegrator::Euler => {
+                self.ang_vel += self.moi_inv() * (self.torque_impulse - self.ang_vel.cross(self.moi() * self.ang_vel) * delta_time);
+                self.orientation += 0.5 * Quaternion::new(0.0, self.ang_vel.x, self.ang_vel.y, self.ang_vel.z) * self.orientation * dt;
+                self.orientation = self.orientation.normalize();
+            },
+            RotationIntegrator::Rk4 => {
+                let momentum = self.moi() * self.ang_vel + self.torque_impulse;
+                let local_moi_inv = self.local_moi_inv;
+                let ang_vel = |q: Quaternion<f64>| {
+                    let rotation = Matrix3::from(q.normalize());
+                    rotation * local_moi_inv * rotation.transpose() * momentum
+                };
+                let derivative = |q: Quaternion<f64>| {
+                    let w = ang_vel(q);
+                    0.5 * Quaternion::new(0.0, w.x, w.y, w.z) * q
+                };
+                let q = self.orientation;
+                let k1 = derivative(q);
+                let k2 = derivative(q + k1 * (0.5 * dt));
+                let k3 = derivative(q + k2 * (0.5 * dt));
+                let k4 = derivative(q + k3 * dt);
+                self.orientation = (q + (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (dt / 6.0)).normalize();
+                self.ang_vel = ang_vel(self.orientation);
+            },
+            RotationIntegrator::TorqueFree => {
+                let momentum = self.moi() * self.ang_vel + self.torque_impulse;
+                let ang_vel = self.moi_inv() * momentum;
+                (self.orientation, self.ang_vel) = torque_free_step(self.local_moi, self.orientation, ang_vel, dt);
+            },
+        }
+    }
+}
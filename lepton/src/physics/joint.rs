@@ -0,0 +1,322 @@
+use cgmath::{Vector3, Quaternion, Matrix3, InnerSpace, Rotation, SquareMatrix, Zero};
+use serde::{Serialize, Deserialize};
+
+use super::{Object, RigidBody};
+use super::manifold::effective_inv_mass;
+use super::rigid_body::perpendicular;
+use super::snapshot::{FakeVector, FakeQuaternion};
+
+/// Fraction of a joint's drift corrected each step
+const BAUMGARTE: f64 = 0.2;
+/// Anchors closer than this have no direction between them
+const MIN_SEPARATION: f64 = 1e-9;
+
+pub type JointId = u16;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum JointKind {
+    /// Holds the two bodies together as one, like docked ships
+    Fixed,
+    /// Pins the anchors together but lets the bodies turn freely
+    Ball,
+    /// Pins the anchors together and lets the bodies turn about one axis, given in the first
+    /// body's frame
+    Hinge { #[serde(with = "FakeVector")] axis: Vector3<f64> },
+    /// Lets the second body slide along an axis in the first body's frame without turning, like
+    /// an elevator car
+    Slider { #[serde(with = "FakeVector")] axis: Vector3<f64> },
+    /// Keeps the anchors a fixed distance apart, like a taut tether
+    Distance { length: f64 },
+    /// Pulls the anchors towards a rest length with a damped spring
+    Spring { length: f64, stiffness: f64, damping: f64 },
+}
+
+/// A constraint between two bodies. The anchors are in each body's frame. Joints are solved
+/// together with the contacts, and record the force and torque they needed to hold so that they
+/// can break.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Joint {
+    pub a: Object,
+    pub b: Object,
+    pub kind: JointKind,
+    #[serde(with = "FakeVector")]
+    pub anchor_a: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    pub anchor_b: Vector3<f64>,
+    pub break_force: Option<f64>,
+    pub break_torque: Option<f64>,
+    /// Whether the joined bodies still collide with each other
+    pub collide_connected: bool,
+    /// Orientation of the second body in the first body's frame when the joint was made
+    #[serde(with = "FakeQuaternion")]
+    reference: Quaternion<f64>,
+    #[serde(with = "FakeVector")]
+    hinge_axis_b: Vector3<f64>,
+    /// Impulses applied to the second body this step, kept to warm start the next
+    #[serde(with = "FakeVector")]
+    linear_impulse: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    angular_impulse: Vector3<f64>,
+    delta_time: f64,
+}
+
+impl Joint {
+    fn new(a: Object, b: Object, anchor_a: Vector3<f64>, anchor_b: Vector3<f64>, kind: JointKind) -> Self {
+        Self {
+            a,
+            b,
+            kind,
+            anchor_a,
+            anchor_b,
+            break_force: None,
+            break_torque: None,
+            collide_connected: false,
+            reference: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            hinge_axis_b: Vector3::zero(),
+            linear_impulse: Vector3::zero(),
+            angular_impulse: Vector3::zero(),
+            delta_time: 0.0,
+        }
+    }
+
+    pub fn fixed(a: Object, b: Object, anchor_a: Vector3<f64>, anchor_b: Vector3<f64>) -> Self {
+        Self::new(a, b, anchor_a, anchor_b, JointKind::Fixed)
+    }
+
+    pub fn ball(a: Object, b: Object, anchor_a: Vector3<f64>, anchor_b: Vector3<f64>) -> Self {
+        Self::new(a, b, anchor_a, anchor_b, JointKind::Ball)
+    }
+
+    pub fn hinge(a: Object, b: Object, anchor_a: Vector3<f64>, anchor_b: Vector3<f64>, axis: Vector3<f64>) -> Self {
+        Self::new(a, b, anchor_a, anchor_b, JointKind::Hinge { axis: axis.normalize() })
+    }
+
+    pub fn slider(a: Object, b: Object, anchor_a: Vector3<f64>, anchor_b: Vector3<f64>, axis: Vector3<f64>) -> Self {
+        Self::new(a, b, anchor_a, anchor_b, JointKind::Slider { axis: axis.normalize() })
+    }
+
+    pub fn distance(a: Object, b: Object, anchor_a: Vector3<f64>, anchor_b: Vector3<f64>, length: f64) -> Self {
+        Self::new(a, b, anchor_a, anchor_b, JointKind::Distance { length })
+    }
+
+    pub fn spring(a: Object, b: Object, anchor_a: Vector3<f64>, anchor_b: Vector3<f64>, length: f64, stiffness: f64, damping: f64) -> Self {
+        Self::new(a, b, anchor_a, anchor_b, JointKind::Spring { length, stiffness, damping })
+    }
+
+    /// Break the joint when the force it needs to hold exceeds break_force
+    pub fn break_force(mut self, break_force: f64) -> Self {
+        self.break_force = Some(break_force);
+        self
+    }
+
+    /// Break the joint when the torque it needs to hold exceeds break_torque
+    pub fn break_torque(mut self, break_torque: f64) -> Self {
+        self.break_torque = Some(break_torque);
+        self
+    }
+
+    /// Let the joined bodies collide with each other, which they don't by default
+    pub fn collide_connected(mut self) -> Self {
+        self.collide_connected = true;
+        self
+    }
+
+    /// Force applied to the second body during the last step. The first body feels the opposite.
+    pub fn force(&self) -> Vector3<f64> {
+        if self.delta_time > 0.0 { self.linear_impulse / self.delta_time } else { Vector3::zero() }
+    }
+
+    /// Torque applied to the second body during the last step, not counting that of the force
+    pub fn torque(&self) -> Vector3<f64> {
+        if self.delta_time > 0.0 { self.angular_impulse / self.delta_time } else { Vector3::zero() }
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.break_force.is_some_and(|limit| self.force().magnitude() > limit)
+            || self.break_torque.is_some_and(|limit| self.torque().magnitude() > limit)
+    }
+
+    /// Record how the bodies sit relative to each other, which fixed, hinge and slider joints
+    /// then hold
+    pub(crate) fn attach(&mut self, a: &RigidBody, b: &RigidBody) {
+        self.reference = a.orientation.invert() * b.orientation;
+        if let JointKind::Hinge { axis } = self.kind {
+            self.hinge_axis_b = self.reference.invert() * axis;
+        }
+    }
+
+    fn arms(&self, a: &RigidBody, b: &RigidBody) -> (Vector3<f64>, Vector3<f64>) {
+        (a.orientation * self.anchor_a, b.orientation * self.anchor_b)
+    }
+
+    /// Drop the parts of last step's impulses which the joint no longer constrains and apply the
+    /// rest again, so that the solver starts close to the answer
+    pub(crate) fn prepare(&mut self, a: &mut RigidBody, b: &mut RigidBody, delta_time: f64) {
+        self.delta_time = delta_time;
+        let (r_a, r_b) = self.arms(a, b);
+        match self.kind {
+            JointKind::Fixed => (),
+            JointKind::Ball => self.angular_impulse = Vector3::zero(),
+            JointKind::Hinge { axis } => {
+                let axis = a.orientation * axis;
+                self.angular_impulse -= axis * axis.dot(self.angular_impulse);
+            },
+            JointKind::Slider { axis } => {
+                let axis = a.orientation * axis;
+                self.linear_impulse -= axis * axis.dot(self.linear_impulse);
+            },
+            JointKind::Distance { .. } | JointKind::Spring { .. } => {
+                self.angular_impulse = Vector3::zero();
+                match separation(a, b, r_a, r_b) {
+                    Some((dir, _)) => self.linear_impulse = dir * dir.dot(self.linear_impulse),
+                    None => self.linear_impulse = Vector3::zero(),
+                }
+            },
+        }
+        apply(a, b, r_a, r_b, self.linear_impulse, self.angular_impulse);
+    }
+
+    /// One pass of sequential impulses over the joint's constraints
+    pub(crate) fn solve(&mut self, a: &mut RigidBody, b: &mut RigidBody) {
+        let (r_a, r_b) = self.arms(a, b);
+        match self.kind {
+            JointKind::Fixed => {
+                self.solve_rotation(a, b);
+                self.solve_point(a, b, r_a, r_b);
+            },
+            JointKind::Ball => self.solve_point(a, b, r_a, r_b),
+            JointKind::Hinge { axis } => {
+                let axis = a.orientation * axis;
+                // Turning the second body's copy of the axis onto the first's
+                let error = (b.orientation * self.hinge_axis_b).cross(axis);
+                let (t_1, t_2) = perpendiculars(axis);
+                self.solve_angular_row(a, b, t_1, -t_1.dot(error));
+                self.solve_angular_row(a, b, t_2, -t_2.dot(error));
+                self.solve_point(a, b, r_a, r_b);
+            },
+            JointKind::Slider { axis } => {
+                self.solve_rotation(a, b);
+                let axis = a.orientation * axis;
+                let drift = (b.pos + r_b) - (a.pos + r_a);
+                let (t_1, t_2) = perpendiculars(axis);
+                self.solve_linear_row(a, b, r_a, r_b, t_1, t_1.dot(drift), 0.0);
+                self.solve_linear_row(a, b, r_a, r_b, t_2, t_2.dot(drift), 0.0);
+            },
+            JointKind::Distance { length } => {
+                if let Some((dir, distance)) = separation(a, b, r_a, r_b) {
+                    self.solve_linear_row(a, b, r_a, r_b, dir, distance - length, 0.0);
+                }
+            },
+            JointKind::Spring { length, stiffness, damping } => {
+                if let Some((dir, distance)) = separation(a, b, r_a, r_b) {
+                    self.solve_spring(a, b, r_a, r_b, dir, distance - length, stiffness, damping);
+                }
+            },
+        }
+    }
+
+    /// Stop the anchors moving apart and pull them back together
+    fn solve_point(&mut self, a: &mut RigidBody, b: &mut RigidBody, r_a: Vector3<f64>, r_b: Vector3<f64>) {
+        let k = Matrix3::from_value(a.inv_mass() + b.inv_mass())
+            - skew(r_a) * a.inv_moi() * skew(r_a)
+            - skew(r_b) * b.inv_moi() * skew(r_b);
+        let k_inv = match k.invert() {
+            Some(k_inv) => k_inv,
+            None => return,
+        };
+        let drift = (b.pos + r_b) - (a.pos + r_a);
+        let velocity = b.point_velocity(r_b) - a.point_velocity(r_a);
+        let impulse = k_inv * -(velocity + drift * (BAUMGARTE / self.delta_time));
+        self.linear_impulse += impulse;
+        apply(a, b, r_a, r_b, impulse, Vector3::zero());
+    }
+
+    /// Stop the bodies turning relative to each other and turn them back to the reference
+    fn solve_rotation(&mut self, a: &mut RigidBody, b: &mut RigidBody) {
+        let k_inv = match (a.inv_moi() + b.inv_moi()).invert() {
+            Some(k_inv) => k_inv,
+            None => return,
+        };
+        let mut error = b.orientation * (a.orientation * self.reference).invert();
+        if error.s < 0.0 {
+            error = -error;
+        }
+        let spin = b.angular_velocity() - a.angular_velocity();
+        let impulse = k_inv * -(spin + error.v * (2.0 * BAUMGARTE / self.delta_time));
+        self.angular_impulse += impulse;
+        apply(a, b, Vector3::zero(), Vector3::zero(), Vector3::zero(), impulse);
+    }
+
+    /// Stop the bodies turning relative to each other about dir, correcting an angle of error
+    fn solve_angular_row(&mut self, a: &mut RigidBody, b: &mut RigidBody, dir: Vector3<f64>, error: f64) {
+        let denom = dir.dot((a.inv_moi() + b.inv_moi()) * dir);
+        if denom <= 0.0 {
+            return;
+        }
+        let spin = dir.dot(b.angular_velocity() - a.angular_velocity());
+        let impulse = dir * (-(spin + error * BAUMGARTE / self.delta_time) / denom);
+        self.angular_impulse += impulse;
+        apply(a, b, Vector3::zero(), Vector3::zero(), Vector3::zero(), impulse);
+    }
+
+    /// Drive the speed of the anchors apart along dir to target_speed, correcting a drift of error
+    fn solve_linear_row(&mut self, a: &mut RigidBody, b: &mut RigidBody, r_a: Vector3<f64>, r_b: Vector3<f64>,
+        dir: Vector3<f64>, error: f64, target_speed: f64) {
+        let denom = effective_inv_mass(a, b, r_a, r_b, dir);
+        if denom <= 0.0 {
+            return;
+        }
+        let speed = dir.dot(b.point_velocity(r_b) - a.point_velocity(r_a));
+        let impulse = dir * ((target_speed - speed - error * BAUMGARTE / self.delta_time) / denom);
+        self.linear_impulse += impulse;
+        apply(a, b, r_a, r_b, impulse, Vector3::zero());
+    }
+
+    /// A soft constraint which behaves like a damped spring but stays stable for stiff springs.
+    /// See Erin Catto, "Soft Constraints", GDC 2011.
+    fn solve_spring(&mut self, a: &mut RigidBody, b: &mut RigidBody, r_a: Vector3<f64>, r_b: Vector3<f64>,
+        dir: Vector3<f64>, stretch: f64, stiffness: f64, damping: f64) {
+        let softness = self.delta_time * (damping + self.delta_time * stiffness);
+        let denom = effective_inv_mass(a, b, r_a, r_b, dir);
+        if softness <= 0.0 || denom <= 0.0 {
+            return;
+        }
+        let gamma = 1.0 / softness;
+        let bias = stretch * self.delta_time * stiffness * gamma;
+        let accumulated = dir.dot(self.linear_impulse);
+        let speed = dir.dot(b.point_velocity(r_b) - a.point_velocity(r_a));
+        let impulse = dir * (-(speed + bias + gamma * accumulated) / (denom + gamma));
+        self.linear_impulse += impulse;
+        apply(a, b, r_a, r_b, impulse, Vector3::zero());
+    }
+}
+
+/// Apply an impulse and a torque impulse to the second body at r_b, and the opposite to the first
+/// at r_a
+fn apply(a: &mut RigidBody, b: &mut RigidBody, r_a: Vector3<f64>, r_b: Vector3<f64>, impulse: Vector3<f64>, torque: Vector3<f64>) {
+    a.apply_impulse_at(-impulse, r_a);
+    b.apply_impulse_at(impulse, r_b);
+    a.torque_impulse -= torque;
+    b.torque_impulse += torque;
+}
+
+/// Direction from the first anchor to the second, and the distance between them
+fn separation(a: &RigidBody, b: &RigidBody, r_a: Vector3<f64>, r_b: Vector3<f64>) -> Option<(Vector3<f64>, f64)> {
+    let gap = (b.pos + r_b) - (a.pos + r_a);
+    let distance = gap.magnitude();
+    if distance < MIN_SEPARATION {
+        return None;
+    }
+    Some((gap / distance, distance))
+}
+
+/// The matrix which takes the cross product with v
+fn skew(v: Vector3<f64>) -> Matrix3<f64> {
+    Matrix3::new(0.0, v.z, -v.y, -v.z, 0.0, v.x, v.y, -v.x, 0.0)
+}
+
+/// Two unit vectors perpendicular to the unit vector v and to each other
+fn perpendiculars(v: Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let t_1 = perpendicular(v);
+    (t_1, v.cross(t_1))
+}
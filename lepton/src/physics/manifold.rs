@@ -0,0 +1,248 @@
+use cgmath::{Vector3, InnerSpace, Rotation, Zero};
+use serde::{Serialize, Deserialize};
+
+use super::{RigidBody, Contact, CollisionEvent};
+use super::world::ColliderPair;
+use super::snapshot::FakeVector;
+
+/// Stored points which separate or slide apart by more than this are dropped
+const BREAKING_DISTANCE: f64 = 0.05;
+/// A new contact this close to a stored point replaces it rather than adding another
+const MERGE_DISTANCE: f64 = 0.02;
+const MAX_POINTS: usize = 4;
+/// Penetration left alone by the solver, so that resting bodies stay in contact from step to step
+const ALLOWED_PENETRATION: f64 = 0.01;
+/// Fraction of the remaining penetration pushed out each step
+const BAUMGARTE: f64 = 0.2;
+/// Contacts closing slower than this do not bounce, which stops resting bodies from jittering
+const RESTING_SPEED: f64 = 0.5;
+const SCALE_ELASTICITY_VEL: f64 = 20.0;
+
+/// One point of a contact manifold. The anchors are kept in each body's frame so that the point
+/// can follow the bodies between steps.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContactPoint {
+    #[serde(with = "FakeVector")]
+    local_a: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    local_b: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    pub point_a: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    pub point_b: Vector3<f64>,
+    pub depth: f64,
+    /// Total impulse along the normal, kept between steps to warm start the solver
+    pub normal_impulse: f64,
+    /// Total friction impulse, perpendicular to the normal, also kept between steps
+    #[serde(with = "FakeVector")]
+    pub tangent_impulse: Vector3<f64>,
+    target_speed: f64,
+}
+
+impl ContactPoint {
+    fn new(contact: &Contact, a: &RigidBody, b: &RigidBody) -> Self {
+        Self {
+            local_a: a.orientation.invert() * (contact.point_a - a.pos),
+            local_b: b.orientation.invert() * (contact.point_b - b.pos),
+            point_a: contact.point_a,
+            point_b: contact.point_b,
+            depth: contact.depth,
+            normal_impulse: 0.0,
+            tangent_impulse: Vector3::zero(),
+            target_speed: 0.0,
+        }
+    }
+
+    /// Where the impulse acts, relative to each body's position
+    fn arms(&self, a: &RigidBody, b: &RigidBody) -> (Vector3<f64>, Vector3<f64>) {
+        let center = (self.point_a + self.point_b) / 2.0;
+        (center - a.pos, center - b.pos)
+    }
+}
+
+/// Up to four points of contact between a pair of colliders, kept from step to step. A single
+/// contact from EPA can't hold a box flat on a surface, but the points gathered over a few steps
+/// can.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ContactManifold {
+    /// Points out of the first body into the second
+    #[serde(with = "FakeVector")]
+    pub normal: Vector3<f64>,
+    pub points: Vec<ContactPoint>,
+    static_friction: f64,
+    dynamic_friction: f64,
+    /// Whether the colliders have only just started touching
+    fresh: bool,
+    /// Fastest speed at which any point was closing this step
+    closing_speed: f64,
+}
+
+impl ContactManifold {
+    pub fn new(normal: Vector3<f64>) -> Self {
+        Self {
+            normal,
+            points: Vec::new(),
+            static_friction: 0.0,
+            dynamic_friction: 0.0,
+            fresh: true,
+            closing_speed: 0.0,
+        }
+    }
+
+    /// Move the stored points with the bodies and drop those which have separated or slid apart
+    pub fn refresh(&mut self, a: &RigidBody, b: &RigidBody) {
+        let normal = self.normal;
+        for point in &mut self.points {
+            point.point_a = a.pos + a.orientation * point.local_a;
+            point.point_b = b.pos + b.orientation * point.local_b;
+            point.depth = (point.point_a - point.point_b).dot(normal);
+        }
+        self.points.retain(|point| {
+            let gap = point.point_a - point.point_b;
+            let drift = gap - normal * normal.dot(gap);
+            point.depth > -BREAKING_DISTANCE && drift.magnitude() < BREAKING_DISTANCE
+        });
+    }
+
+    /// Add a new contact. A stored point close to it is replaced, keeping its impulse. If there are
+    /// then too many points, the deepest is kept along with those which span the largest area.
+    pub fn add(&mut self, contact: &Contact, a: &RigidBody, b: &RigidBody) {
+        self.normal = contact.normal;
+        let mut new_point = ContactPoint::new(contact, a, b);
+        match self.points.iter_mut().find(|p| (p.point_a - contact.point_a).magnitude() < MERGE_DISTANCE) {
+            Some(old) => {
+                new_point.normal_impulse = old.normal_impulse;
+                new_point.tangent_impulse = old.tangent_impulse;
+                *old = new_point;
+            },
+            None => {
+                self.points.push(new_point);
+                if self.points.len() > MAX_POINTS {
+                    self.reduce();
+                }
+            },
+        }
+    }
+
+    fn reduce(&mut self) {
+        let deepest = (0..self.points.len())
+            .max_by(|i, j| self.points[*i].depth.total_cmp(&self.points[*j].depth))
+            .unwrap();
+        let remove = (0..self.points.len())
+            .filter(|i| *i != deepest)
+            .max_by(|i, j| self.area_without(*i).total_cmp(&self.area_without(*j)))
+            .unwrap();
+        self.points.remove(remove);
+    }
+
+    /// A measure of the area spanned by the four points left when one of five is removed
+    fn area_without(&self, removed: usize) -> f64 {
+        let p = self.points.iter().enumerate()
+            .filter(|(i, _)| *i != removed)
+            .map(|(_, point)| point.point_a)
+            .collect::<Vec<_>>();
+        (p[0] - p[1]).cross(p[2] - p[3]).magnitude()
+            .max((p[0] - p[2]).cross(p[1] - p[3]).magnitude())
+            .max((p[0] - p[3]).cross(p[1] - p[2]).magnitude())
+    }
+
+    /// Work out how fast each point should separate, then apply the impulses found last step again
+    /// so that the solver starts close to the answer.
+    pub fn prepare(&mut self, a: &mut RigidBody, b: &mut RigidBody, delta_time: f64) {
+        let elasticity = (a.elasticity * b.elasticity).sqrt();
+        self.static_friction = (a.static_friction * b.static_friction).sqrt();
+        self.dynamic_friction = (a.dynamic_friction * b.dynamic_friction).sqrt();
+        let normal = self.normal;
+        self.closing_speed = 0.0;
+        // Measure how fast every point closes before any impulse is applied again
+        for point in &mut self.points {
+            let (r_a, r_b) = point.arms(a, b);
+            let closing_speed = -normal.dot(b.point_velocity(r_b) - a.point_velocity(r_a));
+            self.closing_speed = self.closing_speed.max(closing_speed);
+            let bounce = if closing_speed > RESTING_SPEED {
+                elasticity * (-closing_speed / SCALE_ELASTICITY_VEL).exp() * closing_speed
+            } else {
+                0.0
+            };
+            let push_out = BAUMGARTE * (point.depth - ALLOWED_PENETRATION).max(0.0) / delta_time;
+            point.target_speed = bounce.max(push_out);
+        }
+        for point in &mut self.points {
+            let (r_a, r_b) = point.arms(a, b);
+            // The normal may have turned since last step, so keep only the part of the old
+            // friction impulse which still lies in the contact plane
+            point.tangent_impulse -= normal * normal.dot(point.tangent_impulse);
+            let impulse = normal * point.normal_impulse + point.tangent_impulse;
+            a.apply_impulse_at(-impulse, r_a);
+            b.apply_impulse_at(impulse, r_b);
+        }
+    }
+
+    /// Describe the contact for the game if the colliders have just met, or struck each other hard
+    /// enough to bounce. Resting contacts are only reported when they begin.
+    pub fn event(&mut self, (a, b, part_a, part_b): ColliderPair) -> Option<CollisionEvent> {
+        if (!self.fresh && self.closing_speed <= RESTING_SPEED) || self.points.is_empty() {
+            return None;
+        }
+        self.fresh = false;
+        let num = self.points.len() as f64;
+        Some(CollisionEvent {
+            a,
+            b,
+            part_a,
+            part_b,
+            point: self.points.iter().map(|p| (p.point_a + p.point_b) / 2.0).sum::<Vector3<f64>>() / num,
+            normal: self.normal,
+            relative_speed: self.closing_speed,
+            impulse: self.points.iter().map(|p| p.normal_impulse).sum(),
+        })
+    }
+
+    /// One pass of sequential impulses over the points. Friction is solved first, limited by the
+    /// normal impulse of the last pass, and then the normal impulse, which may only push the bodies
+    /// apart.
+    pub fn solve(&mut self, a: &mut RigidBody, b: &mut RigidBody) {
+        let normal = self.normal;
+        for point in &mut self.points {
+            let (r_a, r_b) = point.arms(a, b);
+
+            // Friction: stop the sliding if the static cone allows it, otherwise slide against
+            // dynamic friction
+            let velocity = b.point_velocity(r_b) - a.point_velocity(r_a);
+            let slip = velocity - normal * normal.dot(velocity);
+            if slip.magnitude2() > 0.0 {
+                let tangent = slip.normalize();
+                let denom = effective_inv_mass(a, b, r_a, r_b, tangent);
+                if denom > 0.0 {
+                    let mut total = point.tangent_impulse - tangent * (slip.magnitude() / denom);
+                    if total.magnitude() > self.static_friction * point.normal_impulse {
+                        total = total.normalize_to(self.dynamic_friction * point.normal_impulse);
+                    }
+                    let impulse = total - point.tangent_impulse;
+                    point.tangent_impulse = total;
+                    a.apply_impulse_at(-impulse, r_a);
+                    b.apply_impulse_at(impulse, r_b);
+                }
+            }
+
+            let speed = normal.dot(b.point_velocity(r_b) - a.point_velocity(r_a));
+            let denom = effective_inv_mass(a, b, r_a, r_b, normal);
+            if denom <= 0.0 {
+                continue;
+            }
+            let total = (point.normal_impulse + (point.target_speed - speed) / denom).max(0.0);
+            let impulse = normal * (total - point.normal_impulse);
+            point.normal_impulse = total;
+            a.apply_impulse_at(-impulse, r_a);
+            b.apply_impulse_at(impulse, r_b);
+        }
+    }
+}
+
+/// The change in relative speed along the unit vector dir per unit impulse along it, at arms r_a
+/// and r_b
+pub(super) fn effective_inv_mass(a: &RigidBody, b: &RigidBody, r_a: Vector3<f64>, r_b: Vector3<f64>, dir: Vector3<f64>) -> f64 {
+    a.inv_mass() + b.inv_mass()
+        + dir.dot((a.inv_moi() * r_a.cross(dir)).cross(r_a))
+        + dir.dot((b.inv_moi() * r_b.cross(dir)).cross(r_b))
+}
@@ -1,53 +1,154 @@
 mod rigid_body;
 mod collider;
+mod broadphase;
+mod world;
+mod manifold;
+mod joint;
+mod query;
+mod integrator;
+mod free_rotation;
+mod gravity;
+mod aerodynamics;
+mod filter;
+mod snapshot;
+mod replay;
+pub mod orbit;
+#[cfg(test)]
+mod tests;
 
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 use std::sync::mpsc::{Receiver, Sender};
+use anyhow::Result;
 use rustc_hash::FxHashMap;
-use cgmath::{Vector3, InnerSpace};
+use cgmath::{Vector3, Quaternion, Matrix3};
+use serde::{Serialize, Deserialize};
 
 pub use rigid_body::*;
 pub use collider::*;
+pub use joint::{Joint, JointKind, JointId};
+pub use query::{RayHit, PhysicsQueries};
+pub use integrator::{Integrator, RotationIntegrator, Field};
+pub use gravity::{Gravity, GravityMethod, GravityRole};
+pub use aerodynamics::{Atmosphere, Aerodynamics};
+pub use filter::CollisionFilter;
+pub use snapshot::{PhysicsSnapshot, Resolver, NoFunctions};
+pub use replay::{Recorder, Recording, ReplayReport, Divergence};
+pub use world::{PhysicsWorld, FIXED_DELTA_TIME};
 use crate::backend::{Backend};
 use crate::graphics::{GraphicsData, GraphicsInnerData};
+use snapshot::{FakeVector, FakeQuaternion, FakeMatrix};
 
 pub(crate) type PhysicsData = Vec<PhysicsTask>;
-pub type Object = u16;
 
-pub(self) const LIMIT_IMPULSE: bool = false;
-const MAX_DELTA_V: f64 = 100.0;
-const SCALE_ELASTICITY_VEL: f64 = 20.0;
+/// A handle to something in the game with a body or models. Slots are reused once their object
+/// is freed, under a new generation, so a stale handle never reaches the object which took its
+/// place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Object {
+    index: u32,
+    generation: u32,
+}
+
+impl Object {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
 
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
 
+/// Hands out objects, reusing the slots of freed ones
 pub struct ObjectManager {
-    max_id: Object,
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
 }
 
 impl ObjectManager {
     pub fn new() -> Self {
-        ObjectManager { max_id: 0 }
+        ObjectManager { generations: Vec::new(), alive: Vec::new(), free: Vec::new() }
     }
 
     pub fn get_object(&mut self) -> Object {
-        let id = self.max_id;
-        if self.max_id == Object::MAX {
-            panic!("Too many objects have been created");
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Object { index, generation: self.generations[index as usize] }
+            },
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Object { index: (self.generations.len() - 1) as u32, generation: 0 }
+            },
         }
-        self.max_id += 1;
-        id
+    }
+
+    /// Free an object so that its slot can be reused. Returns false if it was already freed.
+    pub fn free_object(&mut self, object: Object) -> bool {
+        if !self.is_alive(object) {
+            return false;
+        }
+        self.generations[object.index as usize] += 1;
+        self.alive[object.index as usize] = false;
+        self.free.push(object.index);
+        true
+    }
+
+    /// Whether the object has been handed out and not yet freed
+    pub fn is_alive(&self, object: Object) -> bool {
+        self.generations.get(object.index as usize) == Some(&object.generation)
+            && self.alive[object.index as usize]
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Updater {
     Fixed,
     Free,
-    Line,
-    Circle,
+    /// Kinematic motion back and forth along a rail, starting at origin. The phase is the distance
+    /// travelled so far; with an infinite length the body moves along the axis forever.
+    Line {
+        #[serde(with = "FakeVector")]
+        origin: Vector3<f64>,
+        #[serde(with = "FakeVector")]
+        axis: Vector3<f64>,
+        speed: f64,
+        length: f64,
+        phase: f64,
+    },
+    /// Kinematic motion around a circle, turning with it like a station ring. The phase is the
+    /// angle from reference, which is a unit vector perpendicular to the axis.
+    Circle {
+        #[serde(with = "FakeVector")]
+        center: Vector3<f64>,
+        #[serde(with = "FakeVector")]
+        axis: Vector3<f64>,
+        #[serde(with = "FakeVector")]
+        reference: Vector3<f64>,
+        radius: f64,
+        ang_speed: f64,
+        phase: f64,
+    },
+    /// Keplerian motion about a fixed center, propagated in closed form. The body passes perigee
+    /// when the orbit's clock reads perigee_time. Periapsis points towards perigee, which a
+    /// circular orbit's zero eccentricity vector cannot do.
     Orbit {
+        #[serde(with = "FakeVector")]
         center: Vector3<f64>,
+        #[serde(with = "FakeVector")]
         eccentricity: Vector3<f64>,
+        #[serde(with = "FakeVector")]
         ang_mom: Vector3<f64>,
         mu: f64,
         perigee_time: f64,
+        #[serde(with = "FakeVector")]
+        periapsis: Vector3<f64>,
+        time: f64,
     },
 }
 
@@ -59,14 +160,100 @@ pub enum PhysicsTask {
     AddLocalImpulseTorque(Object, Vector3<f64>),
     AddGlobalImpulseTorque(Object, Vector3<f64>),
     ShiftPos(Object, Vector3<f64>),
+    CreateJoint(JointId, Joint),
+    DestroyJoint(JointId),
+    /// Add a body to the world. A body the object already had is removed first, with its joints.
+    Spawn(Object, Box<RigidBody>),
+    /// Remove a body from the world, with its joints and contacts
+    Despawn(Object),
+    /// Move a body without blending its drawn pose from where it was
+    SetPos(Object, Vector3<f64>),
+    /// Set a body's velocity, dropping the impulses queued for it so far this step
+    SetVel(Object, Vector3<f64>),
+    SetOrientation(Object, Quaternion<f64>),
+    /// Set a body's angular velocity, dropping the torque impulses queued for it so far this step
+    SetAngVel(Object, Vector3<f64>),
+    /// Set a body's mass and its moment of inertia in its own frame
+    SetMassProperties(Object, f64, Matrix3<f64>),
+    SetUpdater(Object, Updater),
+    /// Replace a body's colliders. Its contacts are forgotten, since they refer to the old ones.
+    SetColliders(Object, Vec<Collider>),
+    /// Ask for the body's state at the end of the step. The answer comes back through
+    /// Renderer::on_body_state, or PhysicsWorld::take_body_states when stepping a world directly.
+    RequestState(Object),
+    SetCollisionFilter(Object, CollisionFilter),
+    /// Stop two bodies colliding with each other, whatever their filters, such as a ship and the
+    /// shots it fires. The rule is dropped when either body is removed.
+    IgnoreCollisions(Object, Object),
+    /// Undo IgnoreCollisions
+    RestoreCollisions(Object, Object),
+}
+
+/// A collision reported back to the game. Events are sent when two colliders start touching and
+/// whenever they strike each other hard enough to bounce.
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub a: Object,
+    pub b: Object,
+    /// The pieces of each body which touched, such as a part attached to a ship
+    pub part_a: ColliderPart,
+    pub part_b: ColliderPart,
+    /// Middle of the contact, in world coordinates
+    pub point: Vector3<f64>,
+    /// Points out of a into b
+    pub normal: Vector3<f64>,
+    /// Speed at which the bodies were closing along the normal
+    pub relative_speed: f64,
+    /// Total impulse along the normal needed to stop them
+    pub impulse: f64,
+}
+
+/// A body entering or leaving a sensor, reported at the end of the step in which it happened
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SensorEvent {
+    pub sensor: Object,
+    /// The piece of the sensor which the body entered or left
+    pub part: ColliderPart,
+    pub body: Object,
+    pub kind: SensorEventKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SensorEventKind {
+    Enter,
+    /// Also sent when either body is removed while the other is inside
+    Exit,
+}
+
+/// Everything about where a body is and how it moves
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BodyState {
+    #[serde(with = "FakeVector")]
+    pub pos: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    pub vel: Vector3<f64>,
+    #[serde(with = "FakeQuaternion")]
+    pub orientation: Quaternion<f64>,
+    #[serde(with = "FakeVector")]
+    pub ang_vel: Vector3<f64>,
+    pub mass: f64,
+    #[serde(with = "FakeMatrix")]
+    pub local_moi: Matrix3<f64>,
+    /// Simulated time at which the state was read
+    pub time: f64,
 }
 
 pub(crate) struct Physics<F: Fn(&mut Vec<PhysicsTask>, (&Object, &RigidBody), (&Object, &RigidBody))> {
     physics_data_receiver: Receiver<PhysicsData>,
-    physics_data_sender: Sender<PhysicsData>,
     graphics_data_sender: Sender<GraphicsData>,
+    collision_event_sender: Sender<Vec<CollisionEvent>>,
+    sensor_event_sender: Sender<Vec<SensorEvent>>,
+    body_state_sender: Sender<Vec<(Object, Option<BodyState>)>>,
+    broken_joint_sender: Sender<Vec<JointId>>,
     interaction: F,
-    pub(crate) rigid_bodies: FxHashMap<Object, RigidBody>,
+    /// Shared with the game thread's PhysicsQueries
+    pub(crate) world: Arc<RwLock<PhysicsWorld>>,
+    recorder: Option<Recorder<BufWriter<File>>>,
 }
 
 impl<F: Fn(&mut Vec<PhysicsTask>, (&Object, &RigidBody), (&Object, &RigidBody))> Physics<F> {
@@ -75,143 +262,87 @@ impl<F: Fn(&mut Vec<PhysicsTask>, (&Object, &RigidBody), (&Object, &RigidBody))>
             Some(r) => r,
             None => panic!("Someone picked up the physics data receiver")
         };
-        let physics_data_sender = backend.physics_data_sender.clone();
         let graphics_data_sender = backend.graphics_data_sender.clone();
+        let collision_event_sender = backend.collision_event_sender.clone();
+        let sensor_event_sender = backend.sensor_event_sender.clone();
+        let body_state_sender = backend.body_state_sender.clone();
+        let broken_joint_sender = backend.broken_joint_sender.clone();
 
         Self {
             physics_data_receiver,
-            physics_data_sender,
             graphics_data_sender,
+            collision_event_sender,
+            sensor_event_sender,
+            body_state_sender,
+            broken_joint_sender,
             interaction,
-            rigid_bodies: FxHashMap::default(),
+            world: backend.physics_world.clone(),
+            recorder: None,
         }
     }
 
+    /// Record every frame from now on to a file, to be replayed with Recording
+    pub(crate) fn record(&mut self, path: &Path) -> Result<()> {
+        self.recorder = Some(Recorder::create(path, &self.world.read().unwrap())?);
+        Ok(())
+    }
+
     pub(crate) fn add_body(&mut self, object: Object, body: RigidBody) {
-        self.rigid_bodies.insert(object, body);
+        self.world.write().unwrap().add_body(object, body);
     }
 
     pub(crate) fn update(&mut self, delta_time: f32) {
-        let mut interaction_forces = Vec::new();
-        // Detect and act on collisions
-        for (o_i, rb_i) in self.rigid_bodies.iter() {
-            for (o_j, rb_j) in self.rigid_bodies.iter() {
-                if o_j <= o_i {
-                    continue;
-                }
-
-                (self.interaction)(&mut interaction_forces, (o_i, rb_i), (o_j, rb_j));
-                
-                if let Some((t, n, r)) = rb_i.detect_collision(&rb_j, delta_time as f64) {
-                    // Convert to mutable references. This is safe because they are non-identical and I'm not editing the hash.
-                    let (rb_i, rb_j): (&mut RigidBody, &mut RigidBody) = unsafe {
-                        let i_ptr = rb_i as *const _ as *mut _;
-                        let j_ptr = rb_j as *const _ as *mut _;
-                        (&mut *i_ptr, &mut *j_ptr)
-                    };
-
-                    // Update rbs up to the moment of collision
-                    rb_i.update_forceless(t);
-                    rb_j.update_forceless(t);
-
-                    // Compute collision impulse
-                    let r1 = r;
-                    let r2 = r - (rb_j.pos - rb_i.pos);
-                    let rel_vel = rb_j.vel + rb_j.ang_vel.cross(r1) - rb_i.vel - rb_i.ang_vel.cross(r2);
-                    let elasticity = (rb_i.elasticity * rb_j.elasticity).sqrt() * (-rel_vel.magnitude() / SCALE_ELASTICITY_VEL).exp();
-                    let normal = n.normalize();
-                    let (minimum_mass, i_denom) = match rb_i.updater {
-                        Updater::Fixed => (f64::INFINITY, 0.0),
-                        _ => (rb_i.mass, 1.0 / rb_i.mass + normal.dot(rb_i.moi_inv() * (r1.cross(normal).cross(r1))))
-                    };
-                    let (minimum_mass, j_denom) = match rb_j.updater {
-                        Updater::Fixed => (minimum_mass, 0.0),
-                        _ => (if rb_j.mass < minimum_mass {rb_j.mass} else {minimum_mass},
-                            1.0 / rb_j.mass + normal.dot(rb_j.moi_inv() * (r2.cross(normal).cross(r2))))
-                    };
-                    let mut impulse = -(1.0 + elasticity) * normal.dot(rel_vel) * normal / (i_denom + j_denom);
-
-                    let impulse_limit = minimum_mass * MAX_DELTA_V;
-                    if LIMIT_IMPULSE && impulse.magnitude2() < impulse_limit * impulse_limit {
-                        println!("Impulse limit {} exceeded!", impulse_limit);
-                        impulse = impulse.normalize();
-                    }
-                    let torque_impulse = r1.cross(impulse);
-
-                    // Apply impulse
-                    rb_i.impulse -= impulse;
-                    rb_i.torque_impulse -= torque_impulse;
-                    rb_i.collide_data = Some((delta_time - t as f32, r2, normal));
-
-                    rb_j.impulse += impulse;
-                    rb_j.torque_impulse += torque_impulse;
-                    rb_j.collide_data = Some((delta_time - t as f32, r1, -normal));
-                }
+        // Hold the world only while stepping it and copying out what the game needs, so that
+        // queries from the game thread aren't kept waiting
+        let mut world = self.world.write().unwrap();
+        for task_vec in self.physics_data_receiver.try_iter() {
+            if let Some(recorder) = &mut self.recorder {
+                recorder.batch(&task_vec);
             }
+            world.push_tasks(task_vec);
         }
+        world.advance_with(delta_time as f64, &self.interaction);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.frame(delta_time as f64, &world);
+        }
+        let events = world.take_collision_events();
+        let sensor_events = world.take_sensor_events();
+        let states = world.take_body_states();
+        let broken_joints = world.take_broken_joints();
+        // Poses for graphics, interpolated between the last two steps
+        let poses = world.bodies()
+            .map(|(object, body)| {
+                let (pos, orientation) = match world.interpolated_pose(object) {
+                    Some(pose) => pose,
+                    None => (body.get_pos(), body.orientation),
+                };
+                (*object, GraphicsInnerData {
+                    push_constants: body.push_constants_at(orientation),
+                    pos,
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(world);
 
-        self.physics_data_sender.send(interaction_forces).unwrap();
-
-        // Add forces
-        for task_vec in self.physics_data_receiver.try_iter() {
-            for task in task_vec.into_iter() {
-                match task {
-                    PhysicsTask::AddGlobalForce(object, force) => {
-                        if let Some(rb) = self.rigid_bodies.get_mut(&object) {
-                            rb.impulse += force * delta_time as f64;
-                        }
-                    },
-                    PhysicsTask::AddGlobalImpulse(object, impulse) => {
-                        if let Some(rb) = self.rigid_bodies.get_mut(&object) {
-                            rb.impulse += impulse;
-                        }
-                    },
-                    PhysicsTask::AddLocalForce(object, force) => {
-                        if let Some(rb) = self.rigid_bodies.get_mut(&object) {
-                            let force = rb.orientation * force;
-                            rb.impulse += force * delta_time as f64;
-                        }
-                    },
-                    PhysicsTask::AddLocalImpulse(object, impulse) => {
-                        if let Some(rb) = self.rigid_bodies.get_mut(&object) {
-                            let force = rb.orientation * impulse / delta_time as f64;
-                            rb.impulse += force * delta_time as f64;
-                        }
-                    },
-                    PhysicsTask::AddLocalImpulseTorque(object, impulse) => {
-                        if let Some(rb) = self.rigid_bodies.get_mut(&object) {
-                            rb.torque_impulse += rb.orientation * impulse;
-                        }
-                    },
-                    PhysicsTask::AddGlobalImpulseTorque(object, impulse) => {
-                        if let Some(rb) = self.rigid_bodies.get_mut(&object) {
-                            rb.torque_impulse += impulse;
-                        }
-                    },
-                    PhysicsTask::ShiftPos(object, delta) => {
-                        if let Some(rb) = self.rigid_bodies.get_mut(&object) {
-                            rb.pos += delta;
-                        }
-                    }
-                }
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.write() {
+                eprintln!("Stopped recording physics: {}", e);
+                self.recorder = None;
             }
         }
-
-        // Update body position
-        for body in self.rigid_bodies.values_mut() {
-            body.update(delta_time as f64);
+        if !events.is_empty() {
+            self.collision_event_sender.send(events).unwrap_or(());
         }
-
-        // Send data to graphics
-        let mut graphics_data = FxHashMap::default();//with_capacity(self.rigid_bodies.len());
-        for (object, body) in &self.rigid_bodies {
-            let pos = body.get_pos();
-            graphics_data.insert(*object, GraphicsInnerData {
-                push_constants: body.push_constants(),
-                pos: Vector3::new(pos.x as f32, pos.y as f32, pos.z as f32),
-            });
+        if !sensor_events.is_empty() {
+            self.sensor_event_sender.send(sensor_events).unwrap_or(());
         }
-
+        if !states.is_empty() {
+            self.body_state_sender.send(states).unwrap_or(());
+        }
+        if !broken_joints.is_empty() {
+            self.broken_joint_sender.send(broken_joints).unwrap_or(());
+        }
+        let graphics_data = poses.into_iter().collect::<FxHashMap<_, _>>();
         self.graphics_data_sender.send(graphics_data).unwrap_or(());
     }
-}
\ No newline at end of file
+}
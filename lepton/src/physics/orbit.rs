@@ -0,0 +1,101 @@
+//! Closed form propagation of Keplerian orbits. Positions are found from the time since perigee by
+//! solving Kepler's equation, so an orbit can be advanced by any amount of time without drift.
+
+use cgmath::{Vector3, InnerSpace};
+use std::f64::consts::PI;
+
+const TOL: f64 = 1.0e-13;
+const MAX_ITERATIONS: u32 = 64;
+/// Orbits with eccentricities this close to one are treated as parabolas
+const PARABOLIC_TOL: f64 = 1.0e-9;
+
+/// The period of the orbit, or None if it is open
+pub fn period(mu: f64, eccentricity: Vector3<f64>, ang_mom: Vector3<f64>) -> Option<f64> {
+    let e = eccentricity.magnitude();
+    if e >= 1.0 - PARABOLIC_TOL {
+        return None;
+    }
+    let a = ang_mom.magnitude2() / mu / (1.0 - e * e);
+    Some(2.0 * PI * (a.powi(3) / mu).sqrt())
+}
+
+/// Get the position of a satellite relative to the center given the time since it passed through
+/// perigee. The periapsis is a unit vector towards perigee; it is only used when the eccentricity
+/// is too small to give a direction.
+pub fn get_pos(mu: f64, time_since_perigee: f64, eccentricity: Vector3<f64>, ang_mom: Vector3<f64>, periapsis: Vector3<f64>) -> Vector3<f64> {
+    get_state(mu, time_since_perigee, eccentricity, ang_mom, periapsis).0
+}
+
+/// Get the position and velocity of a satellite relative to the center given the time since it
+/// passed through perigee.
+pub fn get_state(mu: f64, time_since_perigee: f64, eccentricity: Vector3<f64>, ang_mom: Vector3<f64>, periapsis: Vector3<f64>)
+    -> (Vector3<f64>, Vector3<f64>) {
+    let nu = true_anomaly(mu, time_since_perigee, eccentricity, ang_mom);
+    let e = eccentricity.magnitude();
+    let p = ang_mom.magnitude2() / mu;
+
+    let p_hat = if e > 0.0 { eccentricity / e } else { periapsis };
+    let q_hat = ang_mom.normalize().cross(p_hat);
+    let r = p / (1.0 + e * nu.cos());
+    let pos = (p_hat * nu.cos() + q_hat * nu.sin()) * r;
+    let vel = (-p_hat * nu.sin() + q_hat * (e + nu.cos())) * (mu / p).sqrt();
+    (pos, vel)
+}
+
+/// The angle from perigee of a satellite at the given time since perigee
+pub fn true_anomaly(mu: f64, time_since_perigee: f64, eccentricity: Vector3<f64>, ang_mom: Vector3<f64>) -> f64 {
+    let e = eccentricity.magnitude();
+    let p = ang_mom.magnitude2() / mu;
+
+    if (e - 1.0).abs() < PARABOLIC_TOL {
+        // Parabola: Barker's equation D + D^3 / 3 = 2 sqrt(mu / p^3) t has a closed form solution
+        let w = 3.0 * (mu / p.powi(3)).sqrt() * time_since_perigee;
+        let y = (w + (w * w + 1.0).sqrt()).cbrt();
+        2.0 * (y - 1.0 / y).atan()
+    } else if e > 1.0 {
+        // Hyperbola: solve e sinh F - F = M
+        let a = p / (e * e - 1.0);
+        let target = (mu / a.powi(3)).sqrt() * time_since_perigee;
+        let mut f = (target / e).asinh();
+        for _ in 0..MAX_ITERATIONS {
+            let step = (e * f.sinh() - f - target) / (e * f.cosh() - 1.0);
+            f -= step;
+            if step.abs() < TOL * (1.0 + f.abs()) {
+                break;
+            }
+        }
+        2.0 * ((e + 1.0).sqrt() * (f / 2.0).sinh()).atan2((e - 1.0).sqrt() * (f / 2.0).cosh())
+    } else {
+        // Ellipse: solve E - e sin E = M, with M reduced to one period
+        let a = p / (1.0 - e * e);
+        let target = ((mu / a.powi(3)).sqrt() * time_since_perigee).rem_euclid(2.0 * PI);
+        let mut f = if e > 0.8 { PI } else { target };
+        for _ in 0..MAX_ITERATIONS {
+            let step = (f - e * f.sin() - target) / (1.0 - e * f.cos());
+            f -= step;
+            if step.abs() < TOL {
+                break;
+            }
+        }
+        2.0 * ((1.0 + e).sqrt() * (f / 2.0).sin()).atan2((1.0 - e).sqrt() * (f / 2.0).cos())
+    }
+}
+
+/// The time since perigee of a satellite at the given true anomaly. This inverts true_anomaly.
+pub fn time_since_perigee(mu: f64, nu: f64, eccentricity: Vector3<f64>, ang_mom: Vector3<f64>) -> f64 {
+    let e = eccentricity.magnitude();
+    let p = ang_mom.magnitude2() / mu;
+
+    if (e - 1.0).abs() < PARABOLIC_TOL {
+        let d = (nu / 2.0).tan();
+        (d + d.powi(3) / 3.0) / 2.0 / (mu / p.powi(3)).sqrt()
+    } else if e > 1.0 {
+        let a = p / (e * e - 1.0);
+        let f = 2.0 * ((e - 1.0).sqrt() / (e + 1.0).sqrt() * (nu / 2.0).tan()).atanh();
+        (e * f.sinh() - f) / (mu / a.powi(3)).sqrt()
+    } else {
+        let a = p / (1.0 - e * e);
+        let f = 2.0 * ((1.0 - e).sqrt() * (nu / 2.0).sin()).atan2((1.0 + e).sqrt() * (nu / 2.0).cos());
+        (f - e * f.sin()) / (mu / a.powi(3)).sqrt()
+    }
+}
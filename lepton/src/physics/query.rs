@@ -0,0 +1,102 @@
+use std::sync::{Arc, RwLock};
+use cgmath::{Vector3, InnerSpace};
+
+use super::{Object, PhysicsWorld, ColliderPart, JointId, leaves};
+
+/// Where a ray or swept sphere first touches a collider
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub object: Object,
+    /// The piece of the body which was hit
+    pub part: ColliderPart,
+    /// How far along the ray the hit is
+    pub distance: f64,
+    /// The point of the collider which was touched, in world coordinates
+    pub point: Vector3<f64>,
+    /// Surface normal at the point, facing back towards the ray
+    pub normal: Vector3<f64>,
+}
+
+impl PhysicsWorld {
+    /// The first collider hit by a ray from origin along dir, within max_distance. Objects in
+    /// exclude are ignored, such as the ship the ray is fired from.
+    pub fn raycast(&self, origin: Vector3<f64>, dir: Vector3<f64>, max_distance: f64, exclude: &[Object]) -> Option<RayHit> {
+        self.sphere_cast(origin, 0.0, dir, max_distance, exclude)
+    }
+
+    /// The first collider touched by a sphere swept from origin along dir, within max_distance.
+    /// Objects in exclude are ignored, and sensors let the sphere through.
+    pub fn sphere_cast(&self, origin: Vector3<f64>, radius: f64, dir: Vector3<f64>, max_distance: f64, exclude: &[Object]) -> Option<RayHit> {
+        if dir.magnitude2() == 0.0 {
+            return None;
+        }
+        let dir = dir.normalize();
+        let mut best: Option<RayHit> = None;
+        for (object, body) in self.bodies() {
+            if exclude.contains(object) || body.sensor {
+                continue;
+            }
+            let reach = best.map_or(max_distance, |hit| hit.distance);
+            for (part, collider, pos, orientation) in leaves(&body.colliders, body.pos, body.orientation) {
+                if let Some((distance, point, normal)) = collider.cast_sphere(pos, orientation, origin, dir, radius, reach) {
+                    // Ties go to the lower object so that the answer doesn't depend on the hash map
+                    let better = best.is_none_or(|hit| distance < hit.distance || (distance == hit.distance && *object < hit.object));
+                    if better {
+                        best = Some(RayHit { object: *object, part, distance, point, normal });
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Every object with a collider that overlaps the sphere, in increasing order
+    pub fn overlap_sphere(&self, center: Vector3<f64>, radius: f64) -> Vec<Object> {
+        let mut objects = self.bodies()
+            .filter(|(_, body)| leaves(&body.colliders, body.pos, body.orientation).into_iter()
+                .any(|(_, c, pos, orientation)| c.overlaps_sphere(pos, orientation, center, radius)))
+            .map(|(object, _)| *object)
+            .collect::<Vec<_>>();
+        objects.sort_unstable();
+        objects
+    }
+}
+
+/// A handle to the physics world which can be kept on the game thread to answer queries. Each
+/// query waits for the physics thread to finish any step it is taking.
+#[derive(Clone)]
+pub struct PhysicsQueries {
+    world: Arc<RwLock<PhysicsWorld>>,
+}
+
+impl PhysicsQueries {
+    pub(crate) fn new(world: Arc<RwLock<PhysicsWorld>>) -> Self {
+        Self { world }
+    }
+
+    pub fn raycast(&self, origin: Vector3<f64>, dir: Vector3<f64>, max_distance: f64, exclude: &[Object]) -> Option<RayHit> {
+        self.world.read().unwrap().raycast(origin, dir, max_distance, exclude)
+    }
+
+    pub fn sphere_cast(&self, origin: Vector3<f64>, radius: f64, dir: Vector3<f64>, max_distance: f64, exclude: &[Object]) -> Option<RayHit> {
+        self.world.read().unwrap().sphere_cast(origin, radius, dir, max_distance, exclude)
+    }
+
+    pub fn overlap_sphere(&self, center: Vector3<f64>, radius: f64) -> Vec<Object> {
+        self.world.read().unwrap().overlap_sphere(center, radius)
+    }
+
+    pub fn bodies_in_sensor(&self, sensor: Object) -> Vec<Object> {
+        self.world.read().unwrap().bodies_in_sensor(sensor)
+    }
+
+    /// Force a joint applied to its second body during the last step, or None once it is gone
+    pub fn joint_force(&self, id: JointId) -> Option<Vector3<f64>> {
+        self.world.read().unwrap().get_joint(&id).map(|joint| joint.force())
+    }
+
+    /// Torque a joint applied to its second body during the last step, or None once it is gone
+    pub fn joint_torque(&self, id: JointId) -> Option<Vector3<f64>> {
+        self.world.read().unwrap().get_joint(&id).map(|joint| joint.torque())
+    }
+}
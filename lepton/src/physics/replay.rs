@@ -0,0 +1,171 @@
+use std::fs::File;
+use std::io::{Read, Write, BufReader, BufWriter, ErrorKind};
+use std::path::Path;
+use anyhow::Result;
+use cgmath::InnerSpace;
+use rustc_hash::FxHashMap;
+use serde::{Serialize, Deserialize};
+
+use super::{Object, RigidBody, PhysicsTask, PhysicsWorld, BodyState, PhysicsSnapshot, Resolver};
+use super::snapshot::TaskSnapshot;
+
+/// One call to advance: the task batches pushed before it, its frame time, and every body after
+/// it in order of object
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    delta_time: f64,
+    batches: Vec<Vec<TaskSnapshot>>,
+    states: Vec<(Object, BodyState)>,
+}
+
+fn body_states(world: &PhysicsWorld) -> Vec<(Object, BodyState)> {
+    let mut states = world.bodies()
+        .map(|(object, body)| (*object, body.state(world.time())))
+        .collect::<Vec<_>>();
+    states.sort_unstable_by_key(|(object, _)| *object);
+    states
+}
+
+/// Writes a world's frames out as it runs, starting with a snapshot of the world. Frames are
+/// noted while the world is held and written afterwards, and each write is flushed, so a
+/// recording survives the game being closed or crashing.
+pub struct Recorder<W: Write> {
+    writer: W,
+    batches: Vec<Vec<TaskSnapshot>>,
+    frames: Vec<Frame>,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Record to a new file, replacing any already at path
+    pub fn create<P: AsRef<Path>>(path: P, world: &PhysicsWorld) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), world)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start recording with the world as it is now
+    pub fn new(mut writer: W, world: &PhysicsWorld) -> Result<Self> {
+        bincode::serialize_into(&mut writer, &world.snapshot())?;
+        writer.flush()?;
+        Ok(Self { writer, batches: Vec::new(), frames: Vec::new() })
+    }
+
+    /// Note a batch of tasks as it is pushed to the world
+    pub fn batch(&mut self, tasks: &[PhysicsTask]) {
+        self.batches.push(tasks.iter().map(TaskSnapshot::new).collect());
+    }
+
+    /// Note the frame once the world has been advanced by delta_time, to be written by write
+    pub fn frame(&mut self, delta_time: f64, world: &PhysicsWorld) {
+        self.frames.push(Frame {
+            delta_time,
+            batches: std::mem::take(&mut self.batches),
+            states: body_states(world),
+        });
+    }
+
+    /// Write out the frames noted so far. This needn't hold the world.
+    pub fn write(&mut self) -> Result<()> {
+        for frame in self.frames.drain(..) {
+            bincode::serialize_into(&mut self.writer, &frame)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A recorded run, which can be replayed without the backend to see whether it comes out the same
+pub struct Recording {
+    initial: PhysicsSnapshot,
+    frames: Vec<Frame>,
+}
+
+/// The first body found to differ at the first frame where any did
+#[derive(Clone, Copy, Debug)]
+pub struct Divergence {
+    pub frame: usize,
+    pub object: Object,
+    /// The body at the end of the frame in each run, or None if that run didn't have it
+    pub recorded: Option<BodyState>,
+    pub replayed: Option<BodyState>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReplayReport {
+    pub frames: usize,
+    pub divergence: Option<Divergence>,
+    /// Furthest any body strayed from its recorded position over the whole replay
+    pub max_pos_error: f64,
+}
+
+impl Recording {
+    /// Read a recording written by a Recorder. A frame cut short, as when the game was killed
+    /// while writing it, ends the recording.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let initial = bincode::deserialize_from(&mut reader)?;
+        let mut frames = Vec::new();
+        loop {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push(frame),
+                Err(e) => match *e {
+                    bincode::ErrorKind::Io(ref io) if io.kind() == ErrorKind::UnexpectedEof => break,
+                    _ => return Err(e.into()),
+                },
+            }
+        }
+        Ok(Self { initial, frames })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Feed the recorded tasks and frame times to a fresh world, comparing every body with the
+    /// recording after each frame. The interaction must be the one the recorded world used, since
+    /// the tasks it pushes aren't recorded. The resolver supplies the functions which the
+    /// recording couldn't hold.
+    pub fn replay<R, F>(self, resolver: &mut R, interaction: F) -> Result<ReplayReport>
+    where R: Resolver, F: Fn(&mut Vec<PhysicsTask>, (&Object, &RigidBody), (&Object, &RigidBody)) {
+        let mut world = PhysicsWorld::from_snapshot(self.initial, resolver)?;
+        let mut report = ReplayReport { frames: 0, divergence: None, max_pos_error: 0.0 };
+        for (i, frame) in self.frames.into_iter().enumerate() {
+            for batch in frame.batches {
+                world.push_tasks(batch.into_iter().map(|task| task.restore(resolver)).collect::<Result<Vec<_>>>()?);
+            }
+            world.advance_with(frame.delta_time, &interaction);
+            world.take_collision_events();
+            world.take_sensor_events();
+            world.take_body_states();
+            world.take_broken_joints();
+            report.compare(i, &frame.states, &body_states(&world));
+            report.frames += 1;
+        }
+        Ok(report)
+    }
+}
+
+impl ReplayReport {
+    fn compare(&mut self, frame: usize, recorded: &[(Object, BodyState)], replayed: &[(Object, BodyState)]) {
+        let recorded = recorded.iter().copied().collect::<FxHashMap<_, _>>();
+        let replayed = replayed.iter().copied().collect::<FxHashMap<_, _>>();
+        let mut objects = recorded.keys().chain(replayed.keys()).copied().collect::<Vec<_>>();
+        objects.sort_unstable();
+        objects.dedup();
+        for object in objects {
+            let (a, b) = (recorded.get(&object).copied(), replayed.get(&object).copied());
+            if a == b {
+                continue;
+            }
+            if let (Some(a), Some(b)) = (a, b) {
+                self.max_pos_error = self.max_pos_error.max((a.pos - b.pos).magnitude());
+            }
+            if self.divergence.is_none() {
+                self.divergence = Some(Divergence { frame, object, recorded: a, replayed: b });
+            }
+        }
+    }
+}
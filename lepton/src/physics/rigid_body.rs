@@ -1,10 +1,16 @@
-use cgmath::{Vector3, Matrix3, Quaternion, Matrix4, Matrix, Zero, InnerSpace, SquareMatrix};
+use cgmath::{Vector3, Matrix3, Quaternion, Matrix4, Matrix, Zero, InnerSpace, SquareMatrix, Rotation3, Rad};
 
 use crate::shader::builtin;
-use super::{Updater, Collider, collider::CollisionData};
+use super::{Object, BodyState, Updater, Integrator, RotationIntegrator, Field, GravityRole, Atmosphere, Aerodynamics, CollisionFilter, Collider, ColliderPart, Contact, orbit, leaves, shifted_inertia};
 
-/// Accept collision info when it is accurate to this fraction of delta t
-const COLLIDE_ACCEPTANCE: f64 = 0.1; 
+/// Conservative advancement gives up on finding the time of impact after this many steps
+const MAX_TOI_ITERATIONS: usize = 32;
+/// Colliders closer than this are touching
+const TOI_TOLERANCE: f64 = 1e-4;
+/// How far touching colliders may overlap once they are moved on to measure the contact
+const TOI_PENETRATION: f64 = 5e-3;
+const DEFAULT_STATIC_FRICTION: f64 = 0.6;
+const DEFAULT_DYNAMIC_FRICTION: f64 = 0.4;
 
 pub struct RigidBody {
     pub pos: Vector3<f64>,
@@ -19,11 +25,34 @@ pub struct RigidBody {
     pub local_moi_inv: Matrix3<f64>,
     
     pub updater: Updater,
+    pub integrator: Integrator,
+    pub rotation_integrator: RotationIntegrator,
+    /// Acceleration sampled by the integrator during the step, such as gravity from a body which
+    /// doesn't move
+    pub field: Option<Field>,
+    pub gravity_role: GravityRole,
+    /// Air around the body, with altitude measured by its planet collider
+    pub atmosphere: Option<Atmosphere>,
+    pub aerodynamics: Option<Aerodynamics>,
     
     pub colliders: Vec<Collider>,
+    pub collision_filter: CollisionFilter,
+    /// Reports the bodies inside its colliders instead of colliding with them
+    pub sensor: bool,
     pub elasticity: f64,
+    /// Coulomb friction coefficient which holds a resting contact in place
+    pub static_friction: f64,
+    /// Coulomb friction coefficient once a contact slides
+    pub dynamic_friction: f64,
     pub model_offset: Matrix4<f32>,
-    pub collide_data: Option<(f32, Vector3<f64>, Vector3<f64>)>, // delta_t so far, pos, normal
+    /// Asleep bodies are not moved, and are only tested against bodies which are awake
+    pub asleep: bool,
+    /// How long the body has been slow enough to fall asleep
+    pub sleep_time: f64,
+    pub can_sleep: bool,
+    /// The kinematic body a sleeping body rests on and is carried along by
+    pub carrier: Option<Object>,
+    pub collide_time: Option<f64>, // How far into the step collisions have already moved the body
 }
 
 impl RigidBody {
@@ -39,10 +68,24 @@ impl RigidBody {
             local_moi: Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0),
             local_moi_inv: Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0),
             updater: Updater::Fixed,
+            integrator: Integrator::SemiImplicitEuler,
+            rotation_integrator: RotationIntegrator::Euler,
+            field: None,
+            gravity_role: GravityRole::Inert,
+            atmosphere: None,
+            aerodynamics: None,
             colliders: Vec::new(),
+            collision_filter: CollisionFilter::default(),
+            sensor: false,
             elasticity: 1.0,
+            static_friction: DEFAULT_STATIC_FRICTION,
+            dynamic_friction: DEFAULT_DYNAMIC_FRICTION,
             model_offset: Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)),
-            collide_data: None,
+            asleep: false,
+            sleep_time: 0.0,
+            can_sleep: true,
+            carrier: None,
+            collide_time: None,
         }
     }
 
@@ -58,10 +101,24 @@ impl RigidBody {
             local_moi: Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0),
             local_moi_inv: Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0),
             updater: Updater::Fixed,
+            integrator: Integrator::SemiImplicitEuler,
+            rotation_integrator: RotationIntegrator::Euler,
+            field: None,
+            gravity_role: GravityRole::Inert,
+            atmosphere: None,
+            aerodynamics: None,
             colliders: Vec::new(),
+            collision_filter: CollisionFilter::default(),
+            sensor: false,
             elasticity: 1.0,
+            static_friction: DEFAULT_STATIC_FRICTION,
+            dynamic_friction: DEFAULT_DYNAMIC_FRICTION,
             model_offset: Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0)),
-            collide_data: None,
+            asleep: false,
+            sleep_time: 0.0,
+            can_sleep: true,
+            carrier: None,
+            collide_time: None,
         }
     }
 
@@ -78,23 +135,38 @@ impl RigidBody {
         self
     }
 
+    /// Put the body on a Keplerian orbit about center, at the point of the orbit in the direction
+    /// of its current position. The orbit's clock reads perigee_time when the body passes perigee.
     pub fn orbit(mut self, mu: f64, center: Vector3<f64>, eccentricity: Vector3<f64>, ang_mom: Vector3<f64>, perigee_time: f64) -> Self {
-        let hxr = ang_mom.cross(self.pos);
-        let r = self.pos.magnitude();
-        self.vel = hxr / (r*r) + mu / (ang_mom.magnitude2() * r) * hxr.cross(self.pos.cross(eccentricity));
+        let normal = ang_mom.normalize();
+        let periapsis = if eccentricity.magnitude2() > 0.0 {
+            eccentricity.normalize()
+        } else {
+            perpendicular(normal)
+        };
+        let arm = self.pos - center;
+        let arm = arm - normal * normal.dot(arm);
+        let nu = arm.dot(normal.cross(periapsis)).atan2(arm.dot(periapsis));
         self.updater = Updater::Orbit{
             center,
             eccentricity,
             ang_mom,
             mu,
             perigee_time,
+            periapsis,
+            time: perigee_time + orbit::time_since_perigee(mu, nu, eccentricity, ang_mom),
         };
+        self.kinematic_step(0.0);
         self
     }
 
+    /// Put the body on a circular orbit about center through its current position, moving
+    /// clockwise about normal. Any offset along the normal is removed.
     pub fn circ_orbit(mut self, mu: f64, center: Vector3<f64>, normal: Vector3<f64>) -> Self {
-        let ang_mom  = (mu * (self.pos - center).magnitude()).sqrt() * normal.normalize();
-        self.vel = (self.pos - center).cross(ang_mom) / (self.pos - center).magnitude2();
+        let normal = normal.normalize();
+        let arm = self.pos - center;
+        let arm = arm - normal * normal.dot(arm);
+        let ang_mom = -(mu * arm.magnitude()).sqrt() * normal;
 
         self.updater = Updater::Orbit {
             center,
@@ -102,12 +174,62 @@ impl RigidBody {
             ang_mom,
             mu,
             perigee_time: 0.0,
+            periapsis: arm.normalize(),
+            time: 0.0,
+        };
+        self.kinematic_step(0.0);
+        self
+    }
+
+    /// Move the body back and forth along a rail of the given length, starting from its current
+    /// position. Use an infinite length for a conveyor that never turns around. A rail with no
+    /// length holds the body still at its origin.
+    pub fn line(mut self, axis: Vector3<f64>, speed: f64, length: f64) -> Self {
+        self.updater = Updater::Line {
+            origin: self.pos,
+            axis: axis.normalize(),
+            speed,
+            length,
+            phase: 0.0,
+        };
+        self.kinematic_step(0.0);
+        self
+    }
+
+    /// Carry the body around a circle about the axis through center, turning it as it goes. A body
+    /// placed on the axis spins in place.
+    pub fn circle(mut self, center: Vector3<f64>, axis: Vector3<f64>, ang_speed: f64) -> Self {
+        let axis = axis.normalize();
+        let center = center + axis * axis.dot(self.pos - center);
+        let arm = self.pos - center;
+        let radius = arm.magnitude();
+        let reference = if radius > 0.0 {
+            arm / radius
+        } else {
+            perpendicular(axis)
+        };
+        self.updater = Updater::Circle {
+            center,
+            axis,
+            reference,
+            radius,
+            ang_speed,
+            phase: 0.0,
         };
+        self.kinematic_step(0.0);
         self
     }
 
+    /// Make the body a source of the world's gravity with the given mass
     pub fn gravitate(mut self, mass: f64) -> Self {
         self.mass = mass;
+        self.gravity_role = GravityRole::Source;
+        self
+    }
+
+    /// Let the world's gravity sources pull the body, without it pulling back
+    pub fn attracted(mut self) -> Self {
+        self.gravity_role = GravityRole::Particle;
         self
     }
     
@@ -117,6 +239,99 @@ impl RigidBody {
         self
     }
 
+    /// Choose which groups the body is in and which it collides with
+    pub fn collision_filter(mut self, group: u32, mask: u32) -> Self {
+        self.collision_filter = CollisionFilter::new(group, mask);
+        self
+    }
+
+    /// Make the colliders into sensors, which other bodies pass through while the world reports
+    /// when they enter and leave
+    pub fn sensor(mut self) -> Self {
+        self.sensor = true;
+        self
+    }
+
+    /// Keep the body awake however slowly it moves
+    pub fn never_sleep(mut self) -> Self {
+        self.can_sleep = false;
+        self
+    }
+
+    /// Surround the body with air, which pushes on bodies with aerodynamics
+    pub fn atmosphere(mut self, atmosphere: Atmosphere) -> Self {
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    /// Let the air of other bodies' atmospheres drag and lift the body
+    pub fn aerodynamics(mut self, aerodynamics: Aerodynamics) -> Self {
+        self.aerodynamics = Some(aerodynamics);
+        self
+    }
+
+    /// Choose how the body's position and orientation are stepped while it is free
+    pub fn integrate(mut self, integrator: Integrator, rotation_integrator: RotationIntegrator) -> Self {
+        self.integrator = integrator;
+        self.rotation_integrator = rotation_integrator;
+        self
+    }
+
+    /// Give the body an acceleration that depends on where it is. Unlike forces pushed as tasks,
+    /// the integrator samples it at points within the step.
+    pub fn field(mut self, field: Field) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    /// Make the body free, with the mass and moment of inertia of its colliders filled at the
+    /// given density. The body turns about its position, so the colliders should be centered
+    /// on it. Call this after collide. A body whose colliders have no volume is left as it was.
+    pub fn solid(mut self, density: f64) -> Self {
+        let mass = density * self.colliders.iter().map(|c| c.volume()).sum::<f64>();
+        let local_moi = self.colliders.iter()
+            .map(|c| c.inertia(density) + shifted_inertia(density * c.volume(), c.center_of_mass()))
+            .fold(Matrix3::zero(), |a, b| a + b);
+        if self.set_mass_properties(mass, local_moi) {
+            self.updater = Updater::Free;
+        }
+        self
+    }
+
+    /// Set the friction coefficients. Contacts stick while the tangential impulse needed to hold
+    /// them is within the static cone, and otherwise slide against dynamic friction.
+    pub fn friction(mut self, static_friction: f64, dynamic_friction: f64) -> Self {
+        self.static_friction = static_friction;
+        self.dynamic_friction = dynamic_friction;
+        self
+    }
+
+    /// Set the mass and the moment of inertia in the body's frame, along with its inverse. Returns
+    /// false and leaves them as they were unless the mass is positive and the moment invertible.
+    pub fn set_mass_properties(&mut self, mass: f64, local_moi: Matrix3<f64>) -> bool {
+        let local_moi_inv = match local_moi.invert() {
+            Some(inv) if mass > 0.0 && mass.is_finite() => inv,
+            _ => return false,
+        };
+        self.mass = mass;
+        self.local_moi = local_moi;
+        self.local_moi_inv = local_moi_inv;
+        true
+    }
+
+    /// A copy of where the body is and how it moves, read at the given simulated time
+    pub fn state(&self, time: f64) -> BodyState {
+        BodyState {
+            pos: self.pos,
+            vel: self.vel,
+            orientation: self.orientation,
+            ang_vel: self.ang_vel,
+            mass: self.mass,
+            local_moi: self.local_moi,
+            time,
+        }
+    }
+
     pub fn moi(&self) -> Matrix3<f64> {
         let mat = Matrix3::from(self.orientation);
         mat * self.local_moi * mat.transpose()
@@ -127,13 +342,26 @@ impl RigidBody {
         mat * self.local_moi_inv * mat.transpose()
     }
 
+    /// Radius of a sphere around the body's position which contains all of its colliders
+    pub(crate) fn bounding_radius(&self) -> f64 {
+        self.colliders.iter()
+            .map(|c| c.offset().magnitude() + c.radius())
+            .fold(0.0, f64::max)
+    }
+
 }
 
 impl RigidBody {
     pub(crate) fn push_constants(&self) -> builtin::ObjectPushConstants {
-        let rotation = Matrix4::from(Matrix3::from(self.orientation.cast().unwrap()));
+        self.push_constants_at(self.orientation)
+    }
+
+    /// Push constants for this body's model turned to an orientation but left at the origin. The
+    /// body is moved into place relative to the render origin when it is drawn, in f64.
+    pub(crate) fn push_constants_at(&self, orientation: Quaternion<f64>) -> builtin::ObjectPushConstants {
+        let rotation = Matrix4::from(Matrix3::from(orientation.cast().unwrap()));
         builtin::ObjectPushConstants {
-            model: Matrix4::from_translation(self.pos.cast().unwrap()) * rotation * self.model_offset,
+            model: rotation * self.model_offset,
             rotation,
         }
     }
@@ -143,36 +371,98 @@ impl RigidBody {
     }
 
     pub(crate) fn update(&mut self, delta_time: f64) {
-        let dt = if let Some((t, _, _)) = self.collide_data {t as f64} else {delta_time};
+        if self.asleep {
+            self.impulse = Vector3::zero();
+            self.torque_impulse = Vector3::zero();
+            self.collide_time = None;
+            return;
+        }
+        let dt = delta_time - self.collide_time.take().unwrap_or(0.0);
         match self.updater {
             Updater::Fixed => (),
             Updater::Free => {
-                if let Some((_, r, n)) = self.collide_data {
-                    let impulse_into_ground = self.impulse - r.cross(self.moi_inv() * self.torque_impulse);
-                    self.impulse += n * n.dot(impulse_into_ground);
-                }
                 self.vel += self.impulse / self.mass;
-                self.pos += self.vel * dt;
-                self.ang_vel += self.moi_inv() * (self.torque_impulse - self.ang_vel.cross(self.moi() * self.ang_vel) * delta_time);
-                self.orientation += 0.5 * Quaternion::new(0.0, self.ang_vel.x, self.ang_vel.y, self.ang_vel.z) * self.orientation * dt;
-                self.orientation = self.orientation.normalize();
+                self.integrate_translation(dt);
+                self.integrate_rotation(delta_time, dt);
                 self.impulse = Vector3::zero();
                 self.torque_impulse = Vector3::zero();
-                self.collide_data = None;
             },
-            Updater::Orbit{center, eccentricity, ang_mom, mu, ..} => {
-                let r = self.pos - center;
+            Updater::Line{..} | Updater::Circle{..} | Updater::Orbit{..} => {
+                // Kinematic bodies ignore forces
+                self.kinematic_step(dt);
+                self.impulse = Vector3::zero();
+                self.torque_impulse = Vector3::zero();
+            },
+        }
+        
+    }
 
-                let e_cos_nu = eccentricity.dot(r.normalize());
-                let p = ang_mom.magnitude2() / mu;
+    /// Whether the body's own field pulls on it where it is
+    pub(crate) fn feels_field(&self) -> bool {
+        self.field.as_ref().is_some_and(|field| field(self.pos) != Vector3::zero())
+    }
 
-                self.vel -= mu / r.magnitude2() * r.normalize() * delta_time;
-                self.pos += self.vel * delta_time;
-                self.pos *= p / (1.0 + e_cos_nu) / self.pos.magnitude();
-            }
-            _ => unimplemented!()
+    /// Whether the body can move this step, so that it must be tested against bodies which can't
+    pub(crate) fn is_active(&self) -> bool {
+        !self.asleep && !matches!(self.updater, Updater::Fixed)
+    }
+
+    pub(crate) fn wake(&mut self) {
+        self.asleep = false;
+        self.sleep_time = 0.0;
+        self.carrier = None;
+    }
+
+    /// Stop the body where it is until something wakes it
+    pub(crate) fn sleep(&mut self) {
+        self.asleep = true;
+        self.vel = Vector3::zero();
+        self.ang_vel = Vector3::zero();
+        self.impulse = Vector3::zero();
+        self.torque_impulse = Vector3::zero();
+    }
+
+    /// Move the body forward without forces to a time within the step, if it isn't there already
+    pub(crate) fn advance_to(&mut self, time: f64) {
+        let so_far = self.collide_time.unwrap_or(0.0);
+        if time > so_far {
+            self.update_forceless(time - so_far);
+            self.collide_time = Some(time);
+        }
+    }
+
+    /// One over the mass, or zero for bodies which collisions cannot move
+    pub(crate) fn inv_mass(&self) -> f64 {
+        match self.updater {
+            Updater::Free => 1.0 / self.mass,
+            _ => 0.0,
         }
-        
+    }
+
+    /// The inverse moment of inertia in the world frame, or zero for bodies which collisions
+    /// cannot turn
+    pub(crate) fn inv_moi(&self) -> Matrix3<f64> {
+        match self.updater {
+            Updater::Free => self.moi_inv(),
+            _ => Matrix3::zero(),
+        }
+    }
+
+    /// Velocity of the point at r from the body's position, once the pending impulses are applied
+    pub(crate) fn point_velocity(&self, r: Vector3<f64>) -> Vector3<f64> {
+        let vel = self.vel + self.impulse * self.inv_mass();
+        vel + self.angular_velocity().cross(r)
+    }
+
+    /// Angular velocity once the pending torque impulse is applied
+    pub(crate) fn angular_velocity(&self) -> Vector3<f64> {
+        self.ang_vel + self.inv_moi() * self.torque_impulse
+    }
+
+    /// Queue an impulse acting at r from the body's position
+    pub(crate) fn apply_impulse_at(&mut self, impulse: Vector3<f64>, r: Vector3<f64>) {
+        self.impulse += impulse;
+        self.torque_impulse += r.cross(impulse);
     }
 
     pub(crate) fn update_forceless(&mut self, delta_time: f64) {
@@ -183,70 +473,163 @@ impl RigidBody {
                 self.orientation += 0.5 * Quaternion::new(0.0, self.ang_vel.x, self.ang_vel.y, self.ang_vel.z) * self.orientation * delta_time;
                 self.orientation = self.orientation.normalize();
             },
-            _ => unimplemented!()
+            Updater::Line{..} | Updater::Circle{..} | Updater::Orbit{..} => self.kinematic_step(delta_time),
         }
     }
 
-    pub(crate) fn detect_collision(&self, o: &RigidBody, delta_time: f64) -> Option<(f64, Vector3<f64>, Vector3<f64>)> {
-        if let CollisionData::NoCollision = self.eval_collision(o, delta_time) {
-            return None;
+    /// Advance a kinematic body along its path and set its velocity to match. Orbits are solved in
+    /// closed form, so delta_time may be as large as you like.
+    fn kinematic_step(&mut self, delta_time: f64) {
+        match &mut self.updater {
+            Updater::Line { origin, axis, speed, length, phase } => {
+                *phase += *speed * delta_time;
+                if *length <= 0.0 {
+                    *phase = 0.0;
+                } else if length.is_finite() {
+                    *phase = phase.rem_euclid(2.0 * *length);
+                }
+                let (distance, direction) = if *length <= 0.0 {
+                    (0.0, 0.0)
+                } else if *phase <= *length {
+                    (*phase, 1.0)
+                } else {
+                    (2.0 * *length - *phase, -1.0)
+                };
+                self.pos = *origin + *axis * distance;
+                self.vel = *axis * *speed * direction;
+                self.ang_vel = Vector3::zero();
+            },
+            Updater::Circle { center, axis, reference, radius, ang_speed, phase } => {
+                let turn = *ang_speed * delta_time;
+                *phase = (*phase + turn).rem_euclid(2.0 * std::f64::consts::PI);
+                let arm = (*reference * phase.cos() + axis.cross(*reference) * phase.sin()) * *radius;
+                self.pos = *center + arm;
+                self.ang_vel = *axis * *ang_speed;
+                self.vel = self.ang_vel.cross(arm);
+                self.orientation = (Quaternion::from_axis_angle(*axis, Rad(turn)) * self.orientation).normalize();
+            },
+            Updater::Orbit { center, eccentricity, ang_mom, mu, perigee_time, periapsis, time } => {
+                *time += delta_time;
+                // Keep the clock within one period of perigee so that long warps don't lose precision
+                if let Some(period) = orbit::period(*mu, *eccentricity, *ang_mom) {
+                    *time = *perigee_time + (*time - *perigee_time).rem_euclid(period);
+                }
+                let (pos, vel) = orbit::get_state(*mu, *time - *perigee_time, *eccentricity, *ang_mom, *periapsis);
+                self.pos = *center + pos;
+                self.vel = vel;
+                let spin = self.ang_vel.magnitude();
+                if spin > 0.0 {
+                    self.orientation = (Quaternion::from_axis_angle(self.ang_vel / spin, Rad(spin * delta_time)) * self.orientation).normalize();
+                }
+            },
+            _ => unreachable!(),
         }
+    }
 
-        let mut high = 1.0;
-        let mut low = 0.0; // Guaranteed to be no collision
-
-        loop {
-            if high - low < 0.001 {
-                println!("Binary search got too tight.");
-                // return None;
-            }
-            let mid = (high + low) / 2.0;
-            match self.eval_collision(o, delta_time * mid) {
-                CollisionData::NoCollision => { low = mid; },// Increase frac
-                CollisionData::Collision(normal, center) => {
-                    if high - low < COLLIDE_ACCEPTANCE {
-                        return Some((mid * delta_time, normal, center)); // Accept collision
+    /// The time into the step at which the bodies first touch, found by conservative
+    /// advancement. Each body moves as it would without forces, and no pair of pieces can close
+    /// faster than their relative velocity along the gap plus the speed their spin gives their
+    /// furthest points, so that the bodies can always be moved that far without passing through
+    /// each other. Zero means they already overlap at the start of the step, and the solver should
+    /// push them apart. Bodies which touch are moved on a little, so that they overlap enough to
+    /// measure the contact. Bodies whose filters keep them apart never touch.
+    pub(crate) fn detect_collision(&self, o: &RigidBody, delta_time: f64) -> Option<f64> {
+        if !self.collision_filter.allows(&o.collision_filter) {
+            return None;
+        }
+        let relative_vel = self.vel - o.vel;
+        let mut t = 0.0;
+        for _ in 0..MAX_TOI_ITERATIONS {
+            let (my_rb_pos, my_orientation) = self.forceless_pose(t);
+            let (o_rb_pos, o_orientation) = o.forceless_pose(t);
+            let o_leaves = leaves(&o.colliders, o_rb_pos, o_orientation);
+            let mut step = f64::INFINITY;
+            let mut touching_speed: f64 = 0.0;
+            for (_, my_c, my_pos, my_orientation) in leaves(&self.colliders, my_rb_pos, my_orientation) {
+                for (_, o_c, o_pos, o_orientation) in &o_leaves {
+                    let (o_c, o_pos, o_orientation) = (*o_c, *o_pos, *o_orientation);
+                    if let (Collider::Radial{..}, Collider::Radial{..}) = (my_c, o_c) {
+                        // No planet-planet collisions
+                        continue;
+                    }
+                    let (gap, normal) = match Collider::separation(my_c, o_c, my_pos, o_pos, my_orientation, o_orientation) {
+                        Some(separation) => separation,
+                        None => return Some(t),
+                    };
+                    let my_reach = (my_pos + my_orientation * my_c.offset() - my_rb_pos).magnitude() + my_c.radius();
+                    let o_reach = (o_pos + o_orientation * o_c.offset() - o_rb_pos).magnitude() + o_c.radius();
+                    let closing_speed = relative_vel.dot(normal) + self.ang_vel.magnitude() * my_reach + o.ang_vel.magnitude() * o_reach;
+                    if closing_speed <= 0.0 {
+                        continue;
+                    }
+                    if gap < TOI_TOLERANCE {
+                        touching_speed = touching_speed.max(closing_speed);
                     } else {
-                        high = mid;// Decrease frac
+                        // Aim short of the contact, so that the next pass finds the bodies touching
+                        step = step.min((gap - 0.5 * TOI_TOLERANCE) / closing_speed);
                     }
-                }, 
+                }
+            }
+            if touching_speed > 0.0 {
+                return Some((t + TOI_PENETRATION / touching_speed).min(delta_time));
+            }
+            t += step;
+            if t > delta_time {
+                return None;
             }
         }
+        // Still closing in on a contact. Stopping here is safe, since the bodies can't have
+        // passed through each other yet.
+        Some(t)
     }
 
-    fn eval_collision(&self, o: &RigidBody, delta_t: f64) -> CollisionData {
-        // Work in inertial frame, self-centric. Shift indicates the velocity of the other object. 
-        let my_rb_pos = self.pos + self.vel * delta_t;
-        let my_orientation = self.orientation + 0.5 * Quaternion::new(0.0, self.ang_vel.x, self.ang_vel.y, self.ang_vel.z) * self.orientation * delta_t;
-        let my_orientation = my_orientation.normalize();
-        let o_rb_pos = o.pos + o.vel * delta_t;
-        let o_orientation = o.orientation + 0.5 * Quaternion::new(0.0, o.ang_vel.x, o.ang_vel.y, o.ang_vel.z) * o.orientation * delta_t;
-        let o_orientation = o_orientation.normalize();
-        let shift = delta_t as f64 * (o.vel - self.vel);
-        
-        for my_c in &self.colliders {
-            for o_c in &o.colliders {
+    /// Where update_forceless would move the body in delta_t
+    fn forceless_pose(&self, delta_t: f64) -> (Vector3<f64>, Quaternion<f64>) {
+        let pos = self.pos + self.vel * delta_t;
+        let orientation = self.orientation + 0.5 * Quaternion::new(0.0, self.ang_vel.x, self.ang_vel.y, self.ang_vel.z) * self.orientation * delta_t;
+        (pos, orientation.normalize())
+    }
 
-                let collision_data = if let Collider::Radial{..} = my_c {
-                    if let Collider::Radial{..} = o_c {
-                        // No planet-planet collisions
-                        continue;
-                    }
-                    // Planet gjk
-                    Collider::planet_collide(my_c, o_c, my_rb_pos, o_rb_pos, my_orientation, o_orientation, shift)
-                } else if let Collider::Radial{..} = o_c {
-                    // Planet gjk
-                    Collider::planet_collide(o_c, my_c, o_rb_pos, my_rb_pos, o_orientation, my_orientation, -shift)
-                } else {
-                    // Normal gjk
-                    Collider::gjk_collide(my_c, o_c, my_rb_pos, o_rb_pos, my_orientation, o_orientation, shift)
-                };
+    /// The pieces of this body which overlap any piece of the other at their current poses
+    pub(crate) fn overlapping_parts(&self, o: &RigidBody) -> Vec<ColliderPart> {
+        let o_leaves = leaves(&o.colliders, o.pos, o.orientation);
+        leaves(&self.colliders, self.pos, self.orientation).into_iter()
+            .filter(|(_, my_c, my_pos, my_orientation)| o_leaves.iter().any(|(_, o_c, o_pos, o_orientation)| {
+                !matches!((my_c, o_c), (Collider::Radial{..}, Collider::Radial{..}))
+                    && Collider::separation(my_c, o_c, *my_pos, *o_pos, *my_orientation, *o_orientation).is_none()
+            }))
+            .map(|(part, _, _, _)| part)
+            .collect()
+    }
 
-                if let Some(data) = collision_data {
-                    return data;
-                }
+    /// The contacts between each piece of this body and each piece of the other at their current
+    /// poses, tagged with the two pieces. A pair may touch at several points.
+    pub(crate) fn contacts(&self, o: &RigidBody) -> Vec<(ColliderPart, ColliderPart, Contact)> {
+        let mut contacts = Vec::new();
+        let o_leaves = leaves(&o.colliders, o.pos, o.orientation);
+        for (i, my_c, my_pos, my_orientation) in leaves(&self.colliders, self.pos, self.orientation) {
+            for (j, o_c, o_pos, o_orientation) in &o_leaves {
+                let (o_c, o_pos, o_orientation) = (*o_c, *o_pos, *o_orientation);
+                let found = match (my_c, o_c) {
+                    (Collider::Radial{..}, Collider::Radial{..}) => Vec::new(),
+                    (Collider::Radial{..}, _) => Collider::planet_collide(my_c, o_c, my_pos, o_pos, my_orientation, o_orientation)
+                        .into_iter().collect(),
+                    (_, Collider::Radial{..}) => Collider::planet_collide(o_c, my_c, o_pos, my_pos, o_orientation, my_orientation)
+                        .map(Contact::flipped).into_iter().collect(),
+                    _ => Collider::gjk_contacts(my_c, o_c, my_pos, o_pos, my_orientation, o_orientation),
+                };
+                contacts.extend(found.into_iter().map(|contact| (i, *j, contact)));
             }
         }
-        CollisionData::NoCollision
+        contacts
     }
-}
\ No newline at end of file
+}
+
+/// Any unit vector perpendicular to the unit vector v
+pub(super) fn perpendicular(v: Vector3<f64>) -> Vector3<f64> {
+    if v.x.abs() < 0.9 {
+        v.cross(Vector3::unit_x()).normalize()
+    } else {
+        v.cross(Vector3::unit_y()).normalize()
+    }
+}
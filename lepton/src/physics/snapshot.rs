@@ -0,0 +1,384 @@
+use anyhow::{Result, bail};
+use cgmath::{Vector3, Quaternion, Matrix3, Matrix4};
+use serde::{Serialize, Deserialize};
+
+use super::{Object, RigidBody, Updater, PhysicsTask, Integrator, RotationIntegrator, Field, GravityRole, Gravity,
+    Atmosphere, Aerodynamics, CollisionFilter, Collider, ColliderPart, RadialFunction, Joint, JointId};
+use super::manifold::ContactManifold;
+use super::world::ColliderPair;
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Vector3::<f64>")]
+pub(crate) struct FakeVector {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Matrix3::<f64>")]
+pub(crate) struct FakeMatrix {
+    #[serde(with = "FakeVector")]
+    x: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    y: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    z: Vector3<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Quaternion::<f64>")]
+pub(crate) struct FakeQuaternion {
+    #[serde(with = "FakeVector")]
+    v: Vector3<f64>,
+    s: f64,
+}
+
+/// Supplies the functions which snapshots can't hold when a world is restored. Each is asked for
+/// by the object of the body it belongs to, and a radial collider also by its part.
+pub trait Resolver {
+    /// The surface of a radial collider, such as a planet's terrain
+    fn radial(&mut self, _object: Object, _part: ColliderPart) -> Option<RadialFunction> {
+        None
+    }
+
+    fn field(&mut self, _object: Object) -> Option<Field> {
+        None
+    }
+}
+
+/// Resolves nothing, for worlds without radial colliders or fields
+pub struct NoFunctions;
+
+impl Resolver for NoFunctions {}
+
+/// The whole state of a physics world between two steps: its bodies, joints and contacts, the
+/// tasks still queued and the clock. Restoring it carries on the simulation bit for bit as the
+/// original would have. Serialize it with serde, for example through bincode.
+#[derive(Serialize, Deserialize)]
+pub struct PhysicsSnapshot {
+    pub(super) bodies: Vec<(Object, BodySnapshot)>,
+    pub(super) joints: Vec<(JointId, Joint)>,
+    pub(super) manifolds: Vec<(ColliderPair, ContactManifold)>,
+    pub(super) ignored_pairs: Vec<(Object, Object)>,
+    pub(super) sensor_contents: Vec<(Object, ColliderPart, Object)>,
+    pub(super) tasks: Vec<TaskSnapshot>,
+    pub(super) previous_poses: Vec<(Object, PoseSnapshot)>,
+    pub(super) time: f64,
+    pub(super) fixed_delta_time: f64,
+    pub(super) accumulator: f64,
+    pub(super) gravity: Option<Gravity>,
+    pub(super) sleeping: bool,
+}
+
+impl PhysicsSnapshot {
+    /// Simulated time when the snapshot was taken
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PoseSnapshot {
+    #[serde(with = "FakeVector")]
+    pub pos: Vector3<f64>,
+    #[serde(with = "FakeQuaternion")]
+    pub orientation: Quaternion<f64>,
+}
+
+/// A rigid body with its field and radial collider functions left out
+#[derive(Serialize, Deserialize)]
+pub(crate) struct BodySnapshot {
+    #[serde(with = "FakeVector")]
+    pos: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    vel: Vector3<f64>,
+    mass: f64,
+    #[serde(with = "FakeVector")]
+    impulse: Vector3<f64>,
+    #[serde(with = "FakeVector")]
+    torque_impulse: Vector3<f64>,
+    #[serde(with = "FakeQuaternion")]
+    orientation: Quaternion<f64>,
+    #[serde(with = "FakeVector")]
+    ang_vel: Vector3<f64>,
+    #[serde(with = "FakeMatrix")]
+    local_moi: Matrix3<f64>,
+    #[serde(with = "FakeMatrix")]
+    local_moi_inv: Matrix3<f64>,
+    updater: Updater,
+    integrator: Integrator,
+    rotation_integrator: RotationIntegrator,
+    /// Whether the body had a field
+    field: bool,
+    gravity_role: GravityRole,
+    atmosphere: Option<Atmosphere>,
+    aerodynamics: Option<Aerodynamics>,
+    colliders: Vec<ColliderSnapshot>,
+    collision_filter: CollisionFilter,
+    sensor: bool,
+    elasticity: f64,
+    static_friction: f64,
+    dynamic_friction: f64,
+    model_offset: [[f32; 4]; 4],
+    asleep: bool,
+    sleep_time: f64,
+    can_sleep: bool,
+    carrier: Option<Object>,
+    collide_time: Option<f64>,
+}
+
+impl BodySnapshot {
+    pub fn new(body: &RigidBody) -> Self {
+        Self {
+            pos: body.pos,
+            vel: body.vel,
+            mass: body.mass,
+            impulse: body.impulse,
+            torque_impulse: body.torque_impulse,
+            orientation: body.orientation,
+            ang_vel: body.ang_vel,
+            local_moi: body.local_moi,
+            local_moi_inv: body.local_moi_inv,
+            updater: body.updater.clone(),
+            integrator: body.integrator,
+            rotation_integrator: body.rotation_integrator,
+            field: body.field.is_some(),
+            gravity_role: body.gravity_role,
+            atmosphere: body.atmosphere,
+            aerodynamics: body.aerodynamics,
+            colliders: body.colliders.iter().map(ColliderSnapshot::new).collect(),
+            collision_filter: body.collision_filter,
+            sensor: body.sensor,
+            elasticity: body.elasticity,
+            static_friction: body.static_friction,
+            dynamic_friction: body.dynamic_friction,
+            model_offset: body.model_offset.into(),
+            asleep: body.asleep,
+            sleep_time: body.sleep_time,
+            can_sleep: body.can_sleep,
+            carrier: body.carrier,
+            collide_time: body.collide_time,
+        }
+    }
+
+    /// Rebuild the body of object, asking the resolver for its functions
+    pub fn restore<R: Resolver>(self, object: Object, resolver: &mut R) -> Result<RigidBody> {
+        let field = match self.field {
+            true => match resolver.field(object) {
+                Some(field) => Some(field),
+                None => bail!("No field was resolved for {:?}", object),
+            },
+            false => None,
+        };
+        let mut body = RigidBody::new(self.pos, self.vel, self.orientation, self.ang_vel);
+        body.mass = self.mass;
+        body.impulse = self.impulse;
+        body.torque_impulse = self.torque_impulse;
+        body.local_moi = self.local_moi;
+        body.local_moi_inv = self.local_moi_inv;
+        body.updater = self.updater;
+        body.integrator = self.integrator;
+        body.rotation_integrator = self.rotation_integrator;
+        body.field = field;
+        body.gravity_role = self.gravity_role;
+        body.atmosphere = self.atmosphere;
+        body.aerodynamics = self.aerodynamics;
+        body.colliders = restore_colliders(self.colliders, object, resolver)?;
+        body.collision_filter = self.collision_filter;
+        body.sensor = self.sensor;
+        body.elasticity = self.elasticity;
+        body.static_friction = self.static_friction;
+        body.dynamic_friction = self.dynamic_friction;
+        body.model_offset = Matrix4::from(self.model_offset);
+        body.asleep = self.asleep;
+        body.sleep_time = self.sleep_time;
+        body.can_sleep = self.can_sleep;
+        body.carrier = self.carrier;
+        body.collide_time = self.collide_time;
+        Ok(body)
+    }
+}
+
+/// A collider with a radial collider's function left out
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ColliderSnapshot {
+    Cube { length: f64 },
+    Radial { length: f64 },
+    Polyhedron {
+        vertices: Vec<[f64; 3]>,
+        length: f64,
+        #[serde(with = "FakeVector")]
+        offset: Vector3<f64>,
+        adjacency: Vec<Vec<usize>>,
+        start: usize,
+    },
+    Sphere { radius: f64 },
+    Capsule { half_length: f64, radius: f64 },
+    Box {
+        #[serde(with = "FakeVector")]
+        half_extents: Vector3<f64>,
+    },
+    Compound {
+        children: Vec<ChildSnapshot>,
+        length: f64,
+        #[serde(with = "FakeVector")]
+        offset: Vector3<f64>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ChildSnapshot {
+    #[serde(with = "FakeVector")]
+    translation: Vector3<f64>,
+    #[serde(with = "FakeQuaternion")]
+    rotation: Quaternion<f64>,
+    collider: ColliderSnapshot,
+}
+
+impl ColliderSnapshot {
+    pub fn new(collider: &Collider) -> Self {
+        match collider {
+            Collider::Cube { length } => ColliderSnapshot::Cube { length: *length },
+            Collider::Radial { length, .. } => ColliderSnapshot::Radial { length: *length },
+            Collider::Polyhedron { vertices, length, offset, adjacency, start } => ColliderSnapshot::Polyhedron {
+                vertices: vertices.iter().map(|v| (*v).into()).collect(),
+                length: *length,
+                offset: *offset,
+                adjacency: adjacency.clone(),
+                start: *start,
+            },
+            Collider::Sphere { radius } => ColliderSnapshot::Sphere { radius: *radius },
+            Collider::Capsule { half_length, radius } => ColliderSnapshot::Capsule { half_length: *half_length, radius: *radius },
+            Collider::Box { half_extents } => ColliderSnapshot::Box { half_extents: *half_extents },
+            Collider::Compound { children, length, offset } => ColliderSnapshot::Compound {
+                children: children.iter().map(|(translation, rotation, child)| ChildSnapshot {
+                    translation: *translation,
+                    rotation: *rotation,
+                    collider: ColliderSnapshot::new(child),
+                }).collect(),
+                length: *length,
+                offset: *offset,
+            },
+        }
+    }
+
+    fn restore<R: Resolver>(self, object: Object, part: ColliderPart, resolver: &mut R) -> Result<Collider> {
+        Ok(match self {
+            ColliderSnapshot::Cube { length } => Collider::Cube { length },
+            ColliderSnapshot::Radial { length } => match resolver.radial(object, part) {
+                Some(func) => Collider::Radial { func, length },
+                None => bail!("No function was resolved for radial collider {:?} of {:?}", part, object),
+            },
+            ColliderSnapshot::Polyhedron { vertices, length, offset, adjacency, start } => Collider::Polyhedron {
+                vertices: vertices.into_iter().map(Vector3::from).collect(),
+                length,
+                offset,
+                adjacency,
+                start,
+            },
+            ColliderSnapshot::Sphere { radius } => Collider::Sphere { radius },
+            ColliderSnapshot::Capsule { half_length, radius } => Collider::Capsule { half_length, radius },
+            ColliderSnapshot::Box { half_extents } => Collider::Box { half_extents },
+            ColliderSnapshot::Compound { children, length, offset } => Collider::Compound {
+                children: children.into_iter().enumerate().map(|(j, child)| {
+                    let part = ColliderPart { collider: part.collider, child: Some(j) };
+                    Ok((child.translation, child.rotation, child.collider.restore(object, part, resolver)?))
+                }).collect::<Result<Vec<_>>>()?,
+                length,
+                offset,
+            },
+        })
+    }
+}
+
+fn restore_colliders<R: Resolver>(colliders: Vec<ColliderSnapshot>, object: Object, resolver: &mut R) -> Result<Vec<Collider>> {
+    colliders.into_iter().enumerate()
+        .map(|(i, collider)| collider.restore(object, ColliderPart { collider: i, child: None }, resolver))
+        .collect()
+}
+
+/// A physics task with its body or colliders held as snapshots
+#[derive(Serialize, Deserialize)]
+pub(crate) enum TaskSnapshot {
+    AddGlobalForce(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    AddGlobalImpulse(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    AddLocalForce(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    AddLocalImpulse(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    AddLocalImpulseTorque(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    AddGlobalImpulseTorque(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    ShiftPos(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    CreateJoint(JointId, Joint),
+    DestroyJoint(JointId),
+    Spawn(Object, Box<BodySnapshot>),
+    Despawn(Object),
+    SetPos(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    SetVel(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    SetOrientation(Object, #[serde(with = "FakeQuaternion")] Quaternion<f64>),
+    SetAngVel(Object, #[serde(with = "FakeVector")] Vector3<f64>),
+    SetMassProperties(Object, f64, #[serde(with = "FakeMatrix")] Matrix3<f64>),
+    SetUpdater(Object, Updater),
+    SetColliders(Object, Vec<ColliderSnapshot>),
+    RequestState(Object),
+    SetCollisionFilter(Object, CollisionFilter),
+    IgnoreCollisions(Object, Object),
+    RestoreCollisions(Object, Object),
+}
+
+impl TaskSnapshot {
+    pub fn new(task: &PhysicsTask) -> Self {
+        match task {
+            PhysicsTask::AddGlobalForce(object, v) => TaskSnapshot::AddGlobalForce(*object, *v),
+            PhysicsTask::AddGlobalImpulse(object, v) => TaskSnapshot::AddGlobalImpulse(*object, *v),
+            PhysicsTask::AddLocalForce(object, v) => TaskSnapshot::AddLocalForce(*object, *v),
+            PhysicsTask::AddLocalImpulse(object, v) => TaskSnapshot::AddLocalImpulse(*object, *v),
+            PhysicsTask::AddLocalImpulseTorque(object, v) => TaskSnapshot::AddLocalImpulseTorque(*object, *v),
+            PhysicsTask::AddGlobalImpulseTorque(object, v) => TaskSnapshot::AddGlobalImpulseTorque(*object, *v),
+            PhysicsTask::ShiftPos(object, v) => TaskSnapshot::ShiftPos(*object, *v),
+            PhysicsTask::CreateJoint(id, joint) => TaskSnapshot::CreateJoint(*id, joint.clone()),
+            PhysicsTask::DestroyJoint(id) => TaskSnapshot::DestroyJoint(*id),
+            PhysicsTask::Spawn(object, body) => TaskSnapshot::Spawn(*object, Box::new(BodySnapshot::new(body))),
+            PhysicsTask::Despawn(object) => TaskSnapshot::Despawn(*object),
+            PhysicsTask::SetPos(object, v) => TaskSnapshot::SetPos(*object, *v),
+            PhysicsTask::SetVel(object, v) => TaskSnapshot::SetVel(*object, *v),
+            PhysicsTask::SetOrientation(object, q) => TaskSnapshot::SetOrientation(*object, *q),
+            PhysicsTask::SetAngVel(object, v) => TaskSnapshot::SetAngVel(*object, *v),
+            PhysicsTask::SetMassProperties(object, mass, moi) => TaskSnapshot::SetMassProperties(*object, *mass, *moi),
+            PhysicsTask::SetUpdater(object, updater) => TaskSnapshot::SetUpdater(*object, updater.clone()),
+            PhysicsTask::SetColliders(object, colliders) => TaskSnapshot::SetColliders(*object,
+                colliders.iter().map(ColliderSnapshot::new).collect()),
+            PhysicsTask::RequestState(object) => TaskSnapshot::RequestState(*object),
+            PhysicsTask::SetCollisionFilter(object, filter) => TaskSnapshot::SetCollisionFilter(*object, *filter),
+            PhysicsTask::IgnoreCollisions(a, b) => TaskSnapshot::IgnoreCollisions(*a, *b),
+            PhysicsTask::RestoreCollisions(a, b) => TaskSnapshot::RestoreCollisions(*a, *b),
+        }
+    }
+
+    pub fn restore<R: Resolver>(self, resolver: &mut R) -> Result<PhysicsTask> {
+        Ok(match self {
+            TaskSnapshot::AddGlobalForce(object, v) => PhysicsTask::AddGlobalForce(object, v),
+            TaskSnapshot::AddGlobalImpulse(object, v) => PhysicsTask::AddGlobalImpulse(object, v),
+            TaskSnapshot::AddLocalForce(object, v) => PhysicsTask::AddLocalForce(object, v),
+            TaskSnapshot::AddLocalImpulse(object, v) => PhysicsTask::AddLocalImpulse(object, v),
+            TaskSnapshot::AddLocalImpulseTorque(object, v) => PhysicsTask::AddLocalImpulseTorque(object, v),
+            TaskSnapshot::AddGlobalImpulseTorque(object, v) => PhysicsTask::AddGlobalImpulseTorque(object, v),
+            TaskSnapshot::ShiftPos(object, v) => PhysicsTask::ShiftPos(object, v),
+            TaskSnapshot::CreateJoint(id, joint) => PhysicsTask::CreateJoint(id, joint),
+            TaskSnapshot::DestroyJoint(id) => PhysicsTask::DestroyJoint(id),
+            TaskSnapshot::Spawn(object, body) => PhysicsTask::Spawn(object, Box::new(body.restore(object, resolver)?)),
+            TaskSnapshot::Despawn(object) => PhysicsTask::Despawn(object),
+            TaskSnapshot::SetPos(object, v) => PhysicsTask::SetPos(object, v),
+            TaskSnapshot::SetVel(object, v) => PhysicsTask::SetVel(object, v),
+            TaskSnapshot::SetOrientation(object, q) => PhysicsTask::SetOrientation(object, q),
+            TaskSnapshot::SetAngVel(object, v) => PhysicsTask::SetAngVel(object, v),
+            TaskSnapshot::SetMassProperties(object, mass, moi) => PhysicsTask::SetMassProperties(object, mass, moi),
+            TaskSnapshot::SetUpdater(object, updater) => PhysicsTask::SetUpdater(object, updater),
+            TaskSnapshot::SetColliders(object, colliders) => PhysicsTask::SetColliders(object,
+                restore_colliders(colliders, object, resolver)?),
+            TaskSnapshot::RequestState(object) => PhysicsTask::RequestState(object),
+            TaskSnapshot::SetCollisionFilter(object, filter) => PhysicsTask::SetCollisionFilter(object, filter),
+            TaskSnapshot::IgnoreCollisions(a, b) => PhysicsTask::IgnoreCollisions(a, b),
+            TaskSnapshot::RestoreCollisions(a, b) => PhysicsTask::RestoreCollisions(a, b),
+        })
+    }
+}
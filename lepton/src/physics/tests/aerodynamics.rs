@@ -0,0 +1,68 @@
+use super::*;
+
+/// A round planet of radius 1000 at the origin with air that never thins, and a body with
+/// aerodynamics above it
+fn airy_world(aerodynamics: Aerodynamics, vel: Vector3<f64>) -> PhysicsWorld {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::zero())
+        .collide(vec![Collider::planet(Box::new(|p: Vector3<f64>| p.magnitude() - 1000.0), 1000.0)], 0.5)
+        .atmosphere(Atmosphere::new(1.2, f64::INFINITY)));
+    world.add_body(obj(1), RigidBody::new(Vector3::new(0.0, 1100.0, 0.0), vel, Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::zero())
+        .motivate(2.0, cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0))
+        .aerodynamics(aerodynamics));
+    world
+}
+
+#[test]
+fn drag_reaches_terminal_velocity() {
+    let mut world = airy_world(Aerodynamics::new(1.0, 0.5), Vector3::zero());
+    for _ in 0..600 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), Vector3::new(0.0, -2.0 * 9.8, 0.0)));
+        world.step(DELTA_TIME);
+    }
+    let terminal = (2.0 * 2.0 * 9.8 / (1.2 * 1.0 * 0.5f64)).sqrt();
+    let vel = world.get_body(&obj(1)).unwrap().vel;
+    // Within the half step of gravity which drag sees before it settles
+    assert!((vel.y + terminal).abs() < 9.8 * DELTA_TIME, "fell at {:?} but expected {}", vel, terminal);
+
+    // However thick the air, it only slows the body and never throws it backwards
+    let mut world = airy_world(Aerodynamics::new(1e9, 1.0), Vector3::new(300.0, 0.0, 0.0));
+    world.step(DELTA_TIME);
+    let vel = world.get_body(&obj(1)).unwrap().vel;
+    assert!(vel.x > 0.0 && vel.x < 1e-3, "{:?}", vel);
+}
+
+#[test]
+fn air_turns_with_its_planet() {
+    // A body carried along with the planet's spin feels no wind
+    let spin = Vector3::new(0.0, 0.0, 0.01);
+    let mut world = airy_world(Aerodynamics::new(1.0, 1.0), spin.cross(Vector3::new(0.0, 1100.0, 0.0)));
+    world.get_body_mut(&obj(0)).unwrap().ang_vel = spin;
+    let start = world.get_body(&obj(1)).unwrap().vel;
+    world.step(DELTA_TIME);
+    assert!((world.get_body(&obj(1)).unwrap().vel - start).magnitude() < 1e-12);
+
+    // Above the atmosphere's scale height the air is thinner
+    let air = Atmosphere::new(1.2, 20.0);
+    assert!((air.density(20.0) - 1.2 / std::f64::consts::E).abs() < 1e-12);
+    assert_eq!(air.density(-5.0), 1.2);
+}
+
+#[test]
+fn lift_acts_across_the_airflow() {
+    // A plate pitched 30 degrees nose up flies along x
+    let pitch = Quaternion::from_angle_y(cgmath::Rad(-std::f64::consts::PI / 6.0));
+    let aerodynamics = Aerodynamics::new(0.0, 2.0).lift(0.5, Vector3::unit_z());
+    let mut world = airy_world(aerodynamics, Vector3::new(10.0, 0.0, 0.0));
+    world.get_body_mut(&obj(1)).unwrap().orientation = pitch;
+    world.step(DELTA_TIME);
+    let vel = world.get_body(&obj(1)).unwrap().vel;
+    let expected = 0.5 * 1.2 * 100.0 * 2.0 * 0.5 * (std::f64::consts::PI / 3.0).sin() / 2.0 * DELTA_TIME;
+    assert!((vel - Vector3::new(10.0, 0.0, expected)).magnitude() < 1e-12, "{:?} but expected lift {}", vel, expected);
+
+    // Nose down, the plate is pushed down instead
+    let mut world = airy_world(aerodynamics, Vector3::new(10.0, 0.0, 0.0));
+    world.get_body_mut(&obj(1)).unwrap().orientation = pitch.conjugate();
+    world.step(DELTA_TIME);
+    assert!((world.get_body(&obj(1)).unwrap().vel.z + expected).abs() < 1e-12);
+}
@@ -0,0 +1,77 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::physics::broadphase::{Aabb, SweepAndPrune};
+use super::*;
+
+fn scatter_cubes(num: usize, spread: f64, seed: u64) -> FxHashMap<Object, RigidBody> {
+    let mut rng = Lcg(seed);
+    let mut bodies = FxHashMap::default();
+    for object in 0..num {
+        let body = RigidBody::new(rng.vector(spread), rng.vector(10.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), rng.vector(1.0))
+            .motivate(1.0, cgmath::Matrix3::identity())
+            .collide(vec![Collider::cube(0.5 + rng.next())], 0.5);
+        bodies.insert(obj(object as u32), body);
+    }
+    bodies
+}
+
+/// The pairs that survived the radius test of the old all-pairs loop
+fn brute_force_pairs(bodies: &FxHashMap<Object, RigidBody>) -> Vec<(Object, Object)> {
+    let mut pairs = Vec::new();
+    for (o_i, rb_i) in bodies {
+        for (o_j, rb_j) in bodies {
+            if o_j <= o_i {
+                continue;
+            }
+            if (rb_j.pos - rb_i.pos).magnitude() <= rb_i.bounding_radius() + rb_j.bounding_radius() {
+                pairs.push((*o_i, *o_j));
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+#[test]
+fn broadphase_matches_brute_force() {
+    let mut bodies = scatter_cubes(500, 15.0, 1);
+    let mut broadphase = SweepAndPrune::new();
+
+    for _ in 0..10 {
+        let candidates = broadphase.candidate_pairs(&bodies, DELTA_TIME, &FxHashSet::default());
+
+        // No pair that the old loop would have tested may be culled
+        for pair in brute_force_pairs(&bodies) {
+            assert!(candidates.binary_search(&pair).is_ok(), "Broadphase culled {:?}", pair);
+        }
+        // And every candidate really has overlapping boxes
+        for (o_i, o_j) in &candidates {
+            let a = Aabb::swept(&bodies[o_i], DELTA_TIME).unwrap();
+            let b = Aabb::swept(&bodies[o_j], DELTA_TIME).unwrap();
+            assert!(a.overlaps(&b));
+        }
+
+        for body in bodies.values_mut() {
+            body.pos += body.vel * DELTA_TIME;
+        }
+    }
+}
+
+#[test]
+fn broadphase_honours_filters_and_excluded_pairs() {
+    // Three groups, where the third only collides with itself
+    let mut bodies = scatter_cubes(300, 10.0, 5);
+    for (object, body) in bodies.iter_mut() {
+        body.collision_filter = match object.index() % 3 {
+            0 => CollisionFilter::new(1, 1 | 2),
+            1 => CollisionFilter::new(2, 1 | 2),
+            _ => CollisionFilter::new(4, 4),
+        };
+    }
+    let excluded = brute_force_pairs(&bodies).into_iter().step_by(2).collect::<FxHashSet<_>>();
+    let candidates = SweepAndPrune::new().candidate_pairs(&bodies, DELTA_TIME, &excluded);
+    for pair in brute_force_pairs(&bodies) {
+        let allowed = bodies[&pair.0].collision_filter.allows(&bodies[&pair.1].collision_filter);
+        assert_eq!(candidates.binary_search(&pair).is_ok(), allowed && !excluded.contains(&pair), "{:?}", pair);
+    }
+}
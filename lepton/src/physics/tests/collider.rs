@@ -0,0 +1,261 @@
+use super::*;
+
+#[test]
+fn epa_finds_penetration_depth() {
+    let a = RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0))
+        .collide(vec![Collider::cube(1.0)], 0.5);
+
+    // Face to face, which used to defeat the collision heuristics
+    let b = RigidBody::by_pos(Vector3::new(1.5, 0.2, 0.1))
+        .collide(vec![Collider::cube(1.0)], 0.5);
+    let contacts = a.contacts(&b);
+    assert!(contacts.len() > 1);
+    for (_, _, contact) in contacts {
+        assert!((contact.depth - 0.5).abs() < 1e-9, "{:?}", contact);
+        assert!((contact.normal - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!(((contact.point_a - contact.point_b).dot(contact.normal) - contact.depth).abs() < 1e-9);
+    }
+
+    // Corner into face
+    let b = RigidBody::new(Vector3::new(2.2, 0.0, 0.0), Vector3::zero(),
+        Quaternion::from_axis_angle(Vector3::unit_z(), cgmath::Deg(45.0)), Vector3::zero())
+        .collide(vec![Collider::cube(1.0)], 0.5);
+    let (_, _, contact) = b.contacts(&a)[0];
+    assert!((contact.depth - (2.0f64.sqrt() - 1.2)).abs() < 1e-9, "{:?}", contact);
+    assert!((contact.normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+    assert!((contact.point_a.x - (2.2 - 2.0f64.sqrt())).abs() < 1e-9);
+
+    // Apart
+    let b = RigidBody::by_pos(Vector3::new(2.5, 0.0, 0.0))
+        .collide(vec![Collider::cube(1.0)], 0.5);
+    assert!(a.contacts(&b).is_empty());
+}
+
+/// Two boxes of half width 0.5 held a unit above and below the body's position, the lower one
+/// turned about y
+fn dumbbell(pos: Vector3<f64>) -> RigidBody {
+    let upper = cgmath::Matrix4::from_translation(Vector3::new(0.0, 1.0, 0.0));
+    let lower = cgmath::Matrix4::from_translation(Vector3::new(0.0, -1.0, 0.0))
+        * cgmath::Matrix4::from_angle_y(cgmath::Rad(std::f64::consts::FRAC_PI_4));
+    unit_box(pos).collide(vec![Collider::compound(vec![
+        (upper, Collider::cube(0.5)),
+        (lower, Collider::cube(0.5)),
+    ])], 0.0)
+}
+
+#[test]
+fn compound_bounds_its_children() {
+    let body = dumbbell(Vector3::zero());
+    let compound = &body.colliders[0];
+    assert!(compound.offset().magnitude() < 1e-12, "{:?}", compound.offset());
+    assert!((compound.radius() - (1.0 + 0.5 * 3.0f64.sqrt())).abs() < 1e-12, "{}", compound.radius());
+
+    let parts = leaves(&body.colliders, Vector3::new(0.0, 3.0, 0.0), Quaternion::from_angle_z(cgmath::Rad(std::f64::consts::FRAC_PI_2)));
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[1].0, ColliderPart { collider: 0, child: Some(1) });
+    // Turning the body a quarter turn about z swings the lower box round to +x
+    assert!((parts[1].2 - Vector3::new(1.0, 3.0, 0.0)).magnitude() < 1e-12, "{:?}", parts[1].2);
+}
+
+#[test]
+fn compound_reports_the_child_that_landed() {
+    let mut world = box_stack(0);
+    world.add_body(obj(1), dumbbell(Vector3::new(0.0, 2.0, 0.0)));
+    let mut events = Vec::new();
+    for _ in 0..180 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+        events.append(&mut world.take_collision_events());
+    }
+
+    assert_eq!(events.len(), 1, "{:?}", events);
+    assert_eq!(events[0].part_a, ColliderPart { collider: 0, child: None });
+    assert_eq!(events[0].part_b, ColliderPart { collider: 0, child: Some(1) });
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!((body.pos.y - 1.5).abs() < 0.02, "{:?}", body.pos);
+
+    // A ray across the top of the dumbbell hits the upper box
+    let hit = world.raycast(Vector3::new(5.0, body.pos.y + 1.0, 0.0), -Vector3::unit_x(), 100.0, &[]).unwrap();
+    assert_eq!(hit.object, obj(1));
+    assert_eq!(hit.part, ColliderPart { collider: 0, child: Some(0) });
+    assert!((hit.distance - 4.5).abs() < 0.02, "{:?}", hit);
+}
+
+#[test]
+fn primitive_mass_properties() {
+    let sphere = Collider::sphere(2.0);
+    assert!((sphere.volume() - 32.0 / 3.0 * std::f64::consts::PI).abs() < 1e-9);
+    assert_matrix_near(sphere.inertia(3.0), cgmath::Matrix3::from_diagonal(Vector3::new(1.0, 1.0, 1.0)) * (0.4 * 3.0 * sphere.volume() * 4.0), 1e-9);
+
+    let half_extents = Vector3::new(1.0, 2.0, 3.0);
+    let cuboid = Collider::cuboid(half_extents);
+    assert!((cuboid.volume() - 48.0).abs() < 1e-12);
+    let expected = cgmath::Matrix3::from_diagonal(Vector3::new(13.0, 10.0, 5.0)) * 16.0;
+    assert_matrix_near(cuboid.inertia(1.0), expected, 1e-9);
+
+    // The hull of the corners and some points inside is the same box
+    let mut rng = Lcg(7);
+    let center = Vector3::new(5.0, 0.0, 0.0);
+    let mut points = (0..50)
+        .map(|_| center + Vector3::new(rng.next() * 2.0 - 1.0, rng.next() * 4.0 - 2.0, rng.next() * 6.0 - 3.0))
+        .collect::<Vec<_>>();
+    for i in 0..8 {
+        points.push(center + Vector3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -2.0 } else { 2.0 },
+            if i & 4 == 0 { -3.0 } else { 3.0 },
+        ));
+    }
+    let hull = Collider::convex_hull(points);
+    match &hull {
+        Collider::Polyhedron { vertices, .. } => assert_eq!(vertices.len(), 8),
+        _ => unreachable!(),
+    }
+    assert!((hull.volume() - 48.0).abs() < 1e-9, "{}", hull.volume());
+    assert!((hull.center_of_mass() - center).magnitude() < 1e-9, "{:?}", hull.center_of_mass());
+    assert_matrix_near(hull.inertia(1.0), expected, 1e-9);
+
+    // A capsule with no length is a sphere, and a long thin one is a rod
+    assert_matrix_near(Collider::capsule(0.0, 2.0).inertia(3.0), sphere.inertia(3.0), 1e-9);
+    let rod = Collider::capsule(10.0, 0.01);
+    let rod_mass = rod.volume();
+    assert!((rod.inertia(1.0).x.x / (rod_mass * 400.0 / 12.0) - 1.0).abs() < 1e-2);
+}
+
+#[test]
+fn round_colliders_rest_on_the_floor() {
+    let mut world = box_stack(0);
+    world.add_body(obj(1), RigidBody::by_pos(Vector3::new(-3.0, 1.5, 0.0))
+        .collide(vec![Collider::sphere(0.5)], 0.0).solid(1.0));
+    world.add_body(obj(2), RigidBody::by_pos(Vector3::new(0.0, 1.5, 0.0))
+        .collide(vec![Collider::capsule(1.0, 0.5)], 0.0).solid(1.0));
+    world.add_body(obj(3), RigidBody::by_pos(Vector3::new(3.0, 1.5, 0.0))
+        .collide(vec![Collider::cuboid(Vector3::new(0.5, 0.25, 1.0))], 0.0).solid(1.0));
+    for _ in 0..300 {
+        for object in [obj(1), obj(2), obj(3)] {
+            let mass = world.get_body(&object).unwrap().mass;
+            world.push_task(PhysicsTask::AddGlobalForce(object, Vector3::new(0.0, -9.8 * mass, 0.0)));
+        }
+        world.step(DELTA_TIME);
+    }
+    for (object, height) in [(obj(1), 0.5), (obj(2), 0.5), (obj(3), 0.25)] {
+        let body = world.get_body(&object).unwrap();
+        assert!((body.pos.y - height).abs() < 0.02, "{:?} at {:?}", object, body.pos);
+        assert!(body.vel.magnitude() < 0.05, "{:?} moving at {:?}", object, body.vel);
+    }
+}
+
+/// Checks that a polyhedron's support lookups reach as far as a scan of every vertex
+fn assert_support_matches_scan(collider: &Collider, rng: &mut Lcg) {
+    let vertices = match collider {
+        Collider::Polyhedron { vertices, .. } => vertices,
+        _ => unreachable!(),
+    };
+    let scale = collider.radius();
+    for _ in 0..500 {
+        let dir = Vector3::new(rng.next() - 0.5, rng.next() - 0.5, rng.next() - 0.5);
+        let best = vertices.iter().map(|v| v.dot(dir)).fold(f64::NEG_INFINITY, f64::max);
+        let support = collider.support(dir);
+        assert!(vertices.contains(&support));
+        assert!(best - support.dot(dir) <= 1e-9 * scale * dir.magnitude(), "{} short of {} along {:?}", support.dot(dir), best, dir);
+    }
+}
+
+#[test]
+fn hill_climbing_support_matches_scan() {
+    let mut rng = Lcg(13);
+    for trial in 0..20 {
+        // Points on and inside spheres and stretched boxes, with some repeated
+        let num = 10 + 50 * trial;
+        let stretch = Vector3::new(1.0 + trial as f64, 1.0, 0.1 + rng.next());
+        let mut points = (0..num).map(|i| {
+            let p = Vector3::new(rng.next() - 0.5, rng.next() - 0.5, rng.next() - 0.5);
+            let p = if i % 3 == 0 { p } else { p.normalize() };
+            Vector3::new(p.x * stretch.x, p.y * stretch.y, p.z * stretch.z)
+        }).collect::<Vec<_>>();
+        points.extend_from_within(..5);
+        let collider = Collider::polyhedron(points);
+        match &collider {
+            Collider::Polyhedron { adjacency, .. } => assert!(!adjacency.is_empty()),
+            _ => unreachable!(),
+        }
+        assert_support_matches_scan(&collider, &mut rng);
+    }
+
+    // Grid points have many coplanar faces and ties
+    let mut grid = Vec::new();
+    for i in 0..5 {
+        for j in 0..5 {
+            for k in 0..5 {
+                grid.push(Vector3::new(i as f64, j as f64, k as f64));
+            }
+        }
+    }
+    assert_support_matches_scan(&Collider::polyhedron(grid), &mut rng);
+}
+
+#[test]
+fn degenerate_polyhedra_fall_back_to_scanning() {
+    let mut rng = Lcg(17);
+    let flat = (0..40).map(|_| Vector3::new(rng.next(), rng.next(), 0.0)).collect::<Vec<_>>();
+    let line = (0..10).map(|i| Vector3::new(i as f64, 2.0 * i as f64, 0.0)).collect::<Vec<_>>();
+    for collider in [Collider::polyhedron(flat), Collider::polyhedron(line), Collider::polyhedron(vec![Vector3::new(1.0, 2.0, 3.0)])] {
+        match &collider {
+            Collider::Polyhedron { adjacency, .. } => assert!(adjacency.is_empty()),
+            _ => unreachable!(),
+        }
+        assert_support_matches_scan(&collider, &mut rng);
+    }
+}
+
+#[test]
+fn time_of_impact_by_conservative_advancement() {
+    let a = RigidBody::by_pos(Vector3::zero()).collide(vec![Collider::cube(0.5)], 0.0);
+    let approaching = RigidBody::new(Vector3::new(2.0, 0.0, 0.0), Vector3::new(-10.0, 0.0, 0.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::zero())
+        .collide(vec![Collider::sphere(0.5)], 0.0);
+    let t = a.detect_collision(&approaching, 0.2).unwrap();
+    assert!((t - 0.1).abs() < 1e-3, "{}", t);
+    assert!(a.detect_collision(&approaching, 0.05).is_none());
+
+    let receding = RigidBody::new(Vector3::new(2.0, 0.0, 0.0), Vector3::new(10.0, 0.0, 0.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::zero())
+        .collide(vec![Collider::sphere(0.5)], 0.0);
+    assert!(a.detect_collision(&receding, 0.2).is_none());
+
+    // A spinning rod sweeps its tip into the box without its center coming any closer
+    let rod = RigidBody::new(Vector3::new(0.0, 3.0, 0.0), Vector3::zero(), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::new(10.0, 0.0, 0.0))
+        .collide(vec![Collider::capsule(3.0, 0.1)], 0.0);
+    let t = a.detect_collision(&rod, 0.2).unwrap();
+    assert!(t > 0.0 && t < 0.2, "{}", t);
+
+    // Overlapping bodies are reported at once rather than searched for
+    let overlapping = RigidBody::new(Vector3::new(0.5, 0.0, 0.0), Vector3::new(10.0, 0.0, 0.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::zero())
+        .collide(vec![Collider::sphere(0.5)], 0.0);
+    assert_eq!(a.detect_collision(&overlapping, 0.2), Some(0.0));
+    assert_eq!(overlapping.detect_collision(&a, 0.2), Some(0.0));
+}
+
+#[test]
+fn fast_box_does_not_tunnel_through_thin_plate() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::zero())
+        .collide(vec![Collider::cuboid(Vector3::new(5.0, 0.02, 5.0))], 0.0));
+    // Moves ten times its own size each step
+    world.add_body(obj(1), RigidBody::new(Vector3::new(0.0, 3.0, 0.0), Vector3::new(0.0, -60.0, 0.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::zero())
+        .collide(vec![Collider::cuboid(Vector3::new(0.05, 0.05, 0.05))], 0.0)
+        .solid(1000.0));
+    for _ in 0..30 {
+        world.step(DELTA_TIME);
+        let body = world.get_body(&obj(1)).unwrap();
+        assert!(body.pos.y > 0.0, "{:?}", body.pos);
+    }
+    assert!(world.get_body(&obj(1)).unwrap().vel.magnitude() < 0.1);
+}
+
+#[test]
+fn overlapping_bodies_are_pushed_apart() {
+    let mut world = box_stack(1);
+    world.get_body_mut(&obj(1)).unwrap().pos.y = 0.3;
+    run(&mut world, 120);
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!(body.pos.y > 0.45, "{:?}", body.pos);
+}
@@ -0,0 +1,28 @@
+use super::*;
+
+#[test]
+fn filtered_and_ignored_bodies_pass_through_each_other() {
+    let fall = |world: &mut PhysicsWorld| {
+        for _ in 0..60 {
+            world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+            world.step(DELTA_TIME);
+        }
+        world.get_body(&obj(1)).unwrap().pos.y
+    };
+    let mut world = box_stack(1);
+    assert!((fall(&mut world) - 0.5).abs() < 0.02);
+
+    // The floor only collides with the first group, and the box is in the second
+    let mut world = box_stack(1);
+    world.push_task(PhysicsTask::SetCollisionFilter(obj(0), CollisionFilter::new(1, 1)));
+    world.push_task(PhysicsTask::SetCollisionFilter(obj(1), CollisionFilter::new(2, u32::MAX)));
+    assert!(fall(&mut world) < -3.0);
+
+    let mut world = box_stack(1);
+    world.push_task(PhysicsTask::IgnoreCollisions(obj(1), obj(0)));
+    assert!(fall(&mut world) < -3.0);
+    assert!(world.ignores_collisions(obj(0), obj(1)));
+    world.push_task(PhysicsTask::Despawn(obj(1)));
+    world.step(DELTA_TIME);
+    assert!(!world.ignores_collisions(obj(0), obj(1)));
+}
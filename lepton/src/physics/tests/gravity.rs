@@ -0,0 +1,48 @@
+use super::*;
+
+#[test]
+fn barnes_hut_gravity_matches_pairwise() {
+    let mut rng = Lcg(7);
+    let mut world = PhysicsWorld::new();
+    for i in 0..300 {
+        let pos = Vector3::new(rng.next(), rng.next(), rng.next()) * 100.0;
+        world.add_body(obj(i), RigidBody::by_pos(pos).gravitate(1.0 + rng.next()));
+    }
+    let probes = (0..20).map(|_| Vector3::new(rng.next(), rng.next(), rng.next()) * 150.0 - Vector3::new(25.0, 25.0, 25.0)).collect::<Vec<_>>();
+    world.set_gravity(Some(Gravity::new(2.0).soften(0.1)));
+    let exact = probes.iter().map(|p| world.gravity_at(*p)).collect::<Vec<_>>();
+    for (opening_angle, tol) in [(0.0, 1e-12), (0.5, 2e-2)] {
+        world.set_gravity(Some(Gravity::new(2.0).soften(0.1).barnes_hut(opening_angle)));
+        for (p, exact) in probes.iter().zip(&exact) {
+            let error = (world.gravity_at(*p) - exact).magnitude() / exact.magnitude();
+            assert!(error < tol, "opening angle {} was off by {} at {:?}", opening_angle, error, p);
+        }
+    }
+}
+
+#[test]
+fn gravity_pulls_particles_and_sources() {
+    let mut world = PhysicsWorld::new().with_gravity(Gravity::new(3.0).soften(1.0));
+    let identity = cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0);
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::zero()).motivate(4.0, identity).gravitate(4.0));
+    world.add_body(obj(1), RigidBody::by_pos(Vector3::new(1.0, 0.0, 0.0)).motivate(2.0, identity).gravitate(2.0));
+    world.add_body(obj(2), RigidBody::by_pos(Vector3::new(0.0, 2.0, 0.0)).motivate(1.0, identity).attracted());
+    world.add_body(obj(3), RigidBody::by_pos(Vector3::new(0.0, -2.0, 0.0)).motivate(1.0, identity));
+    world.step(0.5);
+
+    // The sources pull each other equally and oppositely, with the softened strength
+    let (a, b) = (world.get_body(&obj(0)).unwrap(), world.get_body(&obj(1)).unwrap());
+    assert!((a.vel * 4.0 + b.vel * 2.0).magnitude() < 1e-12);
+    let expected = 3.0 * 2.0 / 2.0f64.powf(1.5) * 0.5;
+    assert!((a.vel - Vector3::new(expected, 0.0, 0.0)).magnitude() < 1e-12, "{:?}", a.vel);
+
+    // The particle is pulled by both sources without pulling back, and the inert body not at all
+    let particle = world.get_body(&obj(2)).unwrap();
+    let pull = |pos: Vector3<f64>, mass: f64| {
+        let d = pos - Vector3::new(0.0, 2.0, 0.0);
+        d * (3.0 * mass / (d.magnitude2() + 1.0).powf(1.5))
+    };
+    let expected = (pull(Vector3::zero(), 4.0) + pull(Vector3::new(1.0, 0.0, 0.0), 2.0)) * 0.5;
+    assert!((particle.vel - expected).magnitude() < 1e-12, "{:?} but expected {:?}", particle.vel, expected);
+    assert_eq!(world.get_body(&obj(3)).unwrap().vel, Vector3::zero());
+}
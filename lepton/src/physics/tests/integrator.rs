@@ -0,0 +1,100 @@
+use super::*;
+
+/// A body on an eccentric orbit of period 2π about a fixed center with μ = 1
+fn orbiting_body(integrator: Integrator) -> RigidBody {
+    RigidBody::new(Vector3::new(0.5, 0.0, 0.0), Vector3::new(0.0, 3.0f64.sqrt(), 0.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::zero())
+        .motivate(1.0, cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0))
+        .integrate(integrator, RotationIntegrator::Euler)
+        .field(Box::new(|p: Vector3<f64>| -p / p.magnitude().powi(3)))
+}
+
+/// Check the energy and angular momentum of orbits of a thousand steps each
+fn check_orbit_drift(orbits: usize) {
+    let energy = |body: &RigidBody| 0.5 * body.vel.magnitude2() - 1.0 / body.pos.magnitude();
+    let ang_mom = |body: &RigidBody| body.pos.cross(body.vel).z;
+    let dt = 2.0 * std::f64::consts::PI / 1000.0;
+    // Leapfrog keeps angular momentum exactly, and its energy error stays bounded
+    for (integrator, energy_tol, ang_mom_tol) in [(Integrator::Leapfrog, 1e-3, 1e-9), (Integrator::Rk4, 1e-6, 1e-6)] {
+        let mut body = orbiting_body(integrator);
+        let (start_energy, start_ang_mom) = (energy(&body), ang_mom(&body));
+        let mut worst: f64 = 0.0;
+        for _ in 0..orbits * 1000 {
+            body.update(dt);
+            worst = worst.max(((energy(&body) - start_energy) / start_energy).abs());
+        }
+        assert!(worst < energy_tol, "{:?} energy drifted by {}", integrator, worst);
+        assert!(((ang_mom(&body) - start_ang_mom) / start_ang_mom).abs() < ang_mom_tol, "{:?} angular momentum drifted", integrator);
+    }
+}
+
+#[test]
+fn orbit_energy_drift_by_integrator() {
+    check_orbit_drift(10);
+}
+
+#[test]
+#[ignore = "a million steps, run with --ignored"]
+fn orbit_energy_drift_over_a_million_steps() {
+    check_orbit_drift(1000);
+}
+
+/// A tumbling body with three different moments of inertia, whose principal axes are turned away
+/// from its local axes
+fn tumbling_body(rotation_integrator: RotationIntegrator) -> RigidBody {
+    let turn = cgmath::Matrix3::from(Quaternion::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), cgmath::Rad(0.7)));
+    let moi = turn * cgmath::Matrix3::from_diagonal(Vector3::new(1.0, 2.0, 3.0)) * cgmath::Matrix::transpose(&turn);
+    let mut body = RigidBody::new(Vector3::zero(), Vector3::zero(), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::new(0.3, 2.0, -0.5))
+        .motivate(1.0, cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0))
+        .integrate(Integrator::SemiImplicitEuler, rotation_integrator);
+    body.local_moi = moi;
+    body.local_moi_inv = moi.invert().unwrap();
+    body
+}
+
+fn check_torque_free_drift(steps: usize) {
+    let energy = |body: &RigidBody| 0.5 * body.ang_vel.dot(body.moi() * body.ang_vel);
+    let mut body = tumbling_body(RotationIntegrator::TorqueFree);
+    let (start_energy, start_momentum) = (energy(&body), body.moi() * body.ang_vel);
+    for _ in 0..steps {
+        body.update(0.01);
+    }
+    // Only rounding error accumulates, at about one part in 10¹⁵ per step
+    assert!(((energy(&body) - start_energy) / start_energy).abs() < 1e-8, "{} to {}", start_energy, energy(&body));
+    let momentum = body.moi() * body.ang_vel;
+    assert!((momentum - start_momentum).magnitude() / start_momentum.magnitude() < 1e-8, "{:?} to {:?}", start_momentum, momentum);
+}
+
+#[test]
+fn torque_free_rotation_keeps_energy_and_angular_momentum() {
+    check_torque_free_drift(10_000);
+}
+
+#[test]
+#[ignore = "a million steps, run with --ignored"]
+fn torque_free_rotation_keeps_energy_over_a_million_steps() {
+    check_torque_free_drift(1_000_000);
+}
+
+#[test]
+fn torque_free_rotation_follows_the_equations_of_motion() {
+    // Fine fourth-order steps agree with single exact steps
+    let mut exact = tumbling_body(RotationIntegrator::TorqueFree);
+    let mut fine = tumbling_body(RotationIntegrator::Rk4);
+    for _ in 0..10 {
+        exact.update(0.5);
+        for _ in 0..5000 {
+            fine.update(0.0001);
+        }
+        let difference = exact.orientation - fine.orientation;
+        let difference = if difference.magnitude() > 1.0 { exact.orientation + fine.orientation } else { difference };
+        assert!(difference.magnitude() < 1e-8, "{:?} but expected {:?}", exact.orientation, fine.orientation);
+        assert!((exact.ang_vel - fine.ang_vel).magnitude() < 1e-8, "{:?} but expected {:?}", exact.ang_vel, fine.ang_vel);
+    }
+
+    // A torque kicks the body between exact steps
+    let mut body = tumbling_body(RotationIntegrator::TorqueFree);
+    let before = body.moi() * body.ang_vel;
+    body.torque_impulse = Vector3::new(0.0, 0.0, 1.0);
+    body.update(0.0);
+    assert!((body.moi() * body.ang_vel - before - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-12);
+}
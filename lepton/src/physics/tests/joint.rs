@@ -0,0 +1,113 @@
+use super::*;
+
+#[test]
+fn ball_joint_swings_as_pendulum() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0)));
+    world.add_body(obj(1), unit_box(Vector3::new(2.0, 0.0, 0.0)));
+    world.push_task(PhysicsTask::CreateJoint(0, Joint::ball(obj(0), obj(1), Vector3::zero(), Vector3::new(-2.0, 0.0, 0.0))));
+    let mut lowest: f64 = 0.0;
+    for _ in 0..300 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+        let body = world.get_body(&obj(1)).unwrap();
+        let anchor = body.pos + body.orientation * Vector3::new(-2.0, 0.0, 0.0);
+        assert!(anchor.magnitude() < 0.02, "anchor drifted to {:?}", anchor);
+        lowest = lowest.min(body.pos.y);
+    }
+    // The bob swings down through the bottom of its arc
+    assert!(lowest < -1.9, "{}", lowest);
+
+    world.push_task(PhysicsTask::DestroyJoint(0));
+    world.step(DELTA_TIME);
+    assert!(world.get_joint(&0).is_none());
+}
+
+#[test]
+fn hinge_only_turns_about_its_axis() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0)));
+    world.add_body(obj(1), unit_box(Vector3::new(0.0, 0.0, 1.0)));
+    world.add_joint(0, Joint::hinge(obj(0), obj(1), Vector3::zero(), Vector3::new(0.0, 0.0, -1.0), Vector3::unit_y()));
+    for _ in 0..120 {
+        world.push_task(PhysicsTask::AddGlobalImpulseTorque(obj(1), Vector3::new(0.02, 0.02, 0.02)));
+        world.step(DELTA_TIME);
+    }
+    let body = world.get_body(&obj(1)).unwrap();
+    let axis = body.orientation * Vector3::unit_y();
+    assert!(axis.y > 0.999, "{:?}", axis);
+    assert!(body.ang_vel.y > 1.0, "{:?}", body.ang_vel);
+    assert!(body.ang_vel.x.abs() + body.ang_vel.z.abs() < 0.05, "{:?}", body.ang_vel);
+    let anchor = body.pos + body.orientation * Vector3::new(0.0, 0.0, -1.0);
+    assert!(anchor.magnitude() < 0.02, "{:?}", anchor);
+}
+
+#[test]
+fn slider_moves_only_along_its_axis() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0)));
+    world.add_body(obj(1), unit_box(Vector3::new(0.0, 0.0, 0.0)));
+    world.add_joint(0, Joint::slider(obj(0), obj(1), Vector3::zero(), Vector3::zero(), Vector3::unit_y()));
+    for _ in 0..120 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), Vector3::new(3.0, 2.0, -1.0)));
+        world.push_task(PhysicsTask::AddGlobalImpulseTorque(obj(1), Vector3::new(0.0, 0.05, 0.05)));
+        world.step(DELTA_TIME);
+    }
+    // Only the force along the shaft moves the car, and nothing turns it
+    let body = world.get_body(&obj(1)).unwrap();
+    let expected = 0.5 * 2.0 * (120.0 * DELTA_TIME).powi(2);
+    assert!((body.pos.y - expected).abs() < 0.05, "{:?}, expected y = {}", body.pos, expected);
+    assert!(body.pos.x.abs() + body.pos.z.abs() < 0.01, "{:?}", body.pos);
+    assert!(body.orientation.s > 0.9999, "{:?}", body.orientation);
+}
+
+#[test]
+fn fixed_joint_holds_bodies_together() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), unit_box(Vector3::new(0.0, 0.0, 0.0)));
+    world.add_body(obj(1), unit_box(Vector3::new(1.0, 0.0, 0.0)));
+    world.add_joint(0, Joint::fixed(obj(0), obj(1), Vector3::new(0.5, 0.0, 0.0), Vector3::new(-0.5, 0.0, 0.0)));
+    for _ in 0..120 {
+        world.push_task(PhysicsTask::AddGlobalImpulseTorque(obj(0), Vector3::new(0.0, 0.0, 0.02)));
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), Vector3::new(0.0, 1.0, 0.0)));
+        world.step(DELTA_TIME);
+    }
+    let (a, b) = (world.get_body(&obj(0)).unwrap(), world.get_body(&obj(1)).unwrap());
+    let relative = a.orientation.conjugate() * b.orientation;
+    assert!(relative.s.abs() > 0.9999, "{:?}", relative);
+    let gap = (b.pos + b.orientation * Vector3::new(-0.5, 0.0, 0.0)) - (a.pos + a.orientation * Vector3::new(0.5, 0.0, 0.0));
+    assert!(gap.magnitude() < 0.01, "{:?}", gap);
+    assert!(a.ang_vel.z > 0.5, "{:?}", a.ang_vel);
+}
+
+#[test]
+fn spring_settles_and_overloaded_joint_breaks() {
+    const STIFFNESS: f64 = 50.0;
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0)));
+    world.add_body(obj(1), unit_box(Vector3::new(0.0, -1.0, 0.0)));
+    world.add_body(obj(2), RigidBody::by_pos(Vector3::new(5.0, 0.0, 0.0)));
+    world.add_body(obj(3), unit_box(Vector3::new(5.0, -1.0, 0.0)));
+    world.add_joint(0, Joint::spring(obj(0), obj(1), Vector3::zero(), Vector3::zero(), 1.0, STIFFNESS, 5.0));
+    world.add_joint(1, Joint::distance(obj(2), obj(3), Vector3::zero(), Vector3::zero(), 1.0).break_force(20.0));
+    for i in 0..600 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        // The tether holds the weight, but not a sudden tug later on
+        let tug = if i == 300 { 1_000.0 } else { 0.0 };
+        world.push_task(PhysicsTask::AddGlobalForce(obj(3), Vector3::new(0.0, -9.8 - tug, 0.0)));
+        world.step(DELTA_TIME);
+        if i == 299 {
+            let force = world.get_joint(&1).unwrap().force();
+            assert!((force.y - 9.8).abs() < 0.1, "{:?}", force);
+            assert!(world.take_broken_joints().is_empty());
+        }
+    }
+
+    // The spring stretches until it holds the weight
+    let body = world.get_body(&obj(1)).unwrap();
+    let expected = -1.0 - 9.8 / STIFFNESS;
+    assert!((body.pos.y - expected).abs() < 0.01, "{:?}, expected y = {}", body.pos, expected);
+    assert!((world.get_joint(&0).unwrap().force().y - 9.8).abs() < 0.1);
+    assert_eq!(world.take_broken_joints(), vec![1]);
+    assert!(world.get_joint(&1).is_none());
+}
@@ -0,0 +1,110 @@
+use super::*;
+
+#[test]
+fn box_comes_to_rest_flat() {
+    let mut world = box_stack(1);
+    for _ in 0..300 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+    }
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!((body.pos.y - 0.5).abs() < 0.01, "{:?}", body.pos);
+    assert!(body.vel.magnitude() < 1e-3, "{:?}", body.vel);
+    assert!(body.ang_vel.magnitude() < 1e-3, "{:?}", body.ang_vel);
+}
+
+#[test]
+fn stacked_boxes_come_to_rest() {
+    const NUM_BOXES: usize = 3;
+    let mut world = box_stack(NUM_BOXES);
+    for _ in 0..600 {
+        for i in 0..NUM_BOXES {
+            world.push_task(PhysicsTask::AddGlobalForce(obj(i as u32 + 1), GRAVITY));
+        }
+        world.step(DELTA_TIME);
+    }
+
+    for i in 0..NUM_BOXES {
+        let body = world.get_body(&(obj(i as u32 + 1))).unwrap();
+        let rest_height = 0.5 + i as f64;
+        assert!((body.pos.y - rest_height).abs() < 0.05, "box {} at {:?}", i, body.pos);
+        assert!(Vector3::new(body.pos.x, 0.0, body.pos.z).magnitude() < 0.05, "box {} slid to {:?}", i, body.pos);
+        assert!(body.vel.magnitude() < 0.01, "box {} moving at {:?}", i, body.vel);
+        assert!(body.ang_vel.magnitude() < 0.01, "box {} spinning at {:?}", i, body.ang_vel);
+        let up = body.orientation * Vector3::unit_y();
+        assert!(up.y > 0.999, "box {} tipped to {:?}", i, up);
+    }
+}
+
+#[test]
+fn sliding_box_stops_under_dynamic_friction() {
+    let mut world = box_stack(1);
+    world.get_body_mut(&obj(1)).unwrap().pos.y = 0.5;
+    world.get_body_mut(&obj(1)).unwrap().vel = Vector3::new(3.0, 0.0, 0.0);
+    for _ in 0..120 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+    }
+
+    // Decelerating at dynamic friction times g, the box stops after v^2 / (2 mu g)
+    let body = world.get_body(&obj(1)).unwrap();
+    let expected = 3.0 * 3.0 / (2.0 * body.dynamic_friction * 9.8);
+    assert!(body.vel.magnitude() < 1e-3, "{:?}", body.vel);
+    assert!((body.pos.x - expected).abs() < 0.1, "stopped at {:?}, expected x = {}", body.pos, expected);
+}
+
+#[test]
+fn static_friction_holds_box_against_push() {
+    let mut world = box_stack(1);
+    for _ in 0..120 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+    }
+    // A push within the static cone, but beyond the dynamic one
+    let push = 0.5 * 9.8;
+    for _ in 0..120 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), Vector3::new(push, -9.8, 0.0)));
+        world.step(DELTA_TIME);
+    }
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!(body.pos.x.abs() < 0.01, "{:?}", body.pos);
+}
+
+#[test]
+fn box_rides_moving_deck() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), floor(0.0).line(Vector3::new(1.0, 0.0, 0.0), 1.0, f64::INFINITY));
+    world.add_body(obj(1), unit_cube(Vector3::new(0.0, 0.5, 0.0)));
+    let mut offset = 0.0;
+    for step in 0..180 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+        if step == 60 {
+            offset = world.get_body(&obj(1)).unwrap().pos.x - world.get_body(&obj(0)).unwrap().pos.x;
+        }
+    }
+
+    // Once friction has brought the box up to speed, it is carried along without slipping
+    let deck = world.get_body(&obj(0)).unwrap();
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!((body.vel - deck.vel).magnitude() < 1e-2, "{:?}", body.vel);
+    assert!((body.pos.x - deck.pos.x - offset).abs() < 1e-2, "box at {:?}, deck at {:?}", body.pos, deck.pos);
+}
+
+#[test]
+fn planet_friction_stops_landed_box() {
+    const RADIUS: f64 = 20.0;
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0))
+        .collide(vec![Collider::planet(Box::new(|p: Vector3<f64>| p.magnitude() - RADIUS), RADIUS * 1.1)], 0.0));
+    world.add_body(obj(1), unit_cube(Vector3::new(0.0, RADIUS + 0.5, 0.0)));
+    world.get_body_mut(&obj(1)).unwrap().vel = Vector3::new(2.0, 0.0, 0.0);
+    for _ in 0..180 {
+        let down = -world.get_body(&obj(1)).unwrap().pos.normalize();
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), down * 9.8));
+        world.step(DELTA_TIME);
+    }
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!(body.vel.magnitude() < 0.05, "{:?}", body.vel);
+    assert!(body.pos.x.abs() < 1.0, "{:?}", body.pos);
+}
@@ -0,0 +1,94 @@
+use cgmath::{Vector3, Quaternion, InnerSpace, Zero, Rotation3, SquareMatrix};
+
+use super::*;
+
+mod broadphase;
+mod world;
+mod rigid_body;
+mod orbit;
+mod collider;
+mod manifold;
+mod joint;
+mod query;
+mod integrator;
+mod gravity;
+mod aerodynamics;
+mod filter;
+mod replay;
+
+const DELTA_TIME: f64 = 1.0 / 60.0;
+/// The weight of a unit mass near the ground
+const GRAVITY: Vector3<f64> = Vector3::new(0.0, -9.8, 0.0);
+
+/// Small deterministic generator so the tests don't need a rand dependency
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn vector(&mut self, scale: f64) -> Vector3<f64> {
+        Vector3::new(self.next() - 0.5, self.next() - 0.5, self.next() - 0.5) * 2.0 * scale
+    }
+}
+
+/// The object in slot index, first time round
+fn obj(index: u32) -> Object {
+    Object { index, generation: 0 }
+}
+
+/// Step the world a number of times
+fn run(world: &mut PhysicsWorld, steps: usize) {
+    for _ in 0..steps {
+        world.step(DELTA_TIME);
+    }
+}
+
+/// A free body with the mass and inertia of a solid box of unit mass and width, but no collider
+fn unit_box(pos: Vector3<f64>) -> RigidBody {
+    RigidBody::by_pos(pos)
+        .motivate(1.0, cgmath::Matrix3::new(1.0 / 6.0, 0.0, 0.0, 0.0, 1.0 / 6.0, 0.0, 0.0, 0.0, 1.0 / 6.0))
+}
+
+/// A unit box which collides as a cube of half width 0.5
+fn unit_cube(pos: Vector3<f64>) -> RigidBody {
+    unit_box(pos).collide(vec![Collider::cube(0.5)], 0.0)
+}
+
+/// A fixed cube of half width 5 whose top is at y = 0
+fn floor(elasticity: f64) -> RigidBody {
+    RigidBody::by_pos(Vector3::new(0.0, -5.0, 0.0))
+        .collide(vec![Collider::cube(5.0)], elasticity)
+}
+
+/// A uniform field pulling down with GRAVITY
+fn falling_field() -> Field {
+    Box::new(|_| GRAVITY)
+}
+
+/// Unit cubes dropped onto the floor
+fn box_stack(num: usize) -> PhysicsWorld {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), floor(0.5));
+    for i in 0..num {
+        world.add_body(obj(i as u32 + 1), unit_cube(Vector3::new(0.0, 0.55 + 1.05 * i as f64, 0.0)));
+    }
+    world
+}
+
+/// A stack of boxes pulled down by a uniform field, so that nothing needs to push them each step
+fn settling_stack(num: usize) -> PhysicsWorld {
+    let mut world = box_stack(num);
+    for i in 0..num {
+        world.get_body_mut(&obj(i as u32 + 1)).unwrap().field = Some(falling_field());
+    }
+    world
+}
+
+fn assert_matrix_near(a: cgmath::Matrix3<f64>, b: cgmath::Matrix3<f64>, tol: f64) {
+    for i in 0..3 {
+        assert!((a[i] - b[i]).magnitude() < tol, "{:?} but expected {:?}", a, b);
+    }
+}
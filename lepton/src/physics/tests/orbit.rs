@@ -0,0 +1,64 @@
+use crate::physics::orbit;
+use super::*;
+
+#[test]
+fn kepler_solver_inverts() {
+    let mu = 3.0;
+    let ang_mom = Vector3::new(0.0, 0.0, 2.0);
+    for e in [0.0, 0.3, 0.95, 1.0, 1.5, 4.0] {
+        let eccentricity = Vector3::new(e, 0.0, 0.0);
+        for t in [-7.0, -0.5, 0.0, 0.1, 1.3, 20.0] {
+            let nu = orbit::true_anomaly(mu, t, eccentricity, ang_mom);
+            let back = orbit::time_since_perigee(mu, nu, eccentricity, ang_mom);
+            let again = orbit::true_anomaly(mu, back, eccentricity, ang_mom);
+            assert!((again - nu).sin().abs() < 1e-9 && (again - nu).cos() > 0.0, "e = {}, t = {}", e, t);
+
+            // The state conserves energy and angular momentum
+            let (pos, vel) = orbit::get_state(mu, t, eccentricity, ang_mom, Vector3::unit_x());
+            assert!((pos.cross(vel) - ang_mom).magnitude() < 1e-9);
+            let (pos0, vel0) = orbit::get_state(mu, 0.0, eccentricity, ang_mom, Vector3::unit_x());
+            let energy = vel.magnitude2() / 2.0 - mu / pos.magnitude();
+            let energy0 = vel0.magnitude2() / 2.0 - mu / pos0.magnitude();
+            assert!((energy - energy0).abs() < 1e-9 * (1.0 + energy0.abs()));
+        }
+    }
+}
+
+#[test]
+fn orbit_takes_large_steps_without_drift() {
+    let mu = 1.0e4;
+    let ang_mom = Vector3::new(0.0, 0.0, 300.0);
+    let eccentricity = Vector3::new(0.2, 0.4, 0.0);
+    let period = orbit::period(mu, eccentricity, ang_mom).unwrap();
+    let make = || RigidBody::by_pos(Vector3::zero())
+        .orbit(mu, Vector3::new(10.0, 0.0, 0.0), eccentricity, ang_mom, 0.3 * period);
+
+    let mut small = PhysicsWorld::new();
+    small.add_body(obj(0), make());
+    for _ in 0..1000 {
+        small.step(period / 370.0);
+    }
+    let mut large = make();
+    large.update_forceless(1000.0 * period / 370.0);
+    assert!((large.pos - small.get_body(&obj(0)).unwrap().pos).magnitude() < 1e-6);
+    assert!((large.vel - small.get_body(&obj(0)).unwrap().vel).magnitude() < 1e-6);
+
+    // The body starts in the direction it was placed in, and a million orbits later it is back
+    let start = make();
+    assert!(((start.pos - Vector3::new(10.0, 0.0, 0.0)).normalize() + Vector3::unit_x()).magnitude() < 1e-9);
+    let mut warped = make();
+    warped.update_forceless(1.0e6 * period);
+    assert!((warped.pos - start.pos).magnitude() < 1e-6 * start.pos.magnitude());
+}
+
+#[test]
+fn circular_orbit_keeps_its_radius() {
+    let mut body = RigidBody::by_pos(Vector3::new(0.0, 500.0, 3.0))
+        .circ_orbit(2.0e5, Vector3::zero(), Vector3::new(0.0, 0.0, 1.0));
+    assert_eq!(body.pos, Vector3::new(0.0, 500.0, 0.0));
+    assert!((body.vel - Vector3::new(20.0, 0.0, 0.0)).magnitude() < 1e-9);
+    for _ in 0..100 {
+        body.update(1234.5);
+        assert!((body.pos.magnitude() - 500.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+fn query_world() -> PhysicsWorld {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(5.0, 0.0, 0.0))
+        .collide(vec![Collider::cube(1.0)], 1.0));
+    world.add_body(obj(1), RigidBody::by_pos(Vector3::new(0.0, -20.0, 0.0))
+        .collide(vec![Collider::planet(Box::new(|p: Vector3<f64>| p.magnitude() - 10.0), 11.0)], 1.0));
+    // An octahedron turned a quarter turn about z, so it still has a vertex towards -x
+    let octahedron = vec![
+        Vector3::new(2.0, 0.0, 0.0), Vector3::new(-2.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0),
+        Vector3::new(0.0, -2.0, 0.0), Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -2.0),
+    ];
+    world.add_body(obj(2), RigidBody::new(Vector3::new(-10.0, 0.0, 0.0), Vector3::zero(),
+        Quaternion::from_angle_z(cgmath::Rad(std::f64::consts::FRAC_PI_2)), Vector3::zero())
+        .collide(vec![Collider::polyhedron(octahedron)], 1.0));
+    world
+}
+
+#[test]
+fn raycasts_hit_every_collider_kind() {
+    let world = query_world();
+    let origin = Vector3::new(0.0, 0.0, 0.0);
+
+    let hit = world.raycast(origin, Vector3::new(2.0, 0.0, 0.0), 100.0, &[]).unwrap();
+    assert_eq!(hit.object, obj(0));
+    assert!((hit.distance - 4.0).abs() < 1e-6, "{:?}", hit);
+    assert!((hit.point - Vector3::new(4.0, 0.0, 0.0)).magnitude() < 1e-6, "{:?}", hit);
+    assert!((hit.normal + Vector3::unit_x()).magnitude() < 1e-6, "{:?}", hit);
+
+    let hit = world.raycast(origin, -Vector3::unit_y(), 100.0, &[]).unwrap();
+    assert_eq!(hit.object, obj(1));
+    assert!((hit.distance - 10.0).abs() < 1e-6, "{:?}", hit);
+    assert!((hit.normal - Vector3::unit_y()).magnitude() < 1e-4, "{:?}", hit);
+
+    let hit = world.raycast(origin, -Vector3::unit_x(), 100.0, &[]).unwrap();
+    assert_eq!(hit.object, obj(2));
+    assert!((hit.distance - 8.0).abs() < 1e-6, "{:?}", hit);
+
+    // Rays passing beside a collider or stopping short of it find nothing
+    assert!(world.raycast(origin, Vector3::new(-1.0, 0.3, 0.0), 100.0, &[]).is_none_or(|hit| hit.object != obj(2)));
+    assert!(world.raycast(origin, Vector3::unit_x(), 3.0, &[]).is_none());
+    assert!(world.raycast(origin, Vector3::unit_z(), 100.0, &[]).is_none());
+    assert_eq!(world.raycast(origin, Vector3::unit_x(), 100.0, &[obj(0)]).map(|hit| hit.object), None);
+}
+
+#[test]
+fn sphere_casts_and_overlaps() {
+    let world = query_world();
+    let origin = Vector3::new(0.0, 0.0, 0.0);
+
+    // The sphere touches the cube's face a radius before the ray would
+    let hit = world.sphere_cast(origin, 0.5, Vector3::unit_x(), 100.0, &[]).unwrap();
+    assert_eq!(hit.object, obj(0));
+    assert!((hit.distance - 3.5).abs() < 1e-6, "{:?}", hit);
+    assert!((hit.point.x - 4.0).abs() < 1e-6, "{:?}", hit);
+
+    // A sphere passing just beside the cube's edge still clips it
+    let hit = world.sphere_cast(Vector3::new(0.0, 1.3, 0.0), 0.5, Vector3::unit_x(), 100.0, &[]).unwrap();
+    assert_eq!(hit.object, obj(0));
+    assert!((hit.point - Vector3::new(4.0, 1.0, hit.point.z)).magnitude() < 1e-6, "{:?}", hit);
+
+    let hit = world.sphere_cast(origin, 2.0, -Vector3::unit_y(), 100.0, &[]).unwrap();
+    assert_eq!(hit.object, obj(1));
+    assert!((hit.distance - 8.0).abs() < 1e-6, "{:?}", hit);
+    assert!((hit.point - Vector3::new(0.0, -10.0, 0.0)).magnitude() < 1e-4, "{:?}", hit);
+
+    assert_eq!(world.overlap_sphere(Vector3::new(3.5, 0.0, 0.0), 0.6), vec![obj(0)]);
+    assert_eq!(world.overlap_sphere(Vector3::new(0.0, -9.5, 0.0), 1.0), vec![obj(1)]);
+    assert_eq!(world.overlap_sphere(Vector3::new(-7.0, 0.0, 0.0), 1.5), vec![obj(2)]);
+    assert!(world.overlap_sphere(origin, 1.0).is_empty());
+}
+
+#[test]
+fn queries_are_answered_off_the_physics_thread() {
+    let world = std::sync::Arc::new(std::sync::RwLock::new(query_world()));
+    let queries = PhysicsQueries::new(world.clone());
+    let handle = std::thread::spawn(move || queries.raycast(Vector3::zero(), Vector3::unit_x(), 100.0, &[]));
+    world.write().unwrap().step(DELTA_TIME);
+    assert_eq!(handle.join().unwrap().map(|hit| hit.object), Some(obj(0)));
+}
+
+#[test]
+fn joint_loads_are_answered_by_queries() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::zero()));
+    world.add_body(obj(1), unit_box(Vector3::new(0.0, -1.0, 0.0)));
+    world.add_joint(0, Joint::distance(obj(0), obj(1), Vector3::zero(), Vector3::zero(), 1.0));
+    let world = std::sync::Arc::new(std::sync::RwLock::new(world));
+    let queries = PhysicsQueries::new(world.clone());
+    for _ in 0..60 {
+        let mut world = world.write().unwrap();
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+    }
+    let force = queries.joint_force(0).unwrap();
+    assert!((force.y - 9.8).abs() < 0.1, "{:?}", force);
+    assert!(queries.joint_torque(0).is_some());
+    assert!(queries.joint_force(1).is_none());
+}
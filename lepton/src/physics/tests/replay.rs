@@ -0,0 +1,98 @@
+use super::*;
+
+/// Supplies the uniform field of the settling stack to whichever bodies had one
+struct UniformField;
+
+impl Resolver for UniformField {
+    fn field(&mut self, _object: Object) -> Option<Field> {
+        Some(falling_field())
+    }
+}
+
+type Motion = (Object, Vector3<f64>, Vector3<f64>, Quaternion<f64>, Vector3<f64>, bool);
+
+/// Every body's motion, in order of object
+fn motions(world: &PhysicsWorld) -> Vec<Motion> {
+    let mut motions = world.bodies()
+        .map(|(object, body)| (*object, body.pos, body.vel, body.orientation, body.ang_vel, body.asleep))
+        .collect::<Vec<_>>();
+    motions.sort_unstable_by_key(|motion| motion.0);
+    motions
+}
+
+#[test]
+fn restored_snapshot_continues_bit_for_bit() {
+    let mut world = settling_stack(3);
+    world.add_body(obj(4), RigidBody::by_pos(Vector3::new(10.0, 5.0, 0.0)));
+    world.add_body(obj(5), unit_box(Vector3::new(12.0, 5.0, 0.0)).field(falling_field()));
+    world.push_task(PhysicsTask::CreateJoint(0, Joint::ball(obj(4), obj(5), Vector3::zero(), Vector3::new(-2.0, 0.0, 0.0))));
+    for _ in 0..40 {
+        world.advance(DELTA_TIME * 0.7);
+    }
+
+    // Leave a box queued to be thrown at the stack, so the tasks are saved too
+    world.push_task(PhysicsTask::Spawn(obj(6), Box::new(unit_box(Vector3::new(-3.0, 2.0, 0.0))
+        .collide(vec![Collider::compound(vec![(cgmath::Matrix4::from_scale(1.0), Collider::cuboid(Vector3::new(0.5, 0.5, 0.5)))])], 0.2))));
+    world.push_task(PhysicsTask::SetVel(obj(6), Vector3::new(6.0, 0.0, 0.0)));
+    let bytes = bincode::serialize(&world.snapshot()).unwrap();
+    assert!(PhysicsWorld::from_snapshot(bincode::deserialize(&bytes).unwrap(), &mut NoFunctions).is_err());
+    let mut restored = PhysicsWorld::from_snapshot(bincode::deserialize(&bytes).unwrap(), &mut UniformField).unwrap();
+    assert_eq!(restored.time(), world.time());
+    assert_eq!(motions(&restored), motions(&world));
+
+    // Events which were never taken aren't part of the snapshot
+    world.take_collision_events();
+    let mut events = 0;
+    for _ in 0..240 {
+        world.advance(DELTA_TIME * 0.7);
+        restored.advance(DELTA_TIME * 0.7);
+        assert_eq!(motions(&restored), motions(&world));
+        assert_eq!(restored.interpolated_pose(&obj(6)), world.interpolated_pose(&obj(6)));
+        let (a, b) = (world.take_collision_events(), restored.take_collision_events());
+        assert_eq!(a.len(), b.len());
+        events += a.len();
+    }
+    // The thrown box struck the stack
+    assert!(events > 0);
+}
+
+#[test]
+fn recorded_run_replays_without_divergence() {
+    let mut world = box_stack(2);
+    let mut bytes = Vec::new();
+    let mut recorder = Recorder::new(&mut bytes, &world).unwrap();
+    for i in 0..120 {
+        let mut batch = (1..3).map(|j| PhysicsTask::AddGlobalForce(obj(j), GRAVITY)).collect::<Vec<_>>();
+        if i == 30 {
+            // Throw a box at the stack
+            batch.push(PhysicsTask::Spawn(obj(3), Box::new(unit_box(Vector3::new(-4.0, 1.5, 0.0)).collide(vec![Collider::cube(0.5)], 0.2))));
+            batch.push(PhysicsTask::SetVel(obj(3), Vector3::new(12.0, 0.0, 0.0)));
+        }
+        recorder.batch(&batch);
+        world.push_tasks(batch);
+        // Uneven frames, as the physics thread sees
+        let delta_time = DELTA_TIME * (0.5 + 0.1 * (i % 7) as f64);
+        world.advance(delta_time);
+        recorder.frame(delta_time, &world);
+        recorder.write().unwrap();
+    }
+    drop(recorder);
+
+    let recording = Recording::read(bytes.as_slice()).unwrap();
+    assert_eq!(recording.frames(), 120);
+    let report = recording.replay(&mut NoFunctions, |_, _, _| {}).unwrap();
+    assert_eq!(report.frames, 120);
+    assert!(report.divergence.is_none(), "{:?}", report.divergence);
+    assert_eq!(report.max_pos_error, 0.0);
+
+    // A frame cut off part way is dropped, and a replay which pushes the stack differently is
+    // caught at the first frame which takes a step
+    let recording = Recording::read(&bytes[..bytes.len() - 10]).unwrap();
+    assert_eq!(recording.frames(), 119);
+    let report = recording.replay(&mut NoFunctions, |tasks, (o_i, _), _| {
+        tasks.push(PhysicsTask::AddGlobalForce(*o_i, Vector3::new(1e-3, 0.0, 0.0)));
+    }).unwrap();
+    let divergence = report.divergence.unwrap();
+    assert_eq!((divergence.frame, divergence.object), (1, obj(1)));
+    assert!(report.max_pos_error > 0.0);
+}
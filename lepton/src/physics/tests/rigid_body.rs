@@ -0,0 +1,102 @@
+use super::*;
+
+#[test]
+fn line_body_turns_around_at_the_ends() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(1.0, 0.0, 0.0))
+        .line(Vector3::new(0.0, 2.0, 0.0), 3.0, 6.0));
+
+    // Two seconds out, two seconds back, and out again
+    for (steps, y, vel_y) in [(60, 3.0, 3.0), (120, 3.0, -3.0), (120, 3.0, 3.0)] {
+        run(&mut world, steps);
+        let body = world.get_body(&obj(0)).unwrap();
+        assert!((body.pos - Vector3::new(1.0, y, 0.0)).magnitude() < 1e-9, "{:?}", body.pos);
+        assert!((body.vel.y - vel_y).abs() < 1e-9);
+    }
+
+    // Pushing a kinematic body does nothing
+    world.push_task(PhysicsTask::AddGlobalImpulse(obj(0), Vector3::new(100.0, 0.0, 0.0)));
+    world.step(DELTA_TIME);
+    assert_eq!(world.get_body(&obj(0)).unwrap().vel, Vector3::new(0.0, 3.0, 0.0));
+
+    // A rail with no length holds its body still
+    world.add_body(obj(1), RigidBody::by_pos(Vector3::new(0.0, 0.0, 5.0))
+        .line(Vector3::new(1.0, 0.0, 0.0), 3.0, 0.0));
+    world.step(DELTA_TIME);
+    let body = world.get_body(&obj(1)).unwrap();
+    assert_eq!((body.pos, body.vel), (Vector3::new(0.0, 0.0, 5.0), Vector3::zero()));
+}
+
+#[test]
+fn circle_body_turns_with_its_orbit() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(2.0, 0.0, 5.0))
+        .circle(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), std::f64::consts::PI));
+
+    // A quarter turn takes half a second
+    run(&mut world, 30);
+    let body = world.get_body(&obj(0)).unwrap();
+    assert!((body.pos - Vector3::new(0.0, 2.0, 5.0)).magnitude() < 1e-9, "{:?}", body.pos);
+    assert!((body.vel - Vector3::new(-2.0 * std::f64::consts::PI, 0.0, 0.0)).magnitude() < 1e-9);
+    let facing = body.orientation * Vector3::new(1.0, 0.0, 0.0);
+    assert!((facing - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+
+    // Forceless updates follow the same path
+    let mut body = RigidBody::by_pos(Vector3::new(2.0, 0.0, 5.0))
+        .circle(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), std::f64::consts::PI);
+    body.update_forceless(0.5);
+    assert!((body.pos - Vector3::new(0.0, 2.0, 5.0)).magnitude() < 1e-9);
+}
+
+#[test]
+fn line_body_pushes_free_body() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0))
+        .collide(vec![Collider::cube(1.0)], 0.5)
+        .line(Vector3::new(1.0, 0.0, 0.0), 2.0, f64::INFINITY));
+    world.add_body(obj(1), RigidBody::new(Vector3::new(3.0, 0.2, 0.1), Vector3::new(0.0, 0.0, 0.0),
+        Quaternion::new(0.9, 0.2, 0.3, 0.1).normalize(), Vector3::new(0.0, 0.0, 0.0))
+        .motivate(1.0, cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0))
+        .collide(vec![Collider::cube(1.0)], 0.5));
+
+    run(&mut world, 120);
+    // The rail keeps its speed and the free body is knocked ahead of it
+    assert_eq!(world.get_body(&obj(0)).unwrap().vel, Vector3::new(2.0, 0.0, 0.0));
+    assert!(world.get_body(&obj(1)).unwrap().vel.x > 2.0);
+}
+
+#[test]
+fn solid_body_matches_hand_typed_inertia() {
+    let body = RigidBody::by_pos(Vector3::zero())
+        .collide(vec![Collider::cuboid(Vector3::new(0.5, 0.5, 0.5))], 0.0)
+        .solid(1.0);
+    assert!((body.mass - 1.0).abs() < 1e-12);
+    assert_matrix_near(body.moi(), cgmath::Matrix3::from_diagonal(Vector3::new(1.0, 1.0, 1.0)) / 6.0, 1e-12);
+
+    // Two boxes side by side are a box twice as long
+    let pair = Collider::compound(vec![
+        (cgmath::Matrix4::from_translation(Vector3::new(-0.5, 0.0, 0.0)), Collider::cuboid(Vector3::new(0.5, 0.5, 0.5))),
+        (cgmath::Matrix4::from_translation(Vector3::new(0.5, 0.0, 0.0)), Collider::cuboid(Vector3::new(0.5, 0.5, 0.5))),
+    ]);
+    assert_matrix_near(pair.inertia(1.0), Collider::cuboid(Vector3::new(1.0, 0.5, 0.5)).inertia(1.0), 1e-12);
+
+    // A body with nothing to fill stays fixed
+    let empty = RigidBody::by_pos(Vector3::zero()).solid(1.0);
+    assert!(matches!(empty.updater, Updater::Fixed));
+    assert_eq!(empty.mass, 0.0);
+}
+
+#[test]
+fn far_bodies_are_drawn_precisely_near_the_render_origin() {
+    // Two vertices a millimetre apart are the same f32 at this distance from zero
+    let far = Vector3::new(3.0e7, -1.0e7, 2.0e7);
+    let body = RigidBody::new(far, Vector3::zero(), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::zero());
+    let data = crate::graphics::GraphicsInnerData {
+        push_constants: body.push_constants_at(body.orientation),
+        pos: body.pos + Vector3::new(0.001, 0.0, 0.0),
+    };
+    let model = data.push_constants_from(far).model;
+    assert!((model.w.x - 0.001).abs() < 1e-8);
+    assert_eq!((model.w.y, model.w.z), (0.0, 0.0));
+    assert_eq!(data.push_constants_from(Vector3::zero()).model.w.x, 3.0e7);
+}
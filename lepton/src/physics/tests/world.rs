@@ -0,0 +1,384 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::*;
+
+#[test]
+fn headless_world_steps_without_backend() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0))
+        .motivate(2.0, cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0)));
+    world.add_body(obj(1), RigidBody::by_pos(Vector3::new(100.0, 0.0, 0.0)));
+
+    world.push_task(PhysicsTask::AddGlobalImpulse(obj(0), Vector3::new(4.0, 0.0, 0.0)));
+    run(&mut world, 60);
+
+    let body = world.get_body(&obj(0)).unwrap();
+    assert!((body.vel.x - 2.0).abs() < 1e-12);
+    assert!((body.pos.x - 2.0).abs() < 1e-9);
+    assert!((world.time() - 1.0).abs() < 1e-9);
+
+    assert!(world.remove_body(&obj(1)).is_some());
+    assert!(world.get_body(&obj(1)).is_none());
+    assert_eq!(world.bodies().count(), 1);
+}
+
+fn spinning_pair() -> PhysicsWorld {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.5, 0.0),
+        Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::new(0.3, 0.2, 0.1))
+        .motivate(2.0, cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 3.0)));
+    world.add_body(obj(1), RigidBody::new(Vector3::new(5.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0),
+        Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0))
+        .motivate(1.0, cgmath::Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0)));
+    world
+}
+
+#[test]
+fn fixed_step_is_independent_of_frame_rate() {
+    let mut slow = spinning_pair();
+    let mut fast = spinning_pair();
+    let mut jittery = spinning_pair();
+
+    let mut slow_steps = 0;
+    let mut fast_steps = 0;
+    for _ in 0..60 {
+        slow_steps += slow.advance(2.0 * FIXED_DELTA_TIME);
+        fast_steps += fast.advance(FIXED_DELTA_TIME) + fast.advance(FIXED_DELTA_TIME);
+    }
+    assert_eq!(slow_steps, 120);
+    assert_eq!(fast_steps, 120);
+
+    let mut rng = Lcg(3);
+    let mut jittery_steps = 0;
+    for _ in 0..60 {
+        jittery_steps += jittery.advance(rng.next() * 3.0 * FIXED_DELTA_TIME);
+        assert!(jittery.interpolation_alpha() <= 1.0);
+    }
+
+    // Only the number of steps taken matters, not how the frames were sliced
+    let mut reference = spinning_pair();
+    for _ in 0..jittery_steps {
+        reference.step(FIXED_DELTA_TIME);
+    }
+    for object in [obj(0), obj(1)] {
+        let body = slow.get_body(&object).unwrap();
+        assert_eq!(body.pos, fast.get_body(&object).unwrap().pos);
+        assert_eq!(body.orientation, fast.get_body(&object).unwrap().orientation);
+
+        let body = jittery.get_body(&object).unwrap();
+        assert_eq!(body.pos, reference.get_body(&object).unwrap().pos);
+        assert_eq!(body.orientation, reference.get_body(&object).unwrap().orientation);
+    }
+}
+
+#[test]
+fn interpolated_pose_lies_between_steps() {
+    let mut world = spinning_pair();
+    assert_eq!(world.advance(1.5 * FIXED_DELTA_TIME), 1);
+    assert!((world.interpolation_alpha() - 0.5).abs() < 1e-9);
+
+    let body = world.get_body(&obj(1)).unwrap();
+    let (pos, _) = world.interpolated_pose(&obj(1)).unwrap();
+    let previous_x = body.pos.x + FIXED_DELTA_TIME;
+    assert!((pos.x - (previous_x + body.pos.x) / 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn stalled_frame_drops_the_steps_it_cannot_take() {
+    let mut world = spinning_pair();
+    assert_eq!(world.advance(1.0 + 0.5 * FIXED_DELTA_TIME), 8);
+    assert!((world.interpolation_alpha() - 0.5).abs() < 1e-6);
+    assert_eq!(world.advance(0.0), 0);
+
+    // The same rotation with the opposite sign is still blended the short way
+    let body = world.get_body_mut(&obj(1)).unwrap();
+    body.orientation = -body.orientation;
+    let orientation = body.orientation;
+    let (_, blended) = world.interpolated_pose(&obj(1)).unwrap();
+    assert!((blended.magnitude() - 1.0).abs() < 1e-9);
+    assert!(blended.dot(orientation).abs() > 0.99, "{:?}", blended);
+}
+
+#[test]
+fn box_sleeps_on_moving_deck() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), floor(0.0).line(Vector3::new(1.0, 0.0, 0.0), 1.0, f64::INFINITY));
+    world.add_body(obj(1), unit_cube(Vector3::new(0.0, 0.5, 0.0)).field(falling_field()));
+    run(&mut world, 180);
+    assert!(world.get_body(&obj(1)).unwrap().asleep);
+
+    // Asleep, the box is carried along with the deck rather than left behind
+    let offset = world.get_body(&obj(1)).unwrap().pos - world.get_body(&obj(0)).unwrap().pos;
+    run(&mut world, 120);
+    let deck = world.get_body(&obj(0)).unwrap();
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!(body.asleep);
+    assert!((body.pos - deck.pos - offset).magnitude() < 1e-9, "box at {:?}, deck at {:?}", body.pos, deck.pos);
+    assert!((body.vel - deck.vel).magnitude() < 1e-9, "{:?}", body.vel);
+}
+
+#[test]
+fn landing_reports_one_collision_event() {
+    let mut world = box_stack(1);
+    world.get_body_mut(&obj(1)).unwrap().pos.y = 1.5;
+    let mut events = Vec::new();
+    for _ in 0..180 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+        events.append(&mut world.take_collision_events());
+    }
+
+    // Resting on the floor afterwards reports nothing more
+    assert_eq!(events.len(), 1, "{:?}", events);
+    let event = events[0];
+    assert_eq!((event.a, event.b), (obj(0), obj(1)));
+    assert!((event.normal - Vector3::unit_y()).magnitude() < 1e-3, "{:?}", event.normal);
+    assert!(event.point.y.abs() < 0.05, "{:?}", event.point);
+    let expected = (2.0 * 9.8 * 1.0_f64).sqrt();
+    assert!((event.relative_speed - expected).abs() < 0.3, "{} but expected {}", event.relative_speed, expected);
+    assert!((event.impulse - expected).abs() < 0.5, "{} but expected {}", event.impulse, expected);
+}
+
+#[test]
+fn object_handles_are_reused_with_new_generations() {
+    let mut objects = ObjectManager::new();
+    let (a, b) = (objects.get_object(), objects.get_object());
+    assert_ne!(a, b);
+    assert!(objects.free_object(a));
+    assert!(!objects.free_object(a));
+    assert!(!objects.is_alive(a));
+
+    let c = objects.get_object();
+    assert_eq!(c.index(), a.index());
+    assert_ne!(c, a);
+    assert!(objects.is_alive(b) && objects.is_alive(c));
+    assert_eq!(objects.get_object().index(), 2);
+}
+
+#[test]
+fn bodies_spawn_and_despawn_at_runtime() {
+    let mut objects = ObjectManager::new();
+    let ground = objects.get_object();
+    let mut world = PhysicsWorld::new();
+    world.add_body(ground, floor(0.0));
+
+    // A spawned body feels the tasks queued after it in the same batch, and lands on the floor
+    let debris = objects.get_object();
+    world.push_task(PhysicsTask::Spawn(debris, Box::new(unit_cube(Vector3::new(0.0, 2.0, 0.0)))));
+    world.push_task(PhysicsTask::AddGlobalImpulse(debris, Vector3::new(1.0, 0.0, 0.0)));
+    world.step(DELTA_TIME);
+    assert!(world.get_body(&debris).unwrap().vel.x > 0.9);
+    for _ in 0..120 {
+        world.push_task(PhysicsTask::AddGlobalForce(debris, GRAVITY));
+        world.step(DELTA_TIME);
+    }
+    assert!(world.take_collision_events().iter().any(|event| (event.a, event.b) == (ground, debris)));
+
+    // Once freed, the slot goes to a new object which the old handle cannot touch
+    world.push_task(PhysicsTask::Despawn(debris));
+    world.step(DELTA_TIME);
+    assert!(world.get_body(&debris).is_none() && world.interpolated_pose(&debris).is_none());
+    objects.free_object(debris);
+    let missile = objects.get_object();
+    assert_eq!(missile.index(), debris.index());
+    world.push_task(PhysicsTask::Spawn(missile, Box::new(unit_box(Vector3::new(0.0, 2.0, 0.0)))));
+    world.push_task(PhysicsTask::Despawn(debris));
+    world.push_task(PhysicsTask::AddGlobalImpulse(debris, Vector3::new(1.0, 0.0, 0.0)));
+    world.step(DELTA_TIME);
+    assert_eq!(world.get_body(&missile).unwrap().vel, Vector3::zero());
+    assert_eq!(world.bodies().count(), 2);
+}
+
+#[test]
+fn set_tasks_override_the_body() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), unit_box(Vector3::zero()));
+    world.add_body(obj(1), RigidBody::by_pos(Vector3::new(5.0, 0.0, 0.0)));
+
+    // Setting the velocity drops the impulses queued before it, but not those after
+    world.push_task(PhysicsTask::AddGlobalImpulse(obj(0), Vector3::new(3.0, 0.0, 0.0)));
+    world.push_task(PhysicsTask::AddGlobalImpulseTorque(obj(0), Vector3::new(0.0, 1.0, 0.0)));
+    world.push_task(PhysicsTask::SetVel(obj(0), Vector3::new(0.0, 2.0, 0.0)));
+    world.push_task(PhysicsTask::SetAngVel(obj(0), Vector3::zero()));
+    world.push_task(PhysicsTask::AddGlobalImpulse(obj(0), Vector3::new(0.0, 0.0, 1.0)));
+    world.push_task(PhysicsTask::SetOrientation(obj(0), Quaternion::new(2.0, 0.0, 0.0, 0.0)));
+    world.step(DELTA_TIME);
+    let body = world.get_body(&obj(0)).unwrap();
+    assert_eq!((body.vel, body.ang_vel), (Vector3::new(0.0, 2.0, 1.0), Vector3::zero()));
+    assert_eq!(body.orientation, Quaternion::new(1.0, 0.0, 0.0, 0.0));
+
+    // A teleported body is drawn where it lands, not blended from where it was
+    world.push_task(PhysicsTask::SetPos(obj(0), Vector3::new(100.0, 0.0, 0.0)));
+    world.advance(1.5 * FIXED_DELTA_TIME);
+    assert_eq!(world.interpolated_pose(&obj(0)).unwrap().0, world.get_body(&obj(0)).unwrap().pos);
+
+    // A fixed body is set free with new mass properties
+    world.push_task(PhysicsTask::SetUpdater(obj(1), Updater::Free));
+    world.push_task(PhysicsTask::SetMassProperties(obj(1), 4.0, cgmath::Matrix3::from_diagonal(Vector3::new(1.0, 2.0, 4.0))));
+    world.push_task(PhysicsTask::AddGlobalImpulse(obj(1), Vector3::new(2.0, 0.0, 0.0)));
+    world.push_task(PhysicsTask::AddGlobalImpulseTorque(obj(1), Vector3::new(0.0, 0.0, 2.0)));
+    world.step(DELTA_TIME);
+    let body = world.get_body(&obj(1)).unwrap();
+    assert_eq!(body.vel, Vector3::new(0.5, 0.0, 0.0));
+    assert!((body.ang_vel - Vector3::new(0.0, 0.0, 0.5)).magnitude() < 1e-12);
+
+    // Mass properties which can't be inverted are ignored rather than taken
+    world.push_task(PhysicsTask::SetMassProperties(obj(1), 4.0, cgmath::Matrix3::zero()));
+    world.push_task(PhysicsTask::SetMassProperties(obj(1), 0.0, cgmath::Matrix3::from_diagonal(Vector3::new(1.0, 1.0, 1.0))));
+    world.step(DELTA_TIME);
+    let body = world.get_body(&obj(1)).unwrap();
+    assert_eq!((body.mass, body.local_moi), (4.0, cgmath::Matrix3::from_diagonal(Vector3::new(1.0, 2.0, 4.0))));
+}
+
+#[test]
+fn body_state_is_answered_after_the_step() {
+    let mut world = box_stack(1);
+    for _ in 0..60 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+    }
+    assert!(world.take_body_states().is_empty());
+
+    // Swapping the box for a ball forgets the box's contacts, and the ball settles instead
+    world.push_task(PhysicsTask::SetColliders(obj(1), vec![Collider::sphere(0.25)]));
+    world.push_task(PhysicsTask::RequestState(obj(1)));
+    world.push_task(PhysicsTask::RequestState(obj(7)));
+    world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+    world.step(DELTA_TIME);
+    let states = world.take_body_states();
+    let body = world.get_body(&obj(1)).unwrap();
+    assert_eq!(states, vec![(obj(1), Some(body.state(world.time()))), (obj(7), None)]);
+    assert!((states[0].1.unwrap().time - 61.0 * DELTA_TIME).abs() < 1e-12);
+    for _ in 0..60 {
+        world.push_task(PhysicsTask::AddGlobalForce(obj(1), GRAVITY));
+        world.step(DELTA_TIME);
+    }
+    assert!((world.get_body(&obj(1)).unwrap().pos.y - 0.25).abs() < 0.02);
+}
+
+#[test]
+fn bodies_pass_through_sensors_and_are_reported() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::zero())
+        .collide(vec![Collider::cube(1.0)], 0.5)
+        .sensor());
+    world.add_body(obj(1), unit_box(Vector3::new(-3.0, 0.0, 0.0))
+        .collide(vec![Collider::sphere(0.25)], 0.5));
+    world.get_body_mut(&obj(1)).unwrap().vel = Vector3::new(6.0, 0.0, 0.0);
+
+    let mut events = Vec::new();
+    let mut steps_inside = 0;
+    for _ in 0..60 {
+        world.step(DELTA_TIME);
+        events.append(&mut world.take_sensor_events());
+        if world.bodies_in_sensor(obj(0)) == vec![obj(1)] {
+            steps_inside += 1;
+        }
+    }
+    let part = ColliderPart { collider: 0, child: None };
+    assert_eq!(events, vec![
+        SensorEvent { sensor: obj(0), part, body: obj(1), kind: SensorEventKind::Enter },
+        SensorEvent { sensor: obj(0), part, body: obj(1), kind: SensorEventKind::Exit },
+    ]);
+    // Inside while the ball's center is within 1.25 of the sensor's, at 0.1 per step
+    assert!((24..=26).contains(&steps_inside), "{}", steps_inside);
+    assert_eq!(world.get_body(&obj(1)).unwrap().vel, Vector3::new(6.0, 0.0, 0.0));
+    assert!(world.raycast(Vector3::new(-5.0, 0.0, 0.0), Vector3::unit_x(), 10.0, &[obj(1)]).is_none());
+
+    // Removing the sensor lets go of whatever was inside
+    world.get_body_mut(&obj(1)).unwrap().pos = Vector3::zero();
+    world.step(DELTA_TIME);
+    world.take_sensor_events();
+    world.push_task(PhysicsTask::Despawn(obj(0)));
+    world.step(DELTA_TIME);
+    assert_eq!(world.take_sensor_events(), vec![
+        SensorEvent { sensor: obj(0), part, body: obj(1), kind: SensorEventKind::Exit },
+    ]);
+}
+
+#[test]
+fn resting_stack_falls_asleep_and_wakes_together() {
+    let mut world = settling_stack(3);
+    run(&mut world, 180);
+    assert_eq!(world.islands(), vec![vec![obj(1), obj(2), obj(3)]]);
+    assert!((1..4).all(|i| world.get_body(&obj(i)).unwrap().asleep));
+    let poses = (1..4).map(|i| world.get_body(&obj(i)).unwrap().pos).collect::<Vec<_>>();
+    run(&mut world, 60);
+    assert_eq!((1..4).map(|i| world.get_body(&obj(i)).unwrap().pos).collect::<Vec<_>>(), poses);
+
+    // Pushing the top box wakes the whole island
+    world.push_task(PhysicsTask::AddGlobalImpulse(obj(3), Vector3::new(0.5, 0.0, 0.0)));
+    world.step(DELTA_TIME);
+    assert!((1..4).all(|i| !world.get_body(&obj(i)).unwrap().asleep));
+    run(&mut world, 180);
+    assert!((1..4).all(|i| world.get_body(&obj(i)).unwrap().asleep));
+}
+
+#[test]
+fn sleeping_bodies_wake_when_their_support_goes() {
+    let mut world = settling_stack(2);
+    run(&mut world, 180);
+    assert!(world.get_body(&obj(2)).unwrap().asleep);
+    world.push_task(PhysicsTask::Despawn(obj(1)));
+    run(&mut world, 60);
+    let body = world.get_body(&obj(2)).unwrap();
+    assert!((body.pos.y - 0.5).abs() < 0.03, "{:?}", body.pos);
+
+    // A body dropped onto a sleeping one wakes it
+    world.add_body(obj(3), unit_cube(Vector3::new(0.0, 3.0, 0.0)));
+    world.get_body_mut(&obj(3)).unwrap().field = Some(falling_field());
+    let mut woke = false;
+    for _ in 0..60 {
+        world.step(DELTA_TIME);
+        woke |= !world.get_body(&obj(2)).unwrap().asleep;
+    }
+    assert!(woke);
+    assert!((world.get_body(&obj(3)).unwrap().pos.y - 1.5).abs() < 0.05);
+}
+
+#[test]
+fn weak_gravity_wakes_a_body_and_keeps_it_falling() {
+    let mut world = PhysicsWorld::new();
+    world.add_body(obj(0), RigidBody::by_pos(Vector3::zero()).gravitate(100.0));
+    world.add_body(obj(1), unit_box(Vector3::new(30.0, 0.0, 0.0)).attracted());
+    run(&mut world, 60);
+    assert!(world.get_body(&obj(1)).unwrap().asleep);
+
+    // Once gravity is on, the body is slow enough to sleep for a while but nothing holds it up
+    world.set_gravity(Some(Gravity::new(1.0)));
+    run(&mut world, 180);
+    let body = world.get_body(&obj(1)).unwrap();
+    assert!(!body.asleep);
+    assert!(body.pos.x < 29.6, "{:?}", body.pos);
+}
+
+#[test]
+fn sleeping_scene_does_no_work() {
+    let mut world = box_stack(3);
+    let field_calls = Arc::new(AtomicUsize::new(0));
+    for i in 1..4 {
+        let field_calls = field_calls.clone();
+        world.get_body_mut(&obj(i)).unwrap().field = Some(Box::new(move |_| {
+            field_calls.fetch_add(1, Ordering::Relaxed);
+            GRAVITY
+        }));
+    }
+    world.add_body(obj(4), RigidBody::by_pos(Vector3::new(0.0, 1.5, 0.0))
+        .collide(vec![Collider::cube(2.0)], 0.5)
+        .sensor());
+    run(&mut world, 180);
+    assert!((1..4).all(|i| world.get_body(&obj(i)).unwrap().asleep));
+    world.take_sensor_events();
+
+    // Asleep, the stack is neither pulled nor tested against anything, and stays in the sensor
+    field_calls.store(0, Ordering::Relaxed);
+    let interactions = std::cell::Cell::new(0);
+    for _ in 0..60 {
+        world.step_with(DELTA_TIME, |_, _, _| interactions.set(interactions.get() + 1));
+    }
+    assert_eq!(field_calls.load(Ordering::Relaxed), 0);
+    assert_eq!(interactions.get(), 0);
+    assert!(world.take_sensor_events().is_empty());
+    assert_eq!(world.bodies_in_sensor(obj(4)), vec![obj(0), obj(1), obj(2), obj(3)]);
+}
@@ -0,0 +1,903 @@
+use anyhow::Result;
+use rustc_hash::{FxHashMap, FxHashSet};
+use cgmath::{Vector3, Quaternion, InnerSpace, Zero};
+
+use super::{Object, RigidBody, Updater, PhysicsTask, Joint, JointId, CollisionEvent, SensorEvent, SensorEventKind, BodyState, ColliderPart, Gravity};
+use super::broadphase::SweepAndPrune;
+use super::aerodynamics::air_impulses;
+use super::manifold::ContactManifold;
+use super::snapshot::{PhysicsSnapshot, BodySnapshot, TaskSnapshot, PoseSnapshot, Resolver};
+
+/// Default length of one fixed physics step
+pub const FIXED_DELTA_TIME: f64 = 1.0 / 60.0;
+/// The most fixed steps taken in one call to advance. A part step left over is carried to the next
+/// call, but once this many steps have been taken any whole steps still owed are dropped, so that
+/// a long stall or a machine which can't keep up doesn't leave the world forever behind.
+const MAX_SUBSTEPS: usize = 8;
+const STEP_TOLERANCE: f64 = 1e-9;
+/// Passes the solver makes over every joint and contact manifold each step
+const SOLVER_ITERATIONS: usize = 10;
+/// Free bodies slower than these fall asleep once their whole island has been slow for SLEEP_TIME
+const SLEEP_LINEAR_SPEED: f64 = 0.1;
+const SLEEP_ANGULAR_SPEED: f64 = 0.1;
+const SLEEP_TIME: f64 = 0.5;
+
+/// Two objects and a piece of each which touch. The lower object comes first.
+pub(crate) type ColliderPair = (Object, Object, ColliderPart, ColliderPart);
+
+/// A physics simulation that is independent of the backend and of graphics. It can be stepped
+/// directly, for example by tests, a server or an offline trajectory tool. The threaded physics
+/// engine run by the backend wraps one of these.
+pub struct PhysicsWorld {
+    rigid_bodies: FxHashMap<Object, RigidBody>,
+    broadphase: SweepAndPrune,
+    tasks: Vec<PhysicsTask>,
+    time: f64,
+    fixed_delta_time: f64,
+    accumulator: f64,
+    previous_poses: FxHashMap<Object, (Vector3<f64>, Quaternion<f64>)>,
+    manifolds: FxHashMap<ColliderPair, ContactManifold>,
+    joints: FxHashMap<JointId, Joint>,
+    /// Pairs which never collide, with the lower object first
+    ignored_pairs: FxHashSet<(Object, Object)>,
+    broken_joints: Vec<JointId>,
+    collision_events: Vec<CollisionEvent>,
+    /// Each body inside a piece of a sensor at the end of the last step
+    sensor_contents: FxHashSet<(Object, ColliderPart, Object)>,
+    sensor_events: Vec<SensorEvent>,
+    /// Bodies added, changed by hand or given a task since the sensors were last checked, which
+    /// are checked again even if they can't move
+    disturbed: FxHashSet<Object>,
+    state_requests: Vec<Object>,
+    body_states: Vec<(Object, Option<BodyState>)>,
+    gravity: Option<Gravity>,
+    sleeping: bool,
+}
+
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        Self {
+            rigid_bodies: FxHashMap::default(),
+            broadphase: SweepAndPrune::new(),
+            tasks: Vec::new(),
+            time: 0.0,
+            fixed_delta_time: FIXED_DELTA_TIME,
+            accumulator: 0.0,
+            previous_poses: FxHashMap::default(),
+            manifolds: FxHashMap::default(),
+            joints: FxHashMap::default(),
+            ignored_pairs: FxHashSet::default(),
+            broken_joints: Vec::new(),
+            collision_events: Vec::new(),
+            sensor_contents: FxHashSet::default(),
+            sensor_events: Vec::new(),
+            disturbed: FxHashSet::default(),
+            state_requests: Vec::new(),
+            body_states: Vec::new(),
+            gravity: None,
+            sleeping: true,
+        }
+    }
+
+    /// Use a different length for the fixed steps taken by advance
+    pub fn with_fixed_delta_time(mut self, fixed_delta_time: f64) -> Self {
+        self.fixed_delta_time = fixed_delta_time;
+        self
+    }
+
+    /// Pull the bodies together with gravity
+    pub fn with_gravity(mut self, gravity: Gravity) -> Self {
+        self.gravity = Some(gravity);
+        self
+    }
+
+    /// Let still bodies fall asleep, which is on by default
+    pub fn with_sleeping(mut self, sleeping: bool) -> Self {
+        self.set_sleeping(sleeping);
+        self
+    }
+
+    /// Turning sleeping off wakes every body
+    pub fn set_sleeping(&mut self, sleeping: bool) {
+        self.sleeping = sleeping;
+        if !sleeping {
+            for body in self.rigid_bodies.values_mut() {
+                body.wake();
+            }
+        }
+    }
+
+    /// Changing gravity wakes every body, since sleeping bodies don't feel it
+    pub fn set_gravity(&mut self, gravity: Option<Gravity>) {
+        self.gravity = gravity;
+        for body in self.rigid_bodies.values_mut() {
+            body.wake();
+        }
+    }
+
+    pub fn gravity(&self) -> Option<&Gravity> {
+        self.gravity.as_ref()
+    }
+
+    /// The acceleration gravity would give a particle at point
+    pub fn gravity_at(&self, point: Vector3<f64>) -> Vector3<f64> {
+        match &self.gravity {
+            Some(gravity) => gravity.field_at(&self.rigid_bodies, point),
+            None => Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Save everything needed to carry on the simulation from this point, including the queued
+    /// tasks. Events and state answers not yet taken are left out.
+    pub fn snapshot(&self) -> PhysicsSnapshot {
+        let mut bodies = self.rigid_bodies.iter()
+            .map(|(object, body)| (*object, BodySnapshot::new(body)))
+            .collect::<Vec<_>>();
+        bodies.sort_unstable_by_key(|(object, _)| *object);
+        let mut joints = self.joints.iter().map(|(id, joint)| (*id, joint.clone())).collect::<Vec<_>>();
+        joints.sort_unstable_by_key(|(id, _)| *id);
+        let mut manifolds = self.manifolds.iter().map(|(key, manifold)| (*key, manifold.clone())).collect::<Vec<_>>();
+        manifolds.sort_unstable_by_key(|(key, _)| *key);
+        let mut ignored_pairs = self.ignored_pairs.iter().copied().collect::<Vec<_>>();
+        ignored_pairs.sort_unstable();
+        let mut sensor_contents = self.sensor_contents.iter().copied().collect::<Vec<_>>();
+        sensor_contents.sort_unstable();
+        let mut previous_poses = self.previous_poses.iter()
+            .map(|(object, (pos, orientation))| (*object, PoseSnapshot { pos: *pos, orientation: *orientation }))
+            .collect::<Vec<_>>();
+        previous_poses.sort_unstable_by_key(|(object, _)| *object);
+
+        PhysicsSnapshot {
+            bodies,
+            joints,
+            manifolds,
+            ignored_pairs,
+            sensor_contents,
+            tasks: self.tasks.iter().map(TaskSnapshot::new).collect(),
+            previous_poses,
+            time: self.time,
+            fixed_delta_time: self.fixed_delta_time,
+            accumulator: self.accumulator,
+            gravity: self.gravity,
+            sleeping: self.sleeping,
+        }
+    }
+
+    /// Rebuild a world from a snapshot. Fields and the functions of radial colliders can't be
+    /// saved, so the resolver is asked for them, and it is an error if it has none.
+    pub fn from_snapshot<R: Resolver>(snapshot: PhysicsSnapshot, resolver: &mut R) -> Result<Self> {
+        let mut world = Self::new();
+        for (object, body) in snapshot.bodies {
+            world.rigid_bodies.insert(object, body.restore(object, resolver)?);
+        }
+        world.tasks = snapshot.tasks.into_iter()
+            .map(|task| task.restore(resolver))
+            .collect::<Result<Vec<_>>>()?;
+        world.joints = snapshot.joints.into_iter().collect();
+        world.manifolds = snapshot.manifolds.into_iter().collect();
+        world.ignored_pairs = snapshot.ignored_pairs.into_iter().collect();
+        world.sensor_contents = snapshot.sensor_contents.into_iter().collect();
+        world.previous_poses = snapshot.previous_poses.into_iter()
+            .map(|(object, pose)| (object, (pose.pos, pose.orientation)))
+            .collect();
+        world.time = snapshot.time;
+        world.fixed_delta_time = snapshot.fixed_delta_time;
+        world.accumulator = snapshot.accumulator;
+        world.gravity = snapshot.gravity;
+        world.sleeping = snapshot.sleeping;
+        Ok(world)
+    }
+
+    /// Add a body to the world. If the object already had a body, the old body is returned.
+    pub fn add_body(&mut self, object: Object, body: RigidBody) -> Option<RigidBody> {
+        self.disturbed.insert(object);
+        self.rigid_bodies.insert(object, body)
+    }
+
+    /// Remove a body from the world and return it. Joints to the body are destroyed, and its
+    /// ignored pairs forgotten. The bodies it touched or was joined to are woken.
+    pub fn remove_body(&mut self, object: &Object) -> Option<RigidBody> {
+        let neighbours = self.manifolds.keys().map(|(o_i, o_j, _, _)| (*o_i, *o_j))
+            .chain(self.joints.values().map(|joint| (joint.a, joint.b)))
+            .filter_map(|(o_i, o_j)| if o_i == *object { Some(o_j) } else if o_j == *object { Some(o_i) } else { None })
+            .collect::<Vec<_>>();
+        self.wake_all(&neighbours);
+        self.manifolds.retain(|(o_i, o_j, _, _), _| o_i != object && o_j != object);
+        self.joints.retain(|_, joint| joint.a != *object && joint.b != *object);
+        self.ignored_pairs.retain(|(o_i, o_j)| o_i != object && o_j != object);
+        self.previous_poses.remove(object);
+        self.rigid_bodies.remove(object)
+    }
+
+    pub fn get_body(&self, object: &Object) -> Option<&RigidBody> {
+        self.rigid_bodies.get(object)
+    }
+
+    /// Borrow a body to change it by hand, which wakes it
+    pub fn get_body_mut(&mut self, object: &Object) -> Option<&mut RigidBody> {
+        let body = self.rigid_bodies.get_mut(object)?;
+        body.wake();
+        self.disturbed.insert(*object);
+        Some(body)
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (&Object, &RigidBody)> {
+        self.rigid_bodies.iter()
+    }
+
+    /// Join two bodies, holding them as they are now. The joint is not made if either body is
+    /// missing or both are the same. If the id was already used, the old joint is returned.
+    pub fn add_joint(&mut self, id: JointId, mut joint: Joint) -> Option<Joint> {
+        if joint.a == joint.b {
+            return None;
+        }
+        let (a, b) = (self.rigid_bodies.get(&joint.a)?, self.rigid_bodies.get(&joint.b)?);
+        joint.attach(a, b);
+        self.joints.insert(id, joint)
+    }
+
+    pub fn remove_joint(&mut self, id: &JointId) -> Option<Joint> {
+        self.joints.remove(id)
+    }
+
+    pub fn get_joint(&self, id: &JointId) -> Option<&Joint> {
+        self.joints.get(id)
+    }
+
+    pub fn joints(&self) -> impl Iterator<Item = (&JointId, &Joint)> {
+        self.joints.iter()
+    }
+
+    /// The free bodies grouped by what they touch or are joined to. Fixed and kinematic bodies
+    /// don't join islands together, so boxes resting on the same floor are apart. Each island is
+    /// sorted, and the islands are in order of their lowest object.
+    pub fn islands(&self) -> Vec<Vec<Object>> {
+        let mut objects = self.rigid_bodies.iter()
+            .filter(|(_, body)| matches!(body.updater, Updater::Free))
+            .map(|(object, _)| *object)
+            .collect::<Vec<_>>();
+        objects.sort_unstable();
+        self.islands_from(&objects)
+    }
+
+    /// The islands holding any of the given free bodies, found by walking out from each in turn
+    fn islands_from(&self, seeds: &[Object]) -> Vec<Vec<Object>> {
+        let mut links: FxHashMap<Object, Vec<Object>> = FxHashMap::default();
+        let free = |object: &Object| self.rigid_bodies.get(object).is_some_and(|body| matches!(body.updater, Updater::Free));
+        let pairs = self.manifolds.keys().map(|(o_i, o_j, _, _)| (*o_i, *o_j))
+            .chain(self.joints.values().map(|joint| (joint.a, joint.b)));
+        for (a, b) in pairs {
+            if free(&a) && free(&b) {
+                links.entry(a).or_default().push(b);
+                links.entry(b).or_default().push(a);
+            }
+        }
+        let mut visited = FxHashSet::default();
+        let mut islands = Vec::new();
+        for seed in seeds {
+            if !visited.insert(*seed) {
+                continue;
+            }
+            let mut island = vec![*seed];
+            let mut i = 0;
+            while i < island.len() {
+                for next in links.get(&island[i]).into_iter().flatten() {
+                    if visited.insert(*next) {
+                        island.push(*next);
+                    }
+                }
+                i += 1;
+            }
+            island.sort_unstable();
+            islands.push(island);
+        }
+        islands
+    }
+
+    /// Stop two bodies colliding with each other, whatever their collision filters
+    pub fn ignore_collisions(&mut self, a: Object, b: Object) {
+        self.ignored_pairs.insert((a.min(b), a.max(b)));
+    }
+
+    pub fn restore_collisions(&mut self, a: Object, b: Object) {
+        self.ignored_pairs.remove(&(a.min(b), a.max(b)));
+    }
+
+    /// Whether two bodies have been told to ignore each other
+    pub fn ignores_collisions(&self, a: Object, b: Object) -> bool {
+        self.ignored_pairs.contains(&(a.min(b), a.max(b)))
+    }
+
+    /// The joints which have broken since this was last called
+    pub fn take_broken_joints(&mut self) -> Vec<JointId> {
+        std::mem::take(&mut self.broken_joints)
+    }
+
+    /// The collisions since this was last called, in the order they happened
+    pub fn take_collision_events(&mut self) -> Vec<CollisionEvent> {
+        std::mem::take(&mut self.collision_events)
+    }
+
+    /// Bodies entering and leaving sensors since this was last called, in the order they happened
+    pub fn take_sensor_events(&mut self) -> Vec<SensorEvent> {
+        std::mem::take(&mut self.sensor_events)
+    }
+
+    /// The bodies inside a sensor at the end of the last step, in increasing order
+    pub fn bodies_in_sensor(&self, sensor: Object) -> Vec<Object> {
+        let mut bodies = self.sensor_contents.iter()
+            .filter(|(s, _, _)| *s == sensor)
+            .map(|(_, _, body)| *body)
+            .collect::<Vec<_>>();
+        bodies.sort_unstable();
+        bodies.dedup();
+        bodies
+    }
+
+    /// The answers to state requests since this was last called, in the order they were asked
+    pub fn take_body_states(&mut self) -> Vec<(Object, Option<BodyState>)> {
+        std::mem::take(&mut self.body_states)
+    }
+
+    /// Queue a task. Tasks are applied during the next step.
+    pub fn push_task(&mut self, task: PhysicsTask) {
+        self.tasks.push(task);
+    }
+
+    pub fn push_tasks(&mut self, mut tasks: Vec<PhysicsTask>) {
+        self.tasks.append(&mut tasks);
+    }
+
+    /// Total simulated time
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn fixed_delta_time(&self) -> f64 {
+        self.fixed_delta_time
+    }
+
+    /// Advance the simulation by frame_time in whole fixed steps with no two-body interactions.
+    /// Returns the number of steps taken.
+    pub fn advance(&mut self, frame_time: f64) -> usize {
+        self.advance_with(frame_time, |_, _, _| {})
+    }
+
+    /// Advance the simulation by frame_time in whole fixed steps. The time left over is kept for
+    /// the next call and used to interpolate poses between the last two steps. Queued tasks are
+    /// applied during the first step. Returns the number of steps taken.
+    pub fn advance_with<F>(&mut self, frame_time: f64, interaction: F) -> usize
+    where F: Fn(&mut Vec<PhysicsTask>, (&Object, &RigidBody), (&Object, &RigidBody)) {
+        self.accumulator += frame_time;
+        let mut substeps = 0;
+        // Allow for rounding so that, say, two half frames still make a whole step
+        while self.accumulator >= self.fixed_delta_time * (1.0 - STEP_TOLERANCE) && substeps < MAX_SUBSTEPS {
+            // A resting body is drawn where it is
+            self.previous_poses.clear();
+            for (object, body) in &self.rigid_bodies {
+                if !body.asleep || body.carrier.is_some() {
+                    self.previous_poses.insert(*object, (body.pos, body.orientation));
+                }
+            }
+            self.step_with(self.fixed_delta_time, &interaction);
+            self.accumulator -= self.fixed_delta_time;
+            substeps += 1;
+        }
+        if substeps == MAX_SUBSTEPS {
+            self.accumulator %= self.fixed_delta_time;
+        }
+        substeps
+    }
+
+    /// How far the unsimulated time reaches into the next fixed step, from zero to one
+    pub fn interpolation_alpha(&self) -> f64 {
+        (self.accumulator / self.fixed_delta_time).clamp(0.0, 1.0)
+    }
+
+    /// The position and orientation of a body blended between the last two fixed steps by the
+    /// interpolation alpha. Meant for rendering; the simulation itself never uses it.
+    pub fn interpolated_pose(&self, object: &Object) -> Option<(Vector3<f64>, Quaternion<f64>)> {
+        let body = self.rigid_bodies.get(object)?;
+        Some(match self.previous_poses.get(object) {
+            Some((pos, orientation)) => {
+                let alpha = self.interpolation_alpha();
+                // q and -q are the same rotation; take the one nearer so the blend goes the short way
+                let target = if orientation.dot(body.orientation) < 0.0 { -body.orientation } else { body.orientation };
+                (pos + (body.pos - pos) * alpha, orientation.nlerp(target, alpha))
+            },
+            None => (body.pos, body.orientation),
+        })
+    }
+
+    /// Advance the simulation by delta_time with no two-body interactions
+    pub fn step(&mut self, delta_time: f64) {
+        self.step_with(delta_time, |_, _, _| {});
+    }
+
+    /// Advance the simulation by delta_time. The interaction is called once for every pair of
+    /// bodies near enough to collide this step, unless both are fixed or asleep, in order of
+    /// object. It is meant for forces between bodies which touch or nearly do, such as magnets or
+    /// docking clamps; gravity reaches further. The tasks it pushes are applied in this same step.
+    pub fn step_with<F>(&mut self, delta_time: f64, interaction: F)
+    where F: Fn(&mut Vec<PhysicsTask>, (&Object, &RigidBody), (&Object, &RigidBody)) {
+        // Apply the tasks first, so that bodies spawned or moved by them are tested this step
+        for task in std::mem::take(&mut self.tasks) {
+            self.apply_task(task, delta_time);
+        }
+
+        // Find the pairs which may collide, except joined or ignored bodies. Bodies which can't
+        // move aren't tested against each other.
+        let mut excluded = self.joints.values()
+            .filter(|joint| !joint.collide_connected)
+            .map(|joint| (joint.a.min(joint.b), joint.a.max(joint.b)))
+            .collect::<FxHashSet<_>>();
+        excluded.extend(self.ignored_pairs.iter().copied());
+        let candidates = self.broadphase.candidate_pairs(&self.rigid_bodies, delta_time, &excluded);
+
+        // The candidates come sorted, so that the forces are summed the same way whatever the hash
+        // map's layout, as it is after restoring a snapshot
+        let mut interaction_forces = Vec::new();
+        for (o_i, o_j) in &candidates {
+            let (rb_i, rb_j) = (&self.rigid_bodies[o_i], &self.rigid_bodies[o_j]);
+            if rb_i.is_active() || rb_j.is_active() {
+                interaction(&mut interaction_forces, (o_i, rb_i), (o_j, rb_j));
+            }
+        }
+        for task in interaction_forces {
+            self.apply_task(task, delta_time);
+        }
+
+        // Add forces before the contacts, so that contacts can hold bodies against them. Gravity
+        // comes after the tasks so that it pulls on bodies spawned this step. Sleeping bodies are
+        // left out; they were held against these forces when they fell asleep, and are woken if
+        // that changes.
+        let mut pushed = FxHashSet::default();
+        if let Some(gravity) = &self.gravity {
+            for (object, acceleration) in gravity.accelerations(&self.rigid_bodies) {
+                let rb = self.rigid_bodies.get_mut(&object).unwrap();
+                rb.impulse += acceleration * (rb.mass * delta_time);
+                if acceleration != Vector3::zero() {
+                    pushed.insert(object);
+                }
+            }
+        }
+        // The air pushes against the velocity the other forces give, so thick air holds a body
+        // at its terminal velocity
+        for (object, impulse) in air_impulses(&self.rigid_bodies, delta_time) {
+            self.rigid_bodies.get_mut(&object).unwrap().impulse += impulse;
+            if impulse != Vector3::zero() {
+                pushed.insert(object);
+            }
+        }
+
+        // Sleeping bodies which ride on a kinematic body are moved with it after the step
+        let carrier_poses = self.rigid_bodies.values()
+            .filter_map(|body| body.carrier)
+            .filter_map(|carrier| self.rigid_bodies.get(&carrier).map(|rb| (carrier, (rb.pos, rb.orientation))))
+            .collect::<FxHashMap<_, _>>();
+
+        // With every body asleep or fixed nothing can touch, and the contacts are left as they are
+        if self.rigid_bodies.values().any(|body| body.is_active()) {
+            self.collide(candidates, delta_time);
+        }
+
+        // Update body position
+        for body in self.rigid_bodies.values_mut() {
+            body.update(delta_time);
+        }
+        self.carry_sleepers(&carrier_poses);
+        self.time += delta_time;
+        self.update_sensors(&excluded);
+        if self.sleeping {
+            self.update_sleep(delta_time, &pushed);
+        }
+
+        // Answer the state requests
+        for object in std::mem::take(&mut self.state_requests) {
+            let state = self.rigid_bodies.get(&object).map(|body| body.state(self.time));
+            self.body_states.push((object, state));
+        }
+    }
+
+    /// Find the contacts between the candidate pairs, solve them with the joints, and report the
+    /// collisions
+    fn collide(&mut self, candidates: Vec<(Object, Object)>, delta_time: f64) {
+        // Sleeping bodies keep their contacts, so that their islands hold together
+        let resting_keys = self.manifolds.keys()
+            .filter(|key| !self.is_active_pair(key.0, key.1))
+            .copied()
+            .collect::<FxHashSet<_>>();
+
+        // Detect collisions. Sensors never collide.
+        let collisions = candidates.into_iter()
+            .filter_map(|(o_i, o_j)| {
+                let t = match (self.rigid_bodies.get(&o_i), self.rigid_bodies.get(&o_j)) {
+                    (Some(rb_i), Some(rb_j)) if !rb_i.sensor && !rb_j.sensor && self.is_active_pair(o_i, o_j) => {
+                        rb_i.detect_collision(rb_j, delta_time)
+                    },
+                    _ => None,
+                };
+                t.map(|t| (o_i, o_j, t))
+            })
+            .collect::<Vec<_>>();
+
+        // Move colliding bodies up to the moment of collision and gather their contacts. A body
+        // struck while asleep wakes.
+        let mut touching = FxHashSet::default();
+        for (o_i, o_j, t) in collisions {
+            let manifolds = &mut self.manifolds;
+            with_pair(&mut self.rigid_bodies, o_i, o_j, |rb_i, rb_j| {
+                for rb in [&mut *rb_i, &mut *rb_j] {
+                    if rb.asleep {
+                        rb.wake();
+                    }
+                }
+                rb_i.advance_to(t);
+                rb_j.advance_to(t);
+                for (c_i, c_j, contact) in rb_i.contacts(rb_j) {
+                    let key = (o_i, o_j, c_i, c_j);
+                    let manifold = manifolds.entry(key).or_insert_with(|| ContactManifold::new(contact.normal));
+                    if touching.insert(key) {
+                        manifold.refresh(rb_i, rb_j);
+                    }
+                    manifold.add(&contact, rb_i, rb_j);
+                }
+            });
+        }
+        self.manifolds.retain(|key, _| touching.contains(key) || resting_keys.contains(key));
+
+        // Solve the joints and contacts, then break the joints which couldn't hold
+        self.solve_constraints(delta_time);
+        let mut broken = self.joints.iter()
+            .filter(|(_, joint)| joint.is_broken())
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        broken.sort_unstable();
+        for id in &broken {
+            self.joints.remove(id);
+        }
+        self.broken_joints.append(&mut broken);
+
+        // Report the collisions
+        let mut keys = self.manifolds.keys()
+            .filter(|key| self.is_active_pair(key.0, key.1))
+            .copied()
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        for key in keys {
+            if let Some(event) = self.manifolds.get_mut(&key).unwrap().event(key) {
+                self.collision_events.push(event);
+            }
+        }
+    }
+
+    fn apply_task(&mut self, task: PhysicsTask, delta_time: f64) {
+        // Anything done to a body wakes it, and changing a joint or rule wakes the pair
+        let woken = match &task {
+            PhysicsTask::AddGlobalForce(object, _) | PhysicsTask::AddGlobalImpulse(object, _)
+            | PhysicsTask::AddLocalForce(object, _) | PhysicsTask::AddLocalImpulse(object, _)
+            | PhysicsTask::AddLocalImpulseTorque(object, _) | PhysicsTask::AddGlobalImpulseTorque(object, _)
+            | PhysicsTask::ShiftPos(object, _) | PhysicsTask::SetPos(object, _) | PhysicsTask::SetVel(object, _)
+            | PhysicsTask::SetOrientation(object, _) | PhysicsTask::SetAngVel(object, _)
+            | PhysicsTask::SetMassProperties(object, _, _) | PhysicsTask::SetUpdater(object, _)
+            | PhysicsTask::SetColliders(object, _) | PhysicsTask::SetCollisionFilter(object, _) => vec![*object],
+            PhysicsTask::CreateJoint(_, joint) => vec![joint.a, joint.b],
+            PhysicsTask::DestroyJoint(id) => self.joints.get(id).map_or(Vec::new(), |joint| vec![joint.a, joint.b]),
+            PhysicsTask::IgnoreCollisions(a, b) | PhysicsTask::RestoreCollisions(a, b) => vec![*a, *b],
+            PhysicsTask::Spawn(..) | PhysicsTask::Despawn(_) | PhysicsTask::RequestState(_) => Vec::new(),
+        };
+        self.wake_all(&woken);
+        self.disturbed.extend(woken);
+
+        match task {
+            PhysicsTask::AddGlobalForce(object, force) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.impulse += force * delta_time;
+                }
+            },
+            PhysicsTask::AddGlobalImpulse(object, impulse) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.impulse += impulse;
+                }
+            },
+            PhysicsTask::AddLocalForce(object, force) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    let force = rb.orientation * force;
+                    rb.impulse += force * delta_time;
+                }
+            },
+            PhysicsTask::AddLocalImpulse(object, impulse) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.impulse += rb.orientation * impulse;
+                }
+            },
+            PhysicsTask::AddLocalImpulseTorque(object, impulse) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.torque_impulse += rb.orientation * impulse;
+                }
+            },
+            PhysicsTask::AddGlobalImpulseTorque(object, impulse) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.torque_impulse += impulse;
+                }
+            },
+            PhysicsTask::ShiftPos(object, delta) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.pos += delta;
+                }
+            },
+            PhysicsTask::CreateJoint(id, joint) => {
+                self.add_joint(id, joint);
+            },
+            PhysicsTask::DestroyJoint(id) => {
+                self.joints.remove(&id);
+            },
+            PhysicsTask::Spawn(object, body) => {
+                self.remove_body(&object);
+                self.add_body(object, *body);
+            },
+            PhysicsTask::Despawn(object) => {
+                self.remove_body(&object);
+            },
+            PhysicsTask::SetPos(object, pos) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.pos = pos;
+                    self.previous_poses.remove(&object);
+                }
+            },
+            PhysicsTask::SetVel(object, vel) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.vel = vel;
+                    rb.impulse = Vector3::new(0.0, 0.0, 0.0);
+                }
+            },
+            PhysicsTask::SetOrientation(object, orientation) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.orientation = orientation.normalize();
+                    self.previous_poses.remove(&object);
+                }
+            },
+            PhysicsTask::SetAngVel(object, ang_vel) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.ang_vel = ang_vel;
+                    rb.torque_impulse = Vector3::new(0.0, 0.0, 0.0);
+                }
+            },
+            PhysicsTask::SetMassProperties(object, mass, local_moi) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    if !rb.set_mass_properties(mass, local_moi) {
+                        eprintln!("Ignored mass {} and moment of inertia {:?} for {:?}", mass, local_moi, object);
+                    }
+                }
+            },
+            PhysicsTask::SetUpdater(object, updater) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.updater = updater;
+                }
+            },
+            PhysicsTask::SetColliders(object, colliders) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.colliders = colliders;
+                    self.manifolds.retain(|(o_i, o_j, _, _), _| *o_i != object && *o_j != object);
+                }
+            },
+            PhysicsTask::RequestState(object) => {
+                self.state_requests.push(object);
+            },
+            PhysicsTask::SetCollisionFilter(object, filter) => {
+                if let Some(rb) = self.rigid_bodies.get_mut(&object) {
+                    rb.collision_filter = filter;
+                }
+            },
+            PhysicsTask::IgnoreCollisions(a, b) => {
+                self.ignore_collisions(a, b);
+            },
+            PhysicsTask::RestoreCollisions(a, b) => {
+                self.restore_collisions(a, b);
+            },
+        }
+    }
+
+    /// Put each island to sleep once all of its bodies have been slow for SLEEP_TIME, and wake
+    /// the sleeping bodies of islands which are still moving. An island pushed by gravity, air or
+    /// a field only sleeps while it touches or is joined to a body which holds it against them,
+    /// since otherwise it is only slow because it has just started to fall. An island resting on
+    /// a kinematic body is measured against it, and rides along with it once asleep. Islands with
+    /// no awake body are left alone.
+    fn update_sleep(&mut self, delta_time: f64, pushed: &FxHashSet<Object>) {
+        let mut awake = self.rigid_bodies.iter()
+            .filter(|(_, body)| matches!(body.updater, Updater::Free) && !body.asleep)
+            .map(|(object, _)| *object)
+            .collect::<Vec<_>>();
+        if awake.is_empty() {
+            return;
+        }
+        awake.sort_unstable();
+
+        // The fixed and kinematic bodies which each free body touches or is joined to
+        let updater = |object: &Object| self.rigid_bodies.get(object).map(|body| &body.updater);
+        let supports = self.manifolds.keys().map(|(o_i, o_j, _, _)| (*o_i, *o_j))
+            .chain(self.joints.values().map(|joint| (joint.a, joint.b)))
+            .flat_map(|(a, b)| [(a, b), (b, a)])
+            .filter(|(a, b)| matches!(updater(a), Some(Updater::Free)) && !matches!(updater(b), Some(Updater::Free) | None))
+            .map(|(a, b)| (a, b, !matches!(updater(&b), Some(Updater::Fixed))))
+            .collect::<Vec<_>>();
+        for island in self.islands_from(&awake) {
+            let island_supports = supports.iter().filter(|(a, _, _)| island.binary_search(a).is_ok());
+            let carrier = island_supports.clone()
+                .filter(|(_, _, kinematic)| *kinematic)
+                .map(|(_, b, _)| *b)
+                .min()
+                .map(|object| {
+                    let rb = &self.rigid_bodies[&object];
+                    (object, rb.pos, rb.vel, rb.ang_vel)
+                });
+            // The sleeping bodies of the island were held when they fell asleep, and haven't moved
+            let held = island_supports.count() > 0 || island.iter().all(|object| {
+                let body = &self.rigid_bodies[object];
+                body.asleep || (!pushed.contains(object) && !body.feels_field())
+            });
+            for object in &island {
+                let body = self.rigid_bodies.get_mut(object).unwrap();
+                if body.asleep {
+                    continue;
+                }
+                let (vel, ang_vel) = match carrier {
+                    Some((_, pos, vel, ang_vel)) => (body.vel - vel - ang_vel.cross(body.pos - pos), body.ang_vel - ang_vel),
+                    None => (body.vel, body.ang_vel),
+                };
+                let slow = vel.magnitude() < SLEEP_LINEAR_SPEED && ang_vel.magnitude() < SLEEP_ANGULAR_SPEED;
+                body.sleep_time = if slow && body.can_sleep { body.sleep_time + delta_time } else { 0.0 };
+            }
+
+            let still = held && island.iter().all(|object| {
+                let body = &self.rigid_bodies[object];
+                body.asleep || body.sleep_time >= SLEEP_TIME
+            });
+            for object in &island {
+                let body = self.rigid_bodies.get_mut(object).unwrap();
+                if still {
+                    body.sleep();
+                    if let Some((carrier, pos, vel, ang_vel)) = carrier {
+                        body.carrier = Some(carrier);
+                        body.vel = vel + ang_vel.cross(body.pos - pos);
+                        body.ang_vel = ang_vel;
+                    }
+                } else if body.asleep {
+                    body.wake();
+                }
+            }
+        }
+    }
+
+    /// Move each sleeping body as its carrier moved this step, from the poses the carriers had
+    /// before, so that it stays where it rests on them
+    fn carry_sleepers(&mut self, carrier_poses: &FxHashMap<Object, (Vector3<f64>, Quaternion<f64>)>) {
+        let motions = carrier_poses.iter()
+            .filter_map(|(object, (pos, orientation))| {
+                let rb = self.rigid_bodies.get(object)?;
+                Some((*object, (*pos, rb.pos, rb.orientation * orientation.conjugate(), rb.vel, rb.ang_vel)))
+            })
+            .collect::<FxHashMap<_, _>>();
+        for body in self.rigid_bodies.values_mut() {
+            if let Some((old_pos, pos, turn, vel, ang_vel)) = body.carrier.and_then(|carrier| motions.get(&carrier).copied()) {
+                body.pos = pos + turn * (body.pos - old_pos);
+                body.orientation = (turn * body.orientation).normalize();
+                body.vel = vel + ang_vel.cross(body.pos - pos);
+                body.ang_vel = ang_vel;
+            }
+        }
+    }
+
+    fn wake_all(&mut self, objects: &[Object]) {
+        for object in objects {
+            if let Some(rb) = self.rigid_bodies.get_mut(object) {
+                rb.wake();
+            }
+        }
+    }
+
+    /// Whether either body can move, so that the constraints between them need solving. A
+    /// sleeping body moves with the kinematic body carrying it, so they need none either.
+    fn is_active_pair(&self, a: Object, b: Object) -> bool {
+        let active = |object: &Object| self.rigid_bodies.get(object).is_some_and(|body| body.is_active());
+        let rides = |object: &Object, other: Object| self.rigid_bodies.get(object).is_some_and(|body| body.carrier == Some(other));
+        (active(&a) || active(&b)) && !rides(&a, b) && !rides(&b, a)
+    }
+
+    /// Find the bodies inside each sensor, and report those which have entered or left since the
+    /// last step. Pairs which couldn't collide aren't reported, nor are sensors inside sensors.
+    /// Only pairs where something moved or was disturbed are looked at again.
+    fn update_sensors(&mut self, excluded: &FxHashSet<(Object, Object)>) {
+        let disturbed = std::mem::take(&mut self.disturbed);
+        let changed = |object: &Object, body: &RigidBody| body.is_active() || body.carrier.is_some() || disturbed.contains(object);
+        let sensors = self.rigid_bodies.iter().filter(|(_, body)| body.sensor).collect::<Vec<_>>();
+        if sensors.is_empty() && self.sensor_contents.is_empty() {
+            return;
+        }
+        let moved = self.rigid_bodies.iter().filter(|(object, body)| changed(object, body)).collect::<Vec<_>>();
+
+        // Keep what hasn't moved
+        let mut contents = self.sensor_contents.iter()
+            .filter(|(sensor, _, object)| {
+                match (self.rigid_bodies.get(sensor), self.rigid_bodies.get(object)) {
+                    (Some(rb_s), Some(body)) => !changed(sensor, rb_s) && !changed(object, body),
+                    _ => false,
+                }
+            })
+            .copied()
+            .collect::<FxHashSet<_>>();
+        // A sensor which moved is looked at against every body, and one which didn't against the
+        // bodies which did
+        let everything = if sensors.iter().any(|(sensor, rb_s)| changed(sensor, rb_s)) {
+            self.rigid_bodies.iter().collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        for (sensor, rb_s) in sensors {
+            let others = if changed(sensor, rb_s) { &everything } else { &moved };
+            for (object, body) in others {
+                let reach = rb_s.bounding_radius() + body.bounding_radius();
+                if body.sensor || body.colliders.is_empty() || (body.pos - rb_s.pos).magnitude() > reach
+                    || excluded.contains(&((*sensor).min(**object), (*sensor).max(**object)))
+                    || !rb_s.collision_filter.allows(&body.collision_filter) {
+                    continue;
+                }
+                for part in rb_s.overlapping_parts(body) {
+                    contents.insert((*sensor, part, **object));
+                }
+            }
+        }
+
+        let mut changes = contents.difference(&self.sensor_contents).map(|key| (*key, SensorEventKind::Enter))
+            .chain(self.sensor_contents.difference(&contents).map(|key| (*key, SensorEventKind::Exit)))
+            .collect::<Vec<_>>();
+        changes.sort_unstable_by_key(|(key, _)| *key);
+        self.sensor_events.extend(changes.into_iter()
+            .map(|((sensor, part, body), kind)| SensorEvent { sensor, part, body, kind }));
+        self.sensor_contents = contents;
+    }
+
+    /// Sequential impulses over every joint and contact manifold with a body which can move. Joints
+    /// and pairs are visited in a fixed order so that the result doesn't depend on the hash maps.
+    fn solve_constraints(&mut self, delta_time: f64) {
+        let mut joint_ids = self.joints.iter()
+            .filter(|(_, joint)| self.is_active_pair(joint.a, joint.b))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        joint_ids.sort_unstable();
+        let mut keys = self.manifolds.keys()
+            .filter(|key| self.is_active_pair(key.0, key.1))
+            .copied()
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        for id in &joint_ids {
+            let joint = self.joints.get_mut(id).unwrap();
+            with_pair(&mut self.rigid_bodies, joint.a, joint.b, |rb_a, rb_b| joint.prepare(rb_a, rb_b, delta_time));
+        }
+        for key in &keys {
+            let manifold = self.manifolds.get_mut(key).unwrap();
+            with_pair(&mut self.rigid_bodies, key.0, key.1, |rb_i, rb_j| manifold.prepare(rb_i, rb_j, delta_time));
+        }
+        for _ in 0..SOLVER_ITERATIONS {
+            for id in &joint_ids {
+                let joint = self.joints.get_mut(id).unwrap();
+                with_pair(&mut self.rigid_bodies, joint.a, joint.b, |rb_a, rb_b| joint.solve(rb_a, rb_b));
+            }
+            for key in &keys {
+                let manifold = self.manifolds.get_mut(key).unwrap();
+                with_pair(&mut self.rigid_bodies, key.0, key.1, |rb_i, rb_j| manifold.solve(rb_i, rb_j));
+            }
+        }
+    }
+}
+
+/// Borrow two different bodies mutably at once
+fn with_pair<F, R>(bodies: &mut FxHashMap<Object, RigidBody>, o_i: Object, o_j: Object, f: F) -> Option<R>
+where F: FnOnce(&mut RigidBody, &mut RigidBody) -> R {
+    // Take one body out of the map so that both can be borrowed mutably
+    let mut rb_i = bodies.remove(&o_i)?;
+    let result = bodies.get_mut(&o_j).map(|rb_j| f(&mut rb_i, rb_j));
+    bodies.insert(o_i, rb_i);
+    result
+}
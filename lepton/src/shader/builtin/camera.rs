@@ -27,7 +27,8 @@ impl Data for CameraData {
 /// An example shader, made for use with a camera.
 pub struct Camera {
     aspect: f32,
-    pos: Vector3<f32>,
+    /// World position, which is only made f32 once it is relative to the render origin
+    pos: Vector3<f64>,
     local_rot: Option<Matrix3<f32>>,
     theta: f32,
     phi: f32,
@@ -35,7 +36,7 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn new(graphics: &Graphics, pos: Vector3<f32>) -> Camera {
+    pub fn new(graphics: &Graphics, pos: Vector3<f64>) -> Camera {
         let input = Input::new_buffer::<CameraData>(graphics);
         Camera {
             aspect: graphics.swapchain_extent.width as f32 / graphics.swapchain_extent.height as f32,
@@ -47,19 +48,23 @@ impl Camera {
         }
     }
 
-    pub fn update_input(&mut self, buffer_index: usize) {
+    /// Rebase the render origin if the camera has moved far from it, then upload the view from
+    /// the camera's position relative to the origin
+    pub fn update_input(&mut self, graphics: &Graphics, buffer_index: usize) {
+        graphics.rebase(self.pos);
+        let pos = graphics.to_render_space(self.pos);
         let view = match self.local_rot {
             Some(rotation) => Matrix4::look_at_rh(
-                Point3::from_vec(self.pos),
-                Point3::from_vec(self.pos - rotation * Vector3::new(
+                Point3::from_vec(pos),
+                Point3::from_vec(pos - rotation * Vector3::new(
                     f32::sin(self.theta) * f32::cos(self.phi),
                     f32::sin(self.theta) * f32::sin(self.phi),
                     f32::cos(self.theta))),
                 rotation * UP,
             ),
             None => Matrix4::look_at_rh(
-                Point3::from_vec(self.pos),
-                Point3::from_vec(self.pos - Vector3::new(
+                Point3::from_vec(pos),
+                Point3::from_vec(pos - Vector3::new(
                     f32::sin(self.theta) * f32::cos(self.phi),
                     f32::sin(self.theta) * f32::sin(self.phi),
                     f32::cos(self.theta))),
@@ -73,20 +78,20 @@ impl Camera {
                 proj[1][1] *= -1.0;
                 proj
             },
-            camera_pos: Vector4::new(self.pos.x, self.pos.y, self.pos.z, 0.0),
+            camera_pos: Vector4::new(pos.x, pos.y, pos.z, 0.0),
         };
         self.input.update(data, buffer_index);
     }
 
-    pub fn adjust(&mut self, v: Vector3<f32>) {
+    pub fn adjust(&mut self, v: Vector3<f64>) {
         self.pos += v;
     }
 
-    pub fn set_pos(&mut self, pos: Vector3<f32>) {
+    pub fn set_pos(&mut self, pos: Vector3<f64>) {
         self.pos = pos;
     }
 
-    pub fn get_pos(&mut self) -> &Vector3<f32> {
+    pub fn get_pos(&mut self) -> &Vector3<f64> {
         &self.pos
     }
 
@@ -78,13 +78,14 @@ impl Lights {
         }
     }
 
+    /// Upload the lights relative to the render origin, so call this after the camera rebases it
     pub fn update_input(&mut self, graphics: &Graphics, buffer_index: usize) {
         let mut num_lights = 0;
         for (index, val) in self.object_indices.iter().enumerate() {
             if let Some(o) = val {
                 num_lights += 1;
                 let pos = match graphics.get_pos(o) {
-                    Some(p) => p,
+                    Some(p) => graphics.to_render_space(p),
                     None => continue,
                 };
                 self.light_pos[index] = Vector4::new(pos.x, pos.y, pos.z, 1.0);
@@ -18,6 +18,8 @@ const AMPLITUDE: [f64; NUM_OCTAVES as usize] = [
     2.0, 1.5/2.0, 1.5/4.0, 1.0/8.0, 1.0/16.0, 1.0/32.0, 1.0/64.0
 ];
 pub(super) const SCALE_TO_HEIGHT_RATIO: f64 = 1.2;
+/// Density of air at one atmosphere of pressure
+const AIR_DENSITY: f64 = 1.2;
 
 enum LoadState {
     High(Model),
@@ -53,7 +55,7 @@ pub(super) struct Planet {
 
 impl Planet {
     pub(super) fn new(settings: PlanetSettings, object: Object) -> Self {
-        let atmosphere = Atmosphere::new(1.0, 20.0);
+        let atmosphere = Atmosphere::earthlike();
 
         let mut models = Vec::with_capacity((settings.face_subdivision * settings.face_subdivision) as usize * 6);
         for face in 0..6 {
@@ -83,7 +85,7 @@ impl Planet {
     }
 
     pub fn update(&mut self, graphics: &Graphics, shader: &Shader<builtin::LPSignature>,
-        threadpool: &ThreadPool, position: &Vector3<f32>) {
+        threadpool: &ThreadPool, position: &Vector3<f64>) {
 
         // Manage switches
         for switch in self.model_switches.iter_mut() {
@@ -101,13 +103,13 @@ impl Planet {
 
         // Get current position
         let (planet_pos, planet_rot) = match graphics.get_pos_and_rot(&self.object) {
-            Some(data) => data,
+            Some((pos, rot)) => (pos, rot.cast::<f64>().unwrap()),
             None => (Vector3::zero(), Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0)),
         };
-        let my_id = square::get_id((planet_rot.transpose() * (position - planet_pos)).cast().unwrap(), self.settings.face_subdivision);
+        let my_id = square::get_id(planet_rot.transpose() * (position - planet_pos), self.settings.face_subdivision);
 
         // Get load configuration
-        let height_ratio = (position - planet_pos).magnitude() / self.settings.radius as f32;
+        let height_ratio = ((position - planet_pos).magnitude() / self.settings.radius) as f32;
         let mut config_iter = self.load_configs.iter().enumerate();
         let (my_config_index, my_load_config) = loop {
             match config_iter.next() {
@@ -197,6 +199,10 @@ impl Planet {
 }
 
 impl Atmosphere {
+    pub(super) fn earthlike() -> Self {
+        Self::new(1.0, 20.0)
+    }
+
     fn new(base_pressure: f32, scale_height: f32) -> Self {
         Self {
             base_pressure,
@@ -208,4 +214,9 @@ impl Atmosphere {
     fn get_min_alpha(base_pressure: f32) -> f32 {
         base_pressure
     }
+
+    /// The air which the physics engine pushes ships with
+    pub(super) fn air(&self) -> lepton::physics::Atmosphere {
+        lepton::physics::Atmosphere::new(self.base_pressure as f64 * AIR_DENSITY, self.scale_height as f64)
+    }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 use cgmath::{Vector3, Quaternion, Zero};
 use rand::{SeedableRng, rngs::SmallRng};
 use lepton::prelude::*;
+use lepton::physics::{ColliderPart, RadialFunction};
 use rustc_hash::FxHashMap;
 use crate::threadpool::ThreadPool;
 use noise::{OpenSimplex, Seedable};
@@ -117,12 +118,18 @@ impl SolarSystem {
         }
     }
 
-    fn load_rigid_body(_rng: &SmallRng, system_mass: f64, settings: PlanetSettings, time: f32) -> RigidBody {
+    /// Height above the planet's surface, for its collider
+    fn radial_function(settings: PlanetSettings) -> RadialFunction {
         let noise_map = settings.noise_map.clone();
         let radius = settings.radius;
         let spikiness = settings.spikiness;
         let scale = settings.height * SCALE_TO_HEIGHT_RATIO;
+        Box::new(move |pos: Vector3<f64>| {
+            Planet::value_fn(pos / radius, noise_map, spikiness, scale) * radius
+        })
+    }
 
+    fn load_rigid_body(_rng: &SmallRng, system_mass: f64, settings: PlanetSettings, time: f32) -> RigidBody {
         let initial_pos = if settings.is_star {
             Vector3::new(0.0, 0.0, 0.0)
         } else {
@@ -136,9 +143,7 @@ impl SolarSystem {
             Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0)
         )
         .collide(vec![
-            Collider::planet(Box::new(move |pos| {
-                Planet::value_fn(pos / radius, noise_map, spikiness, scale) * radius
-            }), (1.0 + settings.height) * settings.radius)
+            Collider::planet(Self::radial_function(settings), (1.0 + settings.height) * settings.radius)
         ], 0.3);
 
         if settings.is_star {
@@ -148,6 +153,7 @@ impl SolarSystem {
         }
 
         if !settings.is_star {
+            rb = rb.atmosphere(Atmosphere::earthlike().air());
             rb = rb.circ_orbit(crate::G * system_mass, Vector3::zero(), Vector3::new(0.0, 0.0, 1.0));
         }
 
@@ -164,7 +170,7 @@ impl SolarSystem {
     }
 
     /// Update which planets are loaded and which aren't.
-    pub fn update(&mut self, graphics: &Graphics, low_poly_shader: &Shader<builtin::LPSignature>, threadpool: &ThreadPool, player_pos: &Vector3<f32>) {
+    pub fn update(&mut self, graphics: &Graphics, low_poly_shader: &Shader<builtin::LPSignature>, threadpool: &ThreadPool, player_pos: &Vector3<f64>) {
         for (i, planet) in self.loaded_planets.iter_mut().enumerate() {
             match planet {
                 None => {
@@ -214,4 +220,12 @@ impl SolarSystem {
         // For now, only return the first object
         self.objects[0]
     }
-}
\ No newline at end of file
+}
+
+/// Rebuilds the planets' colliders when a physics recording of the system is replayed
+impl Resolver for SolarSystem {
+    fn radial(&mut self, object: Object, _part: ColliderPart) -> Option<RadialFunction> {
+        let index = self.objects.iter().position(|o| *o == object)?;
+        Some(Self::radial_function(self.settings[index]))
+    }
+}
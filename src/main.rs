@@ -11,6 +11,7 @@ mod threadpool;
 use astro::skybox::Skybox;
 use astro::system::SolarSystem;
 use lepton::prelude::*;
+use lepton::physics::Recording;
 use cgmath::{prelude::*, Vector3, Matrix3, Quaternion};
 use rustc_hash::FxHashMap;
 use threadpool::ThreadPool;
@@ -24,6 +25,7 @@ const LOOK_SENSITIVITY: f32 = 0.1;
 const NUM_SHADERS: usize = 256;
 const MOVE_SENSITIVITY: f32 = 100.0;
 pub const G: f64 = 1.5e1;
+const SYSTEM_SEED: [u8; 32] = [0; 32];
 
 struct Starfarer {
     low_poly_shader: Shader<builtin::LPSignature>,
@@ -59,7 +61,7 @@ impl Starfarer {
         let mut object_manager = ObjectManager::new();
         let mut ship_loader = ShipLoader::new();
 
-        let solar_system = SolarSystem::new([0;32], &mut object_manager, 0.0);
+        let solar_system = SolarSystem::new(SYSTEM_SEED, &mut object_manager, 0.0);
 
         let ships = vec![
             ships::Ship::new(&mut object_manager, &mut ship_loader, ships::compiled::enterprise::KESTREL),
@@ -117,12 +119,13 @@ impl Starfarer {
         ship.poll_tasks(tasks);
     }
 
-    fn update_other(&mut self, graphics: &Graphics, _delta_time: f32) {
-        // Get sky settings
+    /// Point the sky at the planet and sun. This follows the camera, since the positions are
+    /// relative to the render origin.
+    fn update_sky(&mut self, graphics: &Graphics) {
         if let Some((planet, sun, atmosphere, radius)) = self.solar_system.get_skybox_data() {
             self.skybox.reset_push_constants(
-                graphics.get_pos(&planet),
-                graphics.get_pos(&sun),
+                graphics.get_pos(&planet).map(|p| graphics.to_render_space(p)),
+                graphics.get_pos(&sun).map(|p| graphics.to_render_space(p)),
                 atmosphere,
                 radius,
             );
@@ -164,18 +167,6 @@ impl InputReceiver for Starfarer {
 }
 
 impl Renderer for Starfarer {
-    fn interaction(tasks: &mut Vec<PhysicsTask>, (o_i, rb_i): (&Object, &RigidBody), (o_j, rb_j): (&Object, &RigidBody)) {
-        if rb_i.mass > 500_000.0 {
-            let dist = rb_j.pos - rb_i.pos;
-            let force = -G * rb_i.mass * rb_j.mass / dist.magnitude2() * dist.normalize();
-            tasks.push(PhysicsTask::AddGlobalForce(*o_j, force));
-        } else if rb_j.mass > 500_000.0 {
-            let dist = rb_i.pos - rb_j.pos;
-            let force = -G * rb_i.mass * rb_j.mass / dist.magnitude2() * dist.normalize();
-            tasks.push(PhysicsTask::AddGlobalForce(*o_i, force));
-        }
-    }
-
     fn load_models(&mut self, graphics: &Graphics) -> FxHashMap<Object, Vec<DrawState>> {
         let mut map = FxHashMap::default();
         for ship in self.ships.iter_mut() {
@@ -196,13 +187,14 @@ impl Renderer for Starfarer {
             // (Vector3::new(9_002.0, -2.0, 0.001), Vector3::new(0.0, 4.0 - circ_vel, 0.0), Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0)),
             // (Vector3::new(9_032.0, 0.0, 0.0), Vector3::new(10.0, 0.0, 0.0), Quaternion::new(1.0, 0.71, -0.02, 0.3), Vector3::new(1.0, 1.0, 0.0)),
         ];
-        for (ship, (pos, vel, orientation, ang_vel)) in self.ships.iter().zip(initial_values.into_iter()) {
+        for (ship, (pos, vel, orientation, ang_vel)) in self.ships.iter_mut().zip(initial_values.into_iter()) {
             map.insert(ship.object, ship.get_rigid_body(&mut self.ship_loader, pos, vel, orientation, ang_vel));
         }
         map.insert(self.player, RigidBody::new(
             Vector3::new(2.0, 0.0, 1.0), Vector3::new(0.0, 0.0, 0.0),
             Quaternion::new(1.0, 0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0))
-            .motivate(65.0, Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0)));
+            .motivate(65.0, Matrix3::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0))
+            .attracted());
         self.solar_system.init_rigid_body(&mut map);
         map
     }
@@ -225,8 +217,6 @@ impl Renderer for Starfarer {
         self.solar_system.update(graphics, &self.low_poly_shader, &self.threadpool, self.camera.get_pos());
         self.fps_menu.data.update(delta_time, &mut self.fps_menu.elements);
 
-        self.update_other(graphics, delta_time);
-
         tasks
     }
     
@@ -237,14 +227,15 @@ impl Renderer for Starfarer {
         // Update inputs
         if let Some(i) = self.control_ship {
             if let Some(data) = graphics.get_pos_and_rot(&self.ships[i].object) {
-                self.camera.set_pos(data.0 + data.1 * self.ships[i].seat_pos);
+                self.camera.set_pos(data.0 + (data.1 * self.ships[i].seat_pos).cast::<f64>().unwrap());
                 self.camera.set_local_rot(data.1);
             }
         } else if let Some(p) = graphics.get_pos(&self.player) {
             self.camera.set_pos(p);
         }
-        self.camera.update_input(buffer_index);
+        self.camera.update_input(graphics, buffer_index);
         self.lights.update_input(graphics, buffer_index);
+        self.update_sky(graphics);
 
         let mut tasks = vec![
             RenderTask::LoadShader(&self.skybox.skybox_shader),
@@ -273,8 +264,52 @@ impl Renderer for Starfarer {
     }
 }
 
+/// Replay a physics recording without opening a window, and report where it first diverged
+fn replay(path: &str) {
+    let recording = match Recording::load(path) {
+        Ok(recording) => recording,
+        Err(e) => {
+            eprintln!("Could not load the recording {}: {}", path, e);
+            std::process::exit(1);
+        },
+    };
+    // The system takes the first objects, as it does in the game, so its planets match the recording
+    let mut solar_system = SolarSystem::new(SYSTEM_SEED, &mut ObjectManager::new(), 0.0);
+    let report = match recording.replay(&mut solar_system, Starfarer::interaction) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Could not replay {}: {}", path, e);
+            std::process::exit(1);
+        },
+    };
+    println!("Replayed {} frames", report.frames);
+    match report.divergence {
+        Some(divergence) => {
+            println!("First diverged at frame {} on {:?}", divergence.frame, divergence.object);
+            println!("    recorded: {:?}", divergence.recorded);
+            println!("    replayed: {:?}", divergence.replayed);
+        },
+        None => println!("No divergence"),
+    }
+    println!("Furthest any body strayed: {}", report.max_pos_error);
+}
+
 fn main() {
+    // Run with --replay <file> to replay a recording made with STARFARER_RECORD_PHYSICS
+    let args = std::env::args().collect::<Vec<_>>();
+    if let [_, flag, path] = args.as_slice() {
+        if flag == "--replay" {
+            replay(path);
+            return;
+        }
+    }
+
     let mut backend = Backend::new();
+    backend.set_gravity(Gravity::new(G));
+    // Set STARFARER_RECORD_PHYSICS to a file to record the physics for replaying a bug
+    if let Ok(path) = std::env::var("STARFARER_RECORD_PHYSICS") {
+        backend.record_physics(path);
+    }
     let mut graphics = Graphics::new(&mut backend, WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT, true,
         vec![InputType::Camera, InputType::Lights, InputType::UI], NUM_SHADERS);
     
@@ -73,6 +73,21 @@ impl ShipLoader {
         output
     }
 
+    /// A collider for an attached part, made from the vertices of its model
+    pub(super) fn load_part_collider(&self, part_id: PartID) -> Option<Collider> {
+        let part_data = self.load_part_data(part_id);
+        let vertices = match self.load_model_data(part_id).remove(&part_data.object_name)? {
+            Mesh::BumpedMesh(vertices, ..) | Mesh::ModelMesh(vertices, _) => vertices,
+            Mesh::ColliderMesh(..) => return None,
+        };
+        if vertices.is_empty() {
+            return None;
+        }
+        Some(Collider::polyhedron(vertices.into_iter().map(
+            |v| Vector3::new(v.pos[0] as f64, v.pos[1] as f64, v.pos[2] as f64)
+        ).collect()))
+    }
+
     /// Loads the Mesh model data into a hash map, indexed by path?
     fn load_model_data(&self, part_id: PartID) -> HashMap<String, Mesh> {
         // Look for part id in the paths dict
@@ -5,7 +5,8 @@ mod loader;
 mod bytecode;
 mod part;
 use lepton::prelude::*;
-use cgmath::{Vector3, Quaternion, InnerSpace};
+use lepton::physics::ColliderPart;
+use cgmath::{Vector3, Quaternion, Matrix4, InnerSpace};
 
 use part::*;
 use primitives::*;
@@ -17,6 +18,7 @@ pub struct Ship {
     id: PartID,
     pub seat_pos: Vector3<f32>,
     attachments: Vec<PartState>,
+    attachment_colliders: AttachmentColliders,
 
     // For runtime
     tasks: Vec<PhysicsTask>,
@@ -36,6 +38,7 @@ impl Ship {
             id,
             seat_pos: data.seat_pos,
             attachments,
+            attachment_colliders: AttachmentColliders::default(),
 
             tasks: Vec::new(),
         }
@@ -72,12 +75,26 @@ impl Ship {
         output
     }
 
-    pub fn get_rigid_body(&self, ship_loader: &mut ShipLoader, pos: Vector3<f64>, vel: Vector3<f64>, orientation: Quaternion<f64>, ang_vel: Vector3<f64>) -> RigidBody {
+    pub fn get_rigid_body(&mut self, ship_loader: &mut ShipLoader, pos: Vector3<f64>, vel: Vector3<f64>, orientation: Quaternion<f64>, ang_vel: Vector3<f64>) -> RigidBody {
         let data = ship_loader.load_ship_data(self.id);
+        let mut colliders = ship_loader.load_colliders(data.id);
+        let parts = self.attachments.iter()
+            .map(|attachment| (attachment.matrix.cast().unwrap(), ship_loader.load_part_collider(attachment.id)))
+            .collect::<Vec<_>>();
+        self.attachment_colliders = AttachmentColliders::build(&mut colliders, parts);
         RigidBody::new(pos, vel, orientation, ang_vel)
             .motivate(data.mass, data.moment_of_inertia)
             .offset(-data.center_of_mass)
-            .collide(ship_loader.load_colliders(data.id), data.elasticity)
+            .collide(colliders, data.elasticity)
+            .attracted()
+            .aerodynamics(Aerodynamics::new(data.drag_coefficient, data.reference_area)
+                .lift(data.lift_coefficient, Vector3::unit_z()))
+    }
+
+    /// The index of the attachment which a hit on part of the ship's body landed on, or None if
+    /// it landed on the hull
+    pub fn hit_attachment(&self, part: ColliderPart) -> Option<usize> {
+        self.attachment_colliders.attachment(part)
     }
 
     pub fn continuous_commands(&mut self, delta_time: f32, key_tracker: &KeyTracker) {
@@ -109,3 +126,41 @@ impl Ship {
         tasks.append(&mut self.tasks);
     }
 }
+
+/// Where the attachments' colliders went in a ship's body. Attachments without a collider are
+/// left out of the compound, so the attachment of each child is kept.
+#[derive(Default)]
+pub(crate) struct AttachmentColliders {
+    /// Index of the compound among the body's colliders, if any attachment has a collider
+    collider: Option<usize>,
+    /// The attachment of each child of the compound
+    attachments: Vec<usize>,
+}
+
+impl AttachmentColliders {
+    /// Add the compound of the attachments which have colliders to the body's colliders. Each
+    /// attachment is given with its placement on the ship.
+    pub(crate) fn build(colliders: &mut Vec<Collider>, parts: Vec<(Matrix4<f64>, Option<Collider>)>) -> Self {
+        let mut children = Vec::new();
+        let mut attachments = Vec::new();
+        for (i, (matrix, collider)) in parts.into_iter().enumerate() {
+            if let Some(collider) = collider {
+                children.push((matrix, collider));
+                attachments.push(i);
+            }
+        }
+        if children.is_empty() {
+            return Self::default();
+        }
+        colliders.push(Collider::compound(children));
+        Self { collider: Some(colliders.len() - 1), attachments }
+    }
+
+    /// The attachment which a hit on part of the body landed on, or None for the hull
+    pub(crate) fn attachment(&self, part: ColliderPart) -> Option<usize> {
+        if Some(part.collider) != self.collider {
+            return None;
+        }
+        self.attachments.get(part.child?).copied()
+    }
+}
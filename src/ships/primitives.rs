@@ -88,6 +88,11 @@ pub struct ShipData {
     #[serde(with = "FakeVector")]
     pub seat_pos: Vector3<f32>,
     pub elasticity: f64,
+    pub drag_coefficient: f64,
+    /// Area which the drag and lift coefficients are measured against
+    pub reference_area: f64,
+    /// Lift of the hull like a flat plate facing up the ship's z axis
+    pub lift_coefficient: f64,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -5,4 +5,26 @@ fn cross_test() {
     let res = Vector3::new(1.0, 0.0, 0.0).cross(Vector3::new(0.0, 1.0, 0.0));
     println!("{:?}", res);
     assert_eq!(res.dot(Vector3::new(0.0, 0.0, 1.0)) > 0.0, true);
-}
\ No newline at end of file
+}
+#[test]
+fn hits_land_on_the_attachment_past_one_without_a_collider() {
+    use cgmath::Matrix4;
+    use lepton::prelude::*;
+    use crate::ships::AttachmentColliders;
+
+    // The first attachment has no collider, so the second is the compound's first child
+    let mut colliders = vec![Collider::cube(1.0)];
+    let parts = vec![
+        (Matrix4::from_translation(Vector3::new(0.0, 3.0, 0.0)), None),
+        (Matrix4::from_translation(Vector3::new(3.0, 0.0, 0.0)), Some(Collider::cube(0.5))),
+    ];
+    let attachment_colliders = AttachmentColliders::build(&mut colliders, parts);
+    let mut world = PhysicsWorld::new();
+    let object = ObjectManager::new().get_object();
+    world.add_body(object, RigidBody::by_pos(Vector3::new(0.0, 0.0, 0.0)).collide(colliders, 0.0));
+
+    let hit = world.raycast(Vector3::new(10.0, 0.0, 0.0), -Vector3::unit_x(), 100.0, &[]).unwrap();
+    assert_eq!(attachment_colliders.attachment(hit.part), Some(1));
+    let hull = world.raycast(Vector3::new(0.0, 0.0, 10.0), -Vector3::unit_z(), 100.0, &[]).unwrap();
+    assert_eq!(attachment_colliders.attachment(hull.part), None);
+}